@@ -0,0 +1,22 @@
+use std::env;
+use std::path::PathBuf;
+
+fn main() {
+	let crate_dir = PathBuf::from(env::var("CARGO_MANIFEST_DIR").unwrap());
+	let out_dir = PathBuf::from(env::var("OUT_DIR").unwrap());
+	// The header is generated in OUT_DIR so that building never touches the source tree.
+	// tests/c_abi.rs checks that the copy shipped in include/ is up to date.
+	let include_dir = out_dir.join("include");
+	let config = cbindgen::Config::from_file(crate_dir.join("cbindgen.toml")).expect("Invalid cbindgen.toml");
+
+	println!("cargo:rerun-if-changed=src/lib.rs");
+	println!("cargo:rerun-if-changed=cbindgen.toml");
+
+	std::fs::create_dir_all(&include_dir).expect("Unable to create the include dir");
+	cbindgen::Builder::new()
+		.with_crate(&crate_dir)
+		.with_config(config)
+		.generate()
+		.expect("Unable to generate the C header")
+		.write_to_file(include_dir.join("rrt.h"));
+}
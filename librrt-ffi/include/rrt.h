@@ -0,0 +1,169 @@
+/* Registrar Remark Token - C bindings for librrt */
+
+#ifndef RRT_H
+#define RRT_H
+
+/* This file is generated by cbindgen from librrt-ffi/src/lib.rs. Do not edit. */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+// Status codes returned by the `rrt_*` functions.
+// Those codes are part of the ABI and must not be renumbered.
+typedef enum RrtStatus {
+  RRT_STATUS_OK = 0,
+  // A required pointer argument was NULL
+  RRT_STATUS_NULL_POINTER = 1,
+  // A string argument was not valid UTF-8
+  RRT_STATUS_INVALID_UTF8 = 2,
+  // The token version is not supported
+  RRT_STATUS_UNSUPPORTED_VERSION = 3,
+  // The network is not supported
+  RRT_STATUS_UNKNOWN_NETWORK = 4,
+  // The channel is not supported
+  RRT_STATUS_UNKNOWN_CHANNEL = 5,
+  // The token does not have the length expected for its version
+  RRT_STATUS_INVALID_LENGTH = 6,
+  // A field of the token is not properly encoded
+  RRT_STATUS_INVALID_ENCODING = 7,
+  // The checksum of the token does not match its content
+  RRT_STATUS_INVALID_CHECKSUM = 8,
+  // The output buffer cannot hold the result and its NUL terminator
+  RRT_STATUS_BUFFER_TOO_SMALL = 9,
+  // The checksum of this token version covers an account, use `rrt_check_for_account`
+  RRT_STATUS_ACCOUNT_REQUIRED = 10,
+  // The account is neither a valid SS58 address nor a hex public key
+  RRT_STATUS_INVALID_ACCOUNT = 11,
+  // No account is associated with the case of the token
+  RRT_STATUS_UNKNOWN_CASE = 12,
+  // The token was remarked by another account than the one of its case
+  RRT_STATUS_WRONG_SIGNER = 13,
+  // The locale is not available or its file is invalid
+  RRT_STATUS_INVALID_LOCALE = 14,
+  // The case id is too large for this token version
+  RRT_STATUS_CASE_ID_OVERFLOW = 15,
+  // The registry file is invalid
+  RRT_STATUS_INVALID_REGISTRY = 16,
+  // The token targets a registrar that is not in the registry
+  RRT_STATUS_UNKNOWN_REGISTRAR = 17,
+  // The token was revoked
+  RRT_STATUS_REVOKED = 18,
+  // The revocation list is invalid
+  RRT_STATUS_INVALID_REVOCATION_LIST = 19,
+  // The signature is invalid or was not made by a trusted key
+  RRT_STATUS_INVALID_SIGNATURE = 20,
+  // The signing key is invalid
+  RRT_STATUS_INVALID_KEY = 21,
+  // The envelope of the token is malformed or its checksum is wrong
+  RRT_STATUS_INVALID_ENVELOPE = 22,
+  // The secret does not fit in the tokens of the version
+  RRT_STATUS_INVALID_SECRET = 23,
+  // librrt panicked. This is a bug, please report it.
+  RRT_STATUS_PANIC = 255,
+} RrtStatus;
+
+// Opaque handle on a parsed or generated token.
+typedef struct RrtToken RrtToken;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+// Returns a static, NUL terminated, description of `status`.
+// The status is taken as an int so that any value is safe, unknown ones get a generic description.
+const char *rrt_status_message(int status);
+
+// Parses and checks the token string `s`. On success, `*out` receives a
+// handle that must be released with `rrt_free`.
+//
+// # Safety
+// `s` must be a valid NUL terminated string and `out` a valid pointer.
+enum RrtStatus rrt_parse(const char *s, struct RrtToken **out);
+
+// Checks the token string `s` without keeping the parsed token around.
+//
+// # Safety
+// `s` must be a valid NUL terminated string.
+enum RrtStatus rrt_check(const char *s);
+
+// Checks the token string `s` issued for `account`, given as an SS58 address
+// or a `0x` prefixed hex public key. Needed for the tokens bound to an account such as V02.
+//
+// # Safety
+// `s` and `account` must be valid NUL terminated strings.
+enum RrtStatus rrt_check_for_account(const char *s, const char *account);
+
+// Generates a new token with a random secret. `channel` is one of `TW`, `EM`, `MX`,
+// any other channel fails with `RRT_STATUS_UNKNOWN_CHANNEL`. On success, `*out` receives a handle that must be released with `rrt_free`.
+//
+// # Safety
+// `channel` must be a valid NUL terminated string and `out` a valid pointer.
+enum RrtStatus rrt_generate(uint8_t app,
+                            uint8_t version,
+                            uint8_t network,
+                            uint8_t index,
+                            uint64_t case_id,
+                            const char *channel,
+                            struct RrtToken **out);
+
+// Writes the token into `buf` as a NUL terminated string, fields being
+// separated by `sep`. `sep` may be NULL for the compact form.
+//
+// # Safety
+// `token` must come from `rrt_parse` or `rrt_generate`, `sep` must be NULL or
+// a valid NUL terminated string and `buf` must point to at least `len` bytes.
+enum RrtStatus rrt_format(const struct RrtToken *token, const char *sep, char *buf, size_t len);
+
+// Returns the version of the token or 255 if `token` is NULL.
+//
+// # Safety
+// `token` must be NULL or come from `rrt_parse` or `rrt_generate`.
+uint8_t rrt_token_version(const struct RrtToken *token);
+
+// Returns the network of the token or 255 if `token` is NULL.
+//
+// # Safety
+// `token` must be NULL or come from `rrt_parse` or `rrt_generate`.
+uint8_t rrt_token_network(const struct RrtToken *token);
+
+// Returns the registrar index of the token or 255 if `token` is NULL.
+//
+// # Safety
+// `token` must be NULL or come from `rrt_parse` or `rrt_generate`.
+uint8_t rrt_token_index(const struct RrtToken *token);
+
+// Returns the case id of the token or `UINT64_MAX` if `token` is NULL.
+//
+// # Safety
+// `token` must be NULL or come from `rrt_parse` or `rrt_generate`.
+uint64_t rrt_token_case_id(const struct RrtToken *token);
+
+// Writes the 2 chars channel code of the token into `buf`.
+//
+// # Safety
+// `token` must come from `rrt_parse` or `rrt_generate` and `buf` must point
+// to at least `len` bytes.
+enum RrtStatus rrt_token_channel(const struct RrtToken *token, char *buf, size_t len);
+
+// Writes the secret of the token into `buf`.
+//
+// # Safety
+// `token` must come from `rrt_parse` or `rrt_generate` and `buf` must point
+// to at least `len` bytes.
+enum RrtStatus rrt_token_secret(const struct RrtToken *token, char *buf, size_t len);
+
+// Releases a token handle. Passing NULL is a no-op.
+//
+// # Safety
+// `token` must be NULL or come from `rrt_parse` or `rrt_generate` and must
+// not be used afterwards.
+void rrt_free(struct RrtToken *token);
+
+#ifdef __cplusplus
+} // extern "C"
+#endif // __cplusplus
+
+#endif /* RRT_H */
@@ -0,0 +1,541 @@
+//! C ABI for librrt.
+//!
+//! Tokens are handed out as an opaque [RrtToken] handle that must be released
+//! with [rrt_free]. Every fallible function returns an [RrtStatus] and never
+//! unwinds across the FFI boundary: a panic in librrt is reported as
+//! [RrtStatus::Panic].
+//!
+//! The C header is generated by cbindgen at build time and shipped in `include/rrt.h`.
+
+use librrt::*;
+use std::convert::TryFrom;
+use std::ffi::CStr;
+use std::os::raw::{c_char, c_int};
+use std::panic::{self, UnwindSafe};
+use std::ptr;
+
+/// Opaque handle on a parsed or generated token.
+pub struct RrtToken(Token);
+
+/// Status codes returned by the `rrt_*` functions.
+/// Those codes are part of the ABI and must not be renumbered.
+#[repr(C)]
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum RrtStatus {
+	Ok = 0,
+
+	/// A required pointer argument was NULL
+	NullPointer = 1,
+
+	/// A string argument was not valid UTF-8
+	InvalidUtf8 = 2,
+
+	/// The token version is not supported
+	UnsupportedVersion = 3,
+
+	/// The network is not supported
+	UnknownNetwork = 4,
+
+	/// The channel is not supported
+	UnknownChannel = 5,
+
+	/// The token does not have the length expected for its version
+	InvalidLength = 6,
+
+	/// A field of the token is not properly encoded
+	InvalidEncoding = 7,
+
+	/// The checksum of the token does not match its content
+	InvalidChecksum = 8,
+
+	/// The output buffer cannot hold the result and its NUL terminator
+	BufferTooSmall = 9,
+
+	/// The checksum of this token version covers an account, use `rrt_check_for_account`
+	AccountRequired = 10,
+
+	/// The account is neither a valid SS58 address nor a hex public key
+	InvalidAccount = 11,
+
+	/// No account is associated with the case of the token
+	UnknownCase = 12,
+
+	/// The token was remarked by another account than the one of its case
+	WrongSigner = 13,
+
+	/// The locale is not available or its file is invalid
+	InvalidLocale = 14,
+
+	/// The case id is too large for this token version
+	CaseIdOverflow = 15,
+
+	/// The registry file is invalid
+	InvalidRegistry = 16,
+
+	/// The token targets a registrar that is not in the registry
+	UnknownRegistrar = 17,
+
+	/// The token was revoked
+	Revoked = 18,
+
+	/// The revocation list is invalid
+	InvalidRevocationList = 19,
+
+	/// The signature is invalid or was not made by a trusted key
+	InvalidSignature = 20,
+
+	/// The signing key is invalid
+	InvalidKey = 21,
+
+	/// The envelope of the token is malformed or its checksum is wrong
+	InvalidEnvelope = 22,
+
+	/// The secret does not fit in the tokens of the version
+	InvalidSecret = 23,
+
+	/// librrt panicked. This is a bug, please report it.
+	Panic = 255,
+}
+
+impl From<&Error> for RrtStatus {
+	fn from(e: &Error) -> Self {
+		match e {
+			Error::Version(_) => RrtStatus::UnsupportedVersion,
+			Error::UnknownNetwork(_) => RrtStatus::UnknownNetwork,
+			Error::UnknownChannel(_) => RrtStatus::UnknownChannel,
+			Error::LengthError { .. } => RrtStatus::InvalidLength,
+			Error::InvalidEncoding(_) | Error::InvalidField { .. } => RrtStatus::InvalidEncoding,
+			Error::ChecksumError(_) => RrtStatus::InvalidChecksum,
+			Error::AccountRequired => RrtStatus::AccountRequired,
+			Error::InvalidAccount(_) => RrtStatus::InvalidAccount,
+			Error::UnknownCase(_) => RrtStatus::UnknownCase,
+			Error::WrongSigner { .. } => RrtStatus::WrongSigner,
+			Error::InvalidLocale(_) => RrtStatus::InvalidLocale,
+			Error::CaseIdOverflow { .. } => RrtStatus::CaseIdOverflow,
+			Error::InvalidRegistry(_) => RrtStatus::InvalidRegistry,
+			Error::UnknownRegistrar { .. } => RrtStatus::UnknownRegistrar,
+			Error::Revoked(_) => RrtStatus::Revoked,
+			Error::InvalidRevocationList(_) => RrtStatus::InvalidRevocationList,
+			Error::InvalidSignature => RrtStatus::InvalidSignature,
+			Error::InvalidKey(_) => RrtStatus::InvalidKey,
+			Error::InvalidEnvelope(_) => RrtStatus::InvalidEnvelope,
+			Error::InvalidSecret { .. } => RrtStatus::InvalidSecret,
+		}
+	}
+}
+
+impl RrtStatus {
+	/// Every status, to map the codes received from C back to a status
+	const ALL: [RrtStatus; 25] = [
+		RrtStatus::Ok,
+		RrtStatus::NullPointer,
+		RrtStatus::InvalidUtf8,
+		RrtStatus::UnsupportedVersion,
+		RrtStatus::UnknownNetwork,
+		RrtStatus::UnknownChannel,
+		RrtStatus::InvalidLength,
+		RrtStatus::InvalidEncoding,
+		RrtStatus::InvalidChecksum,
+		RrtStatus::BufferTooSmall,
+		RrtStatus::AccountRequired,
+		RrtStatus::InvalidAccount,
+		RrtStatus::UnknownCase,
+		RrtStatus::WrongSigner,
+		RrtStatus::InvalidLocale,
+		RrtStatus::CaseIdOverflow,
+		RrtStatus::InvalidRegistry,
+		RrtStatus::UnknownRegistrar,
+		RrtStatus::Revoked,
+		RrtStatus::InvalidRevocationList,
+		RrtStatus::InvalidSignature,
+		RrtStatus::InvalidKey,
+		RrtStatus::InvalidEnvelope,
+		RrtStatus::InvalidSecret,
+		RrtStatus::Panic,
+	];
+
+	fn message(&self) -> &'static [u8] {
+		match self {
+			RrtStatus::Ok => b"ok\0",
+			RrtStatus::NullPointer => b"null pointer\0",
+			RrtStatus::InvalidUtf8 => b"invalid utf-8 string\0",
+			RrtStatus::UnsupportedVersion => b"unsupported version\0",
+			RrtStatus::UnknownNetwork => b"unknown network\0",
+			RrtStatus::UnknownChannel => b"unknown channel\0",
+			RrtStatus::InvalidLength => b"invalid length\0",
+			RrtStatus::InvalidEncoding => b"invalid encoding\0",
+			RrtStatus::InvalidChecksum => b"invalid checksum\0",
+			RrtStatus::BufferTooSmall => b"buffer too small\0",
+			RrtStatus::AccountRequired => b"account required\0",
+			RrtStatus::InvalidAccount => b"invalid account\0",
+			RrtStatus::UnknownCase => b"unknown case\0",
+			RrtStatus::WrongSigner => b"wrong signer\0",
+			RrtStatus::InvalidLocale => b"invalid locale\0",
+			RrtStatus::CaseIdOverflow => b"case id overflow\0",
+			RrtStatus::InvalidRegistry => b"invalid registry\0",
+			RrtStatus::UnknownRegistrar => b"unknown registrar\0",
+			RrtStatus::Revoked => b"revoked\0",
+			RrtStatus::InvalidRevocationList => b"invalid revocation list\0",
+			RrtStatus::InvalidSignature => b"invalid signature\0",
+			RrtStatus::InvalidKey => b"invalid key\0",
+			RrtStatus::InvalidEnvelope => b"invalid envelope\0",
+			RrtStatus::InvalidSecret => b"invalid secret\0",
+			RrtStatus::Panic => b"internal error\0",
+		}
+	}
+}
+
+/// Runs `f` and turns a panic into [RrtStatus::Panic]
+fn guard<F: FnOnce() -> RrtStatus + UnwindSafe>(f: F) -> RrtStatus {
+	panic::catch_unwind(f).unwrap_or(RrtStatus::Panic)
+}
+
+/// Borrows a C string as a &str
+unsafe fn to_str<'a>(s: *const c_char) -> Result<&'a str, RrtStatus> {
+	if s.is_null() {
+		return Err(RrtStatus::NullPointer);
+	}
+	CStr::from_ptr(s).to_str().map_err(|_| RrtStatus::InvalidUtf8)
+}
+
+/// Copies `s` into `buf` as a NUL terminated string
+unsafe fn write_str(s: &str, buf: *mut c_char, len: usize) -> RrtStatus {
+	if buf.is_null() {
+		return RrtStatus::NullPointer;
+	}
+	if s.len() + 1 > len {
+		return RrtStatus::BufferTooSmall;
+	}
+	ptr::copy_nonoverlapping(s.as_ptr(), buf as *mut u8, s.len());
+	*buf.add(s.len()) = 0;
+	RrtStatus::Ok
+}
+
+/// Returns a static, NUL terminated, description of `status`.
+/// The status is taken as an int so that any value is safe, unknown ones get a generic description.
+#[no_mangle]
+pub extern "C" fn rrt_status_message(status: c_int) -> *const c_char {
+	let message = match RrtStatus::ALL.iter().find(|s| **s as c_int == status) {
+		Some(status) => status.message(),
+		None => b"unknown status\0",
+	};
+	message.as_ptr() as *const c_char
+}
+
+/// Parses and checks the token string `s`. On success, `*out` receives a
+/// handle that must be released with `rrt_free`.
+///
+/// # Safety
+/// `s` must be a valid NUL terminated string and `out` a valid pointer.
+#[no_mangle]
+pub unsafe extern "C" fn rrt_parse(s: *const c_char, out: *mut *mut RrtToken) -> RrtStatus {
+	guard(|| {
+		if out.is_null() {
+			return RrtStatus::NullPointer;
+		}
+		let s = match to_str(s) {
+			Ok(s) => s,
+			Err(status) => return status,
+		};
+		match Builder::build_with_variant(s) {
+			Ok(token) => {
+				*out = Box::into_raw(Box::new(RrtToken(token)));
+				RrtStatus::Ok
+			}
+			Err(e) => RrtStatus::from(&e),
+		}
+	})
+}
+
+/// Checks the token string `s` without keeping the parsed token around.
+///
+/// # Safety
+/// `s` must be a valid NUL terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn rrt_check(s: *const c_char) -> RrtStatus {
+	guard(|| match to_str(s) {
+		Ok(s) => match Builder::build_with_variant(s) {
+			Ok(_) => RrtStatus::Ok,
+			Err(e) => RrtStatus::from(&e),
+		},
+		Err(status) => status,
+	})
+}
+
+/// Checks the token string `s` issued for `account`, given as an SS58 address
+/// or a `0x` prefixed hex public key. Needed for the tokens bound to an account such as V02.
+///
+/// # Safety
+/// `s` and `account` must be valid NUL terminated strings.
+#[no_mangle]
+pub unsafe extern "C" fn rrt_check_for_account(s: *const c_char, account: *const c_char) -> RrtStatus {
+	guard(|| {
+		let (s, account) = match (to_str(s), to_str(account)) {
+			(Ok(s), Ok(account)) => (s, account),
+			(Err(status), _) | (_, Err(status)) => return status,
+		};
+		match account.parse().and_then(|account| Builder::build_for_account(s, &account)) {
+			Ok(_) => RrtStatus::Ok,
+			Err(e) => RrtStatus::from(&e),
+		}
+	})
+}
+
+/// Generates a new token with a random secret. `channel` is one of `TW`, `EM`, `MX`,
+/// any other channel fails with `RRT_STATUS_UNKNOWN_CHANNEL`. On success, `*out` receives a handle that must be released with `rrt_free`.
+///
+/// # Safety
+/// `channel` must be a valid NUL terminated string and `out` a valid pointer.
+#[no_mangle]
+pub unsafe extern "C" fn rrt_generate(
+	app: u8,
+	version: u8,
+	network: u8,
+	index: u8,
+	case_id: u64,
+	channel: *const c_char,
+	out: *mut *mut RrtToken,
+) -> RrtStatus {
+	guard(|| {
+		if out.is_null() {
+			return RrtStatus::NullPointer;
+		}
+		let channel = match to_str(channel).map(Channel::from) {
+			Ok(Channel::Unknown) => return RrtStatus::UnknownChannel,
+			Ok(channel) => channel,
+			Err(status) => return status,
+		};
+		let token = Version::try_from(version)
+			.map_err(Error::Version)
+			.and_then(|v| Token::new(v, app, network, index, case_id, channel, None));
+		match token {
+			Ok(token) => {
+				*out = Box::into_raw(Box::new(RrtToken(token)));
+				RrtStatus::Ok
+			}
+			Err(e) => RrtStatus::from(&e),
+		}
+	})
+}
+
+/// Writes the token into `buf` as a NUL terminated string, fields being
+/// separated by `sep`. `sep` may be NULL for the compact form.
+///
+/// # Safety
+/// `token` must come from `rrt_parse` or `rrt_generate`, `sep` must be NULL or
+/// a valid NUL terminated string and `buf` must point to at least `len` bytes.
+#[no_mangle]
+pub unsafe extern "C" fn rrt_format(
+	token: *const RrtToken,
+	sep: *const c_char,
+	buf: *mut c_char,
+	len: usize,
+) -> RrtStatus {
+	guard(|| {
+		if token.is_null() {
+			return RrtStatus::NullPointer;
+		}
+		let sep = if sep.is_null() {
+			""
+		} else {
+			match to_str(sep) {
+				Ok(s) => s,
+				Err(status) => return status,
+			}
+		};
+		write_str(&(*token).0.format_string(sep), buf, len)
+	})
+}
+
+/// Returns the version of the token or 255 if `token` is NULL.
+///
+/// # Safety
+/// `token` must be NULL or come from `rrt_parse` or `rrt_generate`.
+#[no_mangle]
+pub unsafe extern "C" fn rrt_token_version(token: *const RrtToken) -> u8 {
+	match token.as_ref() {
+		Some(t) => *t.0.version() as u8,
+		None => u8::MAX,
+	}
+}
+
+/// Returns the network of the token or 255 if `token` is NULL.
+///
+/// # Safety
+/// `token` must be NULL or come from `rrt_parse` or `rrt_generate`.
+#[no_mangle]
+pub unsafe extern "C" fn rrt_token_network(token: *const RrtToken) -> u8 {
+	match token.as_ref() {
+		Some(t) => t.0.network().into(),
+		None => u8::MAX,
+	}
+}
+
+/// Returns the registrar index of the token or 255 if `token` is NULL.
+///
+/// # Safety
+/// `token` must be NULL or come from `rrt_parse` or `rrt_generate`.
+#[no_mangle]
+pub unsafe extern "C" fn rrt_token_index(token: *const RrtToken) -> u8 {
+	match token.as_ref() {
+		Some(t) => *t.0.index(),
+		None => u8::MAX,
+	}
+}
+
+/// Returns the case id of the token or `UINT64_MAX` if `token` is NULL.
+///
+/// # Safety
+/// `token` must be NULL or come from `rrt_parse` or `rrt_generate`.
+#[no_mangle]
+pub unsafe extern "C" fn rrt_token_case_id(token: *const RrtToken) -> u64 {
+	match token.as_ref() {
+		Some(t) => *t.0.case_id(),
+		None => u64::MAX,
+	}
+}
+
+/// Writes the 2 chars channel code of the token into `buf`.
+///
+/// # Safety
+/// `token` must come from `rrt_parse` or `rrt_generate` and `buf` must point
+/// to at least `len` bytes.
+#[no_mangle]
+pub unsafe extern "C" fn rrt_token_channel(token: *const RrtToken, buf: *mut c_char, len: usize) -> RrtStatus {
+	match token.as_ref() {
+		Some(t) => write_str(&t.0.channel().to_string(), buf, len),
+		None => RrtStatus::NullPointer,
+	}
+}
+
+/// Writes the secret of the token into `buf`.
+///
+/// # Safety
+/// `token` must come from `rrt_parse` or `rrt_generate` and `buf` must point
+/// to at least `len` bytes.
+#[no_mangle]
+pub unsafe extern "C" fn rrt_token_secret(token: *const RrtToken, buf: *mut c_char, len: usize) -> RrtStatus {
+	match token.as_ref() {
+		Some(t) => write_str(t.0.secret(), buf, len),
+		None => RrtStatus::NullPointer,
+	}
+}
+
+/// Releases a token handle. Passing NULL is a no-op.
+///
+/// # Safety
+/// `token` must be NULL or come from `rrt_parse` or `rrt_generate` and must
+/// not be used afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn rrt_free(token: *mut RrtToken) {
+	if !token.is_null() {
+		drop(Box::from_raw(token));
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use std::ffi::CString;
+
+	#[test]
+	fn it_parses_and_formats() {
+		let s = CString::new("0001020103039TWJXBACTSPAQ").unwrap();
+		let mut token: *mut RrtToken = ptr::null_mut();
+		let mut buf = [0 as c_char; 64];
+
+		unsafe {
+			assert_eq!(RrtStatus::Ok, rrt_parse(s.as_ptr(), &mut token));
+			assert_eq!(12345, rrt_token_case_id(token));
+			assert_eq!(2, rrt_token_network(token));
+			assert_eq!(RrtStatus::Ok, rrt_format(token, ptr::null(), buf.as_mut_ptr(), buf.len()));
+			assert_eq!("0001020103039TWJXBACTSPAQ", CStr::from_ptr(buf.as_ptr()).to_str().unwrap());
+			assert_eq!(RrtStatus::BufferTooSmall, rrt_format(token, ptr::null(), buf.as_mut_ptr(), 25));
+			rrt_free(token);
+		}
+	}
+
+	#[test]
+	fn it_checks_tokens_for_an_account() {
+		let alice: AccountId = "0xd43593c715fdd31c61141abd04a99fd6822c8558854ccde39a5684e7a56da27d".parse().unwrap();
+		let v02 = CString::new(TokenV02::new(0, 2, 1, 12345, Channel::Twitter, &alice).to_string()).unwrap();
+		let alice = CString::new("HNZata7iMYWmk5RvZRTiAsSDhV8366zq2YGb3tLH5Upf74F").unwrap();
+		let bob = CString::new(format!("{}", AccountId([2; 32]))).unwrap();
+		let junk = CString::new("junk").unwrap();
+
+		unsafe {
+			assert_eq!(RrtStatus::AccountRequired, rrt_check(v02.as_ptr()));
+			assert_eq!(RrtStatus::Ok, rrt_check_for_account(v02.as_ptr(), alice.as_ptr()));
+			assert_eq!(RrtStatus::InvalidChecksum, rrt_check_for_account(v02.as_ptr(), bob.as_ptr()));
+			assert_eq!(RrtStatus::InvalidAccount, rrt_check_for_account(v02.as_ptr(), junk.as_ptr()));
+		}
+	}
+
+	#[test]
+	fn it_maps_errors() {
+		let bad_checksum = CString::new("0001020103039TWJXBACTSPAA").unwrap();
+		let bad_version = CString::new("0099020103039TWJXBACTSPAQ").unwrap();
+		let too_short = CString::new("0001020103039TWJXBACTSP").unwrap();
+
+		unsafe {
+			assert_eq!(RrtStatus::InvalidChecksum, rrt_check(bad_checksum.as_ptr()));
+			assert_eq!(RrtStatus::UnsupportedVersion, rrt_check(bad_version.as_ptr()));
+			assert_eq!(RrtStatus::InvalidLength, rrt_check(too_short.as_ptr()));
+			assert_eq!(RrtStatus::NullPointer, rrt_check(ptr::null()));
+		}
+	}
+
+	#[test]
+	fn it_rejects_case_ids_that_do_not_fit() {
+		let channel = CString::new("TW").unwrap();
+		let mut token = ptr::null_mut();
+		unsafe {
+			assert_eq!(RrtStatus::CaseIdOverflow, rrt_generate(0, 1, 2, 1, 0x100000, channel.as_ptr(), &mut token));
+			assert!(token.is_null());
+			assert_eq!(RrtStatus::Ok, rrt_generate(0, 4, 2, 1, 0x100000, channel.as_ptr(), &mut token));
+			assert_eq!(0x100000, rrt_token_case_id(token));
+			rrt_free(token);
+		}
+	}
+
+	#[test]
+	fn it_rejects_unknown_channels() {
+		let channel = CString::new("ZZ").unwrap();
+		let mut token = ptr::null_mut();
+		unsafe {
+			assert_eq!(RrtStatus::UnknownChannel, rrt_generate(0, 1, 2, 1, 7, channel.as_ptr(), &mut token));
+		}
+		assert!(token.is_null());
+	}
+
+	#[test]
+	fn it_does_not_unwind_across_the_boundary() {
+		let junk = CString::new("0").unwrap();
+		assert_eq!(RrtStatus::InvalidLength, unsafe { rrt_check(junk.as_ptr()) });
+
+		let junk = CString::new("ZZZ").unwrap();
+		assert_eq!(RrtStatus::InvalidLength, unsafe { rrt_check(junk.as_ptr()) });
+
+		// Only 0-9 and A-Z, in any case, are part of a token, the other chars are ignored
+		let junk = CString::new("%&*é").unwrap();
+		assert_eq!(RrtStatus::InvalidLength, unsafe { rrt_check(junk.as_ptr()) });
+
+		let junk = CString::new("ZZ01020103039TWJXBACTSPAQ").unwrap();
+		assert_eq!(RrtStatus::InvalidEncoding, unsafe { rrt_check(junk.as_ptr()) });
+	}
+
+	#[test]
+	fn it_describes_any_status_code() {
+		let message = |code| unsafe { CStr::from_ptr(rrt_status_message(code)) }.to_str().unwrap();
+		assert_eq!("invalid checksum", message(RrtStatus::InvalidChecksum as c_int));
+		assert_eq!("internal error", message(255));
+		assert_eq!("unknown status", message(24));
+		assert_eq!("unknown status", message(-1));
+	}
+
+	#[test]
+	fn it_reports_panics() {
+		assert_eq!(RrtStatus::Panic, guard(|| panic!("boom")));
+		assert_eq!(RrtStatus::Ok, guard(|| RrtStatus::Ok));
+	}
+}
@@ -0,0 +1,85 @@
+/*
+ * Exercises the C ABI of librrt the way a C consumer would.
+ * rrt_c_tests() returns 0 on success or the line of the first failed check.
+ * tests/c_abi.rs builds it as a program linked to librrt and runs it.
+ */
+#include <stdio.h>
+#include <string.h>
+
+#include "rrt.h"
+
+#define CHECK(cond)                                                    \
+	do {                                                               \
+		if (!(cond)) {                                                 \
+			fprintf(stderr, "%s:%d: check failed: %s\n", __FILE__, __LINE__, #cond); \
+			return __LINE__;                                           \
+		}                                                              \
+	} while (0)
+
+static int test_parse(void) {
+	RrtToken *token = NULL;
+	char buf[64];
+
+	CHECK(rrt_parse("0001020103039TWJXBACTSPAQ", &token) == RRT_STATUS_OK);
+	CHECK(token != NULL);
+	CHECK(rrt_token_version(token) == 1);
+	CHECK(rrt_token_network(token) == 2);
+	CHECK(rrt_token_index(token) == 1);
+	CHECK(rrt_token_case_id(token) == 12345);
+
+	CHECK(rrt_token_channel(token, buf, sizeof(buf)) == RRT_STATUS_OK);
+	CHECK(strcmp(buf, "TW") == 0);
+	CHECK(rrt_token_secret(token, buf, sizeof(buf)) == RRT_STATUS_OK);
+	CHECK(strcmp(buf, "JXBACTSP") == 0);
+
+	CHECK(rrt_format(token, NULL, buf, sizeof(buf)) == RRT_STATUS_OK);
+	CHECK(strcmp(buf, "0001020103039TWJXBACTSPAQ") == 0);
+	CHECK(rrt_format(token, "-", buf, sizeof(buf)) == RRT_STATUS_OK);
+	CHECK(strcmp(buf, "00-01-02-01-03039-TW-JXBACTSP-AQ") == 0);
+	CHECK(rrt_format(token, NULL, buf, 10) == RRT_STATUS_BUFFER_TOO_SMALL);
+
+	rrt_free(token);
+	return 0;
+}
+
+static int test_check(void) {
+	CHECK(rrt_check("0001020103039TWJXBACTSPAQ") == RRT_STATUS_OK);
+	CHECK(rrt_check("0001020103039TWJXBACTSPAA") == RRT_STATUS_INVALID_CHECKSUM);
+	CHECK(rrt_check("0099020103039TWJXBACTSPAQ") == RRT_STATUS_UNSUPPORTED_VERSION);
+	CHECK(rrt_check(NULL) == RRT_STATUS_NULL_POINTER);
+	CHECK(strcmp(rrt_status_message(RRT_STATUS_INVALID_CHECKSUM), "invalid checksum") == 0);
+	CHECK(strcmp(rrt_status_message(1000), "unknown status") == 0);
+	return 0;
+}
+
+static int test_generate(void) {
+	RrtToken *token = NULL;
+	char buf[64];
+
+	CHECK(rrt_generate(0, 1, 42, 1, 1234, "EM", &token) == RRT_STATUS_OK);
+	CHECK(rrt_token_case_id(token) == 1234);
+	CHECK(rrt_format(token, NULL, buf, sizeof(buf)) == RRT_STATUS_OK);
+	CHECK(strlen(buf) == 25);
+	CHECK(rrt_check(buf) == RRT_STATUS_OK);
+	rrt_free(token);
+
+	CHECK(rrt_generate(0, 7, 42, 1, 1234, "EM", &token) == RRT_STATUS_UNSUPPORTED_VERSION);
+	CHECK(rrt_generate(0, 1, 42, 1, 1234, "ZZ", &token) == RRT_STATUS_UNKNOWN_CHANNEL);
+	return 0;
+}
+
+int rrt_c_tests(void) {
+	int res;
+
+	if ((res = test_parse()) != 0)
+		return res;
+	if ((res = test_check()) != 0)
+		return res;
+	if ((res = test_generate()) != 0)
+		return res;
+	return 0;
+}
+
+int main(void) {
+	return rrt_c_tests() == 0 ? 0 : 1;
+}
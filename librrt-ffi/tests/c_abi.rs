@@ -0,0 +1,50 @@
+//! Runs the C test program from tests/c against the C ABI and checks the shipped header.
+
+use std::env;
+use std::path::Path;
+use std::process::Command;
+
+#[test]
+fn it_passes_the_c_test_program() {
+	let compiler = env::var("CC").unwrap_or_else(|_| String::from("cc"));
+	if !Command::new(&compiler).arg("--version").output().is_ok_and(|o| o.status.success()) {
+		eprintln!("No C compiler was found, the C test program was skipped");
+		return;
+	}
+
+	// The test runs from target/<profile>/deps, cargo puts librrt in target/<profile>
+	let exe = env::current_exe().unwrap();
+	let lib_dir = exe.parent().and_then(Path::parent).unwrap();
+	let program = Path::new(env!("CARGO_TARGET_TMPDIR")).join("test_rrt");
+	let mut cc = Command::new(&compiler);
+	cc.arg("-Wall");
+	if env::var_os("CI").is_some() {
+		cc.arg("-Werror");
+	}
+	let status = cc
+		.arg(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/c/test_rrt.c"))
+		.arg("-I")
+		.arg(Path::new(env!("OUT_DIR")).join("include"))
+		.arg("-L")
+		.arg(lib_dir)
+		.arg(format!("-Wl,-rpath,{}", lib_dir.display()))
+		.arg("-lrrt")
+		.arg("-o")
+		.arg(&program)
+		.status()
+		.unwrap();
+	assert!(status.success(), "Cannot build the C test program");
+
+	let status = Command::new(&program).status().unwrap();
+	assert!(status.success(), "The C test program failed, see the line number above");
+}
+
+#[test]
+fn it_ships_an_up_to_date_header() {
+	let generated = include_str!(concat!(env!("OUT_DIR"), "/include/rrt.h"));
+	assert!(
+		generated == include_str!("../include/rrt.h"),
+		"include/rrt.h is outdated, copy {}/include/rrt.h over it",
+		env!("OUT_DIR")
+	);
+}
@@ -0,0 +1,210 @@
+//! Python bindings for librrt.
+//!
+//! Build and install in the current virtualenv with:
+//!
+//! ```sh
+//! cd librrt-py && maturin develop
+//! ```
+//!
+//! All the errors raised by the module derive from `librrt.RrtError`.
+
+use librrt::*;
+use pyo3::create_exception;
+use pyo3::exceptions::PyException;
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+use std::convert::TryFrom;
+
+create_exception!(librrt, RrtError, PyException, "Base class of all the librrt errors");
+create_exception!(librrt, VersionError, RrtError, "The version of the token is not supported");
+create_exception!(librrt, NetworkError, RrtError, "The network of the token is not supported");
+create_exception!(librrt, ChannelError, RrtError, "The channel of the token is not supported");
+create_exception!(librrt, LengthError, RrtError, "The token does not have the length expected for its version");
+create_exception!(librrt, EncodingError, RrtError, "A field of the token is not properly encoded");
+create_exception!(librrt, ChecksumError, RrtError, "The checksum of the token does not match its content");
+create_exception!(librrt, AccountError, RrtError, "The account is invalid, missing or not the one of the case");
+
+/// Maps a librrt [Error] to the matching Python exception
+fn to_py_err(e: Error) -> PyErr {
+	let msg = e.report();
+	match e {
+		Error::Version(_) => VersionError::new_err(msg),
+		Error::UnknownNetwork(_) => NetworkError::new_err(msg),
+		Error::UnknownChannel(_) => ChannelError::new_err(msg),
+		Error::LengthError { .. } => LengthError::new_err(msg),
+		Error::InvalidEncoding(_)
+		| Error::InvalidField { .. }
+		| Error::CaseIdOverflow { .. }
+		| Error::InvalidEnvelope(_)
+		| Error::InvalidSecret { .. } => EncodingError::new_err(msg),
+		Error::ChecksumError(_) => ChecksumError::new_err(msg),
+		Error::InvalidAccount(_) | Error::AccountRequired | Error::UnknownCase(_) | Error::WrongSigner { .. } => {
+			AccountError::new_err(msg)
+		}
+		Error::InvalidLocale(_)
+		| Error::InvalidRegistry(_)
+		| Error::UnknownRegistrar { .. }
+		| Error::Revoked(_)
+		| Error::InvalidRevocationList(_)
+		| Error::InvalidSignature
+		| Error::InvalidKey(_) => RrtError::new_err(msg),
+	}
+}
+
+fn to_dict<'py>(py: Python<'py>, token: &impl Tokenize) -> PyResult<Bound<'py, PyDict>> {
+	let dict = PyDict::new(py);
+	dict.set_item("token", token.format_string(""))?;
+	dict.set_item("app", *token.app())?;
+	dict.set_item("version", *token.version() as u8)?;
+	dict.set_item("network", Into::<u8>::into(token.network()))?;
+	dict.set_item("network_name", Into::<String>::into(token.network()))?;
+	dict.set_item("index", *token.index())?;
+	dict.set_item("case_id", *token.case_id())?;
+	dict.set_item("channel", token.channel().to_string())?;
+	dict.set_item("secret", token.secret())?;
+	dict.set_item("checksum", token.checksum())?;
+	Ok(dict)
+}
+
+/// Builds the token, checking it against the account when one is given
+fn build(token: &str, account: Option<&str>) -> Result<Token, Error> {
+	match account {
+		Some(account) => Builder::build_for_account(token, &account.parse()?),
+		None => Builder::build_any(token),
+	}
+}
+
+/// Parses and checks a token, possibly in its envelope. Returns a dict with the fields of the token.
+/// V02 tokens need the SS58 address of the account they were issued for.
+#[pyfunction]
+#[pyo3(signature = (token, account = None))]
+fn parse<'py>(py: Python<'py>, token: &str, account: Option<&str>) -> PyResult<Bound<'py, PyDict>> {
+	let token = build(token, account).map_err(to_py_err)?;
+	to_dict(py, &token)
+}
+
+/// Returns True when the token is valid
+#[pyfunction]
+#[pyo3(signature = (token, account = None))]
+fn check(token: &str, account: Option<&str>) -> bool {
+	build(token, account).is_ok()
+}
+
+/// Generates a new token with a random secret and returns it in its compact form.
+/// Raises ChannelError for an unknown channel.
+#[pyfunction]
+#[pyo3(signature = (network, case_id, channel, app = 0, version = 1, index = 1, separator = "", account = None))]
+#[allow(clippy::too_many_arguments)]
+fn generate(
+	network: u8,
+	case_id: u64,
+	channel: &str,
+	app: u8,
+	version: u8,
+	index: u8,
+	separator: &str,
+	account: Option<&str>,
+) -> PyResult<String> {
+	let channel = match Channel::from(channel) {
+		Channel::Unknown => {
+			return Err(ChannelError::new_err(format!("Unknown channel {:?}, expected one of TW, EM, MX", channel)))
+		}
+		channel => channel,
+	};
+	let version = Version::try_from(version).map_err(|e| VersionError::new_err(e.to_string()))?;
+	let account: Option<AccountId> = account.map(str::parse).transpose().map_err(to_py_err)?;
+	let token = Token::new(version, app, network, index, case_id, channel, account.as_ref()).map_err(to_py_err)?;
+	Ok(token.format_string(separator))
+}
+
+/// Returns the checksum letters of `data` for the given token version.
+/// The checksum of V02 tokens also covers the account, whose SS58 address is then required.
+#[pyfunction]
+#[pyo3(signature = (data, version = 1, account = None))]
+fn checksum(data: &str, version: u8, account: Option<&str>) -> PyResult<String> {
+	let output = match (Version::try_from(version), account) {
+		(Ok(Version::V00), _) => ChecksumOutput::Single(ChecksumV00::new().calculate(data.as_bytes())),
+		(Ok(Version::V02), Some(account)) => {
+			ChecksumOutput::Dual(TokenV02::calculate(data, &account.parse().map_err(to_py_err)?))
+		}
+		(Ok(Version::V02), None) => return Err(to_py_err(Error::AccountRequired)),
+		(Ok(Version::V01), _) | (Ok(Version::V03), _) | (Ok(Version::V04), _) => {
+			ChecksumOutput::Dual(ChecksumV01::new().calculate(data.as_bytes()))
+		}
+		(Err(e), _) => return Err(VersionError::new_err(e.to_string())),
+	};
+	Ok(output.to_string())
+}
+
+#[pymodule]
+#[pyo3(name = "librrt")]
+fn init(m: &Bound<'_, PyModule>) -> PyResult<()> {
+	let py = m.py();
+	m.add_function(wrap_pyfunction!(parse, m)?)?;
+	m.add_function(wrap_pyfunction!(check, m)?)?;
+	m.add_function(wrap_pyfunction!(generate, m)?)?;
+	m.add_function(wrap_pyfunction!(checksum, m)?)?;
+	m.add("RrtError", py.get_type::<RrtError>())?;
+	m.add("VersionError", py.get_type::<VersionError>())?;
+	m.add("NetworkError", py.get_type::<NetworkError>())?;
+	m.add("ChannelError", py.get_type::<ChannelError>())?;
+	m.add("LengthError", py.get_type::<LengthError>())?;
+	m.add("EncodingError", py.get_type::<EncodingError>())?;
+	m.add("ChecksumError", py.get_type::<ChecksumError>())?;
+	m.add("AccountError", py.get_type::<AccountError>())?;
+	Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn it_returns_a_dict() {
+		Python::attach(|py| {
+			let dict = parse(py, "0001020103039TWJXBACTSPAQ", None).unwrap();
+			let case_id: u64 = dict.get_item("case_id").unwrap().unwrap().extract().unwrap();
+			let network: String = dict.get_item("network_name").unwrap().unwrap().extract().unwrap();
+			assert_eq!(12345, case_id);
+			assert_eq!("Kusama", network);
+		});
+	}
+
+	#[test]
+	fn it_maps_errors_to_typed_exceptions() {
+		Python::attach(|py| {
+			let err = parse(py, "0001020103039TWJXBACTSPAA", None).unwrap_err();
+			assert!(err.is_instance_of::<ChecksumError>(py));
+			assert!(err.is_instance_of::<RrtError>(py));
+
+			let err = parse(py, "0099020103039TWJXBACTSPAQ", None).unwrap_err();
+			assert!(err.is_instance_of::<VersionError>(py));
+		});
+	}
+
+	#[test]
+	fn it_rejects_unknown_channels() {
+		Python::attach(|py| {
+			let err = generate(2, 12345, "FOO", 0, 1, 1, "", None).unwrap_err();
+			assert!(err.is_instance_of::<ChannelError>(py));
+			assert!(generate(2, 12345, "XX", 0, 1, 1, "", None).is_err());
+		});
+	}
+
+	#[test]
+	fn it_binds_v02_tokens_to_an_account() {
+		let alice = "HNZata7iMYWmk5RvZRTiAsSDhV8366zq2YGb3tLH5Upf74F";
+		let token = generate(2, 12345, "TW", 0, 2, 1, "", Some(alice)).unwrap();
+		assert!(check(&token, Some(alice)));
+		assert!(!check(&token, None));
+		assert!(check(&token, Some("5GrwvaEF5zXb26Fz9rcQpDWS57CtERHpNehXCPcNoHGKutQY")), "same key on Westend");
+		assert!(!check(&token, Some(&AccountId([2; 32]).to_string())));
+		assert_eq!(&token[23..], checksum(&token[..23], 2, Some(alice)).unwrap());
+	}
+
+	#[test]
+	fn it_matches_the_token_checksum() {
+		assert_eq!("AQ", checksum("0001020103039TWJXBACTSP", 1, None).unwrap());
+		assert!(check(&generate(2, 12345, "TW", 0, 1, 1, "", None).unwrap(), None));
+	}
+}
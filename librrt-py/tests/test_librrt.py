@@ -0,0 +1,78 @@
+"""Run with: maturin develop && pytest tests"""
+import pytest
+
+import librrt
+
+TOKEN = "0001020103039TWJXBACTSPAQ"
+
+
+def test_parse_returns_the_fields():
+    token = librrt.parse(TOKEN)
+    assert token == {
+        "token": TOKEN,
+        "app": 0,
+        "version": 1,
+        "network": 2,
+        "network_name": "Kusama",
+        "index": 1,
+        "case_id": 12345,
+        "channel": "TW",
+        "secret": "JXBACTSP",
+        "checksum": "AQ",
+    }
+
+
+def test_check():
+    assert librrt.check(TOKEN)
+    assert not librrt.check("0001020103039TWJXBACTSPAA")
+    assert not librrt.check("junk")
+
+
+@pytest.mark.parametrize(
+    "token, error",
+    [
+        ("0001020103039TWJXBACTSPAA", librrt.ChecksumError),
+        ("0099020103039TWJXBACTSPAQ", librrt.VersionError),
+        ("0", librrt.LengthError),
+    ],
+)
+def test_parse_raises_typed_errors(token, error):
+    with pytest.raises(error):
+        librrt.parse(token)
+    with pytest.raises(librrt.RrtError):
+        librrt.parse(token)
+
+
+def test_parse_accepts_envelopes():
+    assert librrt.parse("rrt1-ksm-0001020103039twjxbactspaq-esvvmc")["token"] == TOKEN
+    with pytest.raises(librrt.EncodingError):
+        librrt.parse("rrt1-ksm-0001020103039twjxbactspaq-esvvmq")
+
+
+def test_generate():
+    token = librrt.generate(42, 1234, "EM")
+    assert len(token) == 25
+    assert librrt.parse(token)["case_id"] == 1234
+    assert len(librrt.generate(42, 1234, "EM", separator="-")) == 32
+    with pytest.raises(librrt.VersionError):
+        librrt.generate(42, 1234, "EM", version=7)
+    with pytest.raises(librrt.ChannelError):
+        librrt.generate(42, 1234, "FOO")
+
+
+def test_checksum_matches_the_token():
+    assert librrt.checksum(TOKEN[:-2]) == TOKEN[-2:]
+    assert librrt.checksum("0000000012345TWRAJQFIZW", version=0) == "W"
+
+
+def test_v02_tokens_are_bound_to_an_account():
+    alice = "HNZata7iMYWmk5RvZRTiAsSDhV8366zq2YGb3tLH5Upf74F"
+    token = librrt.generate(2, 1234, "EM", version=2, account=alice)
+    assert librrt.parse(token, account=alice)["version"] == 2
+    with pytest.raises(librrt.AccountError):
+        librrt.parse(token)
+    with pytest.raises(librrt.AccountError):
+        librrt.generate(2, 1234, "EM", version=2)
+    assert librrt.checksum(token[:-2], version=2, account=alice) == token[-2:]
+    with pytest.raises(librrt.AccountError):
+        librrt.checksum(token[:-2], version=2)
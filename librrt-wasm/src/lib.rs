@@ -0,0 +1,141 @@
+//! WebAssembly bindings for librrt.
+//!
+//! This allows a web UI to parse, check and generate tokens with the exact
+//! same code as the backend. Build it with:
+//!
+//! ```sh
+//! wasm-pack build librrt-wasm --target web
+//! ```
+
+use librrt::*;
+use std::convert::TryFrom;
+use std::str::FromStr;
+use wasm_bindgen::prelude::*;
+
+/// The fields of a token, as seen from JS.
+#[wasm_bindgen(getter_with_clone)]
+#[derive(Debug, PartialEq, Clone)]
+pub struct ParsedToken {
+	/// The compact form of the token
+	pub token: String,
+	pub app: u8,
+	pub version: u8,
+	pub network: u8,
+
+	/// The name of the network such as `Kusama` or `Network 03` when unknown
+	pub network_name: String,
+	pub index: u8,
+	pub case_id: u64,
+
+	/// The 2 chars channel code such as `TW`
+	pub channel: String,
+	pub secret: String,
+	pub checksum: String,
+}
+
+impl<T: Tokenize> From<&T> for ParsedToken {
+	fn from(t: &T) -> Self {
+		Self {
+			token: t.format_string(""),
+			app: *t.app(),
+			version: *t.version() as u8,
+			network: t.network().into(),
+			network_name: t.network().into(),
+			index: *t.index(),
+			case_id: *t.case_id(),
+			channel: t.channel().to_string(),
+			secret: t.secret().clone(),
+			checksum: t.checksum(),
+		}
+	}
+}
+
+#[wasm_bindgen]
+impl ParsedToken {
+	/// Returns the token with its fields separated by `sep`. Throws if the token is not valid.
+	pub fn format(&self, sep: &str) -> Result<String, JsError> {
+		Builder::build_with_variant(&self.token).map(|t| t.format_string(sep)).map_err(to_js_error)
+	}
+
+	/// Returns the token in its envelope such as `rrt1-ksm-...`. Throws if the token is not valid.
+	pub fn envelope(&self) -> Result<String, JsError> {
+		Builder::build_with_variant(&self.token).map(|t| t.to_envelope()).map_err(to_js_error)
+	}
+}
+
+fn to_js_error(e: Error) -> JsError {
+	JsError::new(&e.report())
+}
+
+/// Parses and checks any supported token, possibly in its envelope. Throws if the token is not valid.
+#[wasm_bindgen]
+pub fn parse(token: &str) -> Result<ParsedToken, JsError> {
+	Builder::build_any(token).map(|t| ParsedToken::from(&t)).map_err(to_js_error)
+}
+
+/// Checks a token that is not bound to an account. Throws with the reason if the token is not valid.
+#[wasm_bindgen]
+pub fn check(token: &str) -> Result<(), JsError> {
+	Builder::build_with_variant(token).map(|_| ()).map_err(to_js_error)
+}
+
+/// Returns true when `token` is a valid token of any supported version.
+#[wasm_bindgen(js_name = isValid)]
+pub fn is_valid(token: &str) -> bool {
+	Builder::build_any(token).is_ok()
+}
+
+/// Checks that a signed token, such as `0001020103039TWJXBACTSPAQ.E...`, was signed by `signer`.
+/// The signer is the public key of the registrar, as SS58 or hex. Throws if the signature is not valid.
+#[wasm_bindgen(js_name = verifySignature)]
+pub fn verify_signature(signed: &str, signer: &str) -> Result<ParsedToken, JsError> {
+	let signer = AccountId::from_str(signer).map_err(to_js_error)?;
+	let signed = SignedToken::parse(signed).map_err(to_js_error)?;
+	signed.verify(&signer).map_err(to_js_error)?;
+	Ok(ParsedToken::from(&signed.token))
+}
+
+/// Generates a new token with a random secret. Throws for an unknown channel.
+#[wasm_bindgen]
+pub fn generate(
+	app: u8,
+	version: u8,
+	network: u8,
+	index: u8,
+	case_id: u64,
+	channel: &str,
+) -> Result<ParsedToken, JsError> {
+	let channel = match Channel::from(channel) {
+		Channel::Unknown => {
+			return Err(JsError::new(&format!("Unknown channel {:?}, expected one of TW, EM, MX", channel)))
+		}
+		channel => channel,
+	};
+	let version = Version::try_from(version).map_err(|e| JsError::new(&e.to_string()))?;
+	if version == Version::V02 {
+		return Err(JsError::new("V02 tokens are bound to an account and cannot be generated here"));
+	}
+	let token = Token::new(version, app, network, index, case_id, channel, None).map_err(to_js_error)?;
+	Ok(ParsedToken::from(&token))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn it_exposes_the_fields() {
+		let token = TokenV01::from_str("0001020103039TWJXBACTSPAQ").unwrap();
+		let parsed = ParsedToken::from(&token);
+
+		assert_eq!("0001020103039TWJXBACTSPAQ", parsed.token);
+		assert_eq!(1, parsed.version);
+		assert_eq!(2, parsed.network);
+		assert_eq!("Kusama", parsed.network_name);
+		assert_eq!(12345, parsed.case_id);
+		assert_eq!("TW", parsed.channel);
+		assert_eq!("JXBACTSP", parsed.secret);
+		assert_eq!("AQ", parsed.checksum);
+		assert_eq!("00-01-02-01-03039-TW-JXBACTSP-AQ", parsed.format("-").unwrap());
+	}
+}
@@ -0,0 +1,68 @@
+//! Run with `wasm-pack test --node librrt-wasm`
+#![cfg(target_arch = "wasm32")]
+
+use librrt_wasm::*;
+use wasm_bindgen_test::*;
+
+#[wasm_bindgen_test]
+fn it_parses_a_token() {
+	let parsed = parse("0001020103039TWJXBACTSPAQ").unwrap();
+	assert_eq!(12345, parsed.case_id);
+	assert_eq!("Kusama", parsed.network_name);
+	assert_eq!("JXBACTSP", parsed.secret);
+}
+
+#[wasm_bindgen_test]
+fn it_parses_envelopes() {
+	let envelope = parse("0001020103039TWJXBACTSPAQ").unwrap().envelope().unwrap();
+	assert!(envelope.starts_with("rrt1-ksm-"));
+	assert_eq!(12345, parse(&envelope).unwrap().case_id);
+	assert!(!is_valid(&envelope.replace("ksm", "dot")));
+}
+
+#[wasm_bindgen_test]
+fn it_rejects_bad_tokens() {
+	assert!(!is_valid("0001020103039TWJXBACTSPAA"));
+	assert!(!is_valid("0099020103039TWJXBACTSPAQ"));
+	assert!(!is_valid("0"));
+	assert!(check("0001020103039TWJXBACTSPAA").is_err());
+	// Throws rather than trapping on text that is not a token
+	assert!(check("A-B-C-D-E-F-G-H-I-J-K-L-M-N").is_err());
+	assert!(check("0001020103039TWJXBACTSPAé").is_err());
+	assert!(check("").is_err());
+
+	// Fields changed from JS make format and envelope throw instead of returning ""
+	let mut parsed = parse("0001020103039TWJXBACTSPAQ").unwrap();
+	parsed.token = String::from("0001020103039TWJXBACTSPAA");
+	assert!(parsed.format("-").is_err());
+	assert!(parsed.envelope().is_err());
+}
+
+#[wasm_bindgen_test]
+fn it_checks_a_token() {
+	assert!(check("0001020103039TWJXBACTSPAQ").is_ok());
+}
+
+#[wasm_bindgen_test]
+fn it_generates_valid_tokens() {
+	let generated = generate(0, 1, 42, 1, 1234, "EM").unwrap();
+	assert_eq!(25, generated.token.len());
+	assert!(is_valid(&generated.token));
+	assert_eq!(generated, parse(&generated.token).unwrap());
+	assert!(generate(0, 7, 42, 1, 1234, "EM").is_err());
+}
+
+#[wasm_bindgen_test]
+fn it_rejects_unknown_channels() {
+	assert!(generate(0, 1, 42, 1, 1234, "FOO").is_err());
+	assert!(generate(0, 1, 42, 1, 1234, "XX").is_err());
+}
+
+#[wasm_bindgen_test]
+fn it_verifies_signed_tokens() {
+	let signed = "0001020103039TWOZKWXPFEYD.E1YK6ACQEF31AZ2P5RZ6WM40VWDA8GK5H4KSHGQBWBE30M0CJV49GV2RB2PZA028T59P69462HTJ1GE5G6JF0XW4SBSB9W191AGKY82R";
+	let signer = "0x8a88e3dd7409f195fd52db2d3cba5d72ca6709bf1d94121bf3748801b40f6f5c";
+	assert_eq!(12345, verify_signature(signed, signer).unwrap().case_id);
+	assert!(verify_signature(&signed.replace("OZKW", "OZKX"), signer).is_err());
+	assert!(verify_signature("0001020103039TWOZKWXPFEYD", signer).is_err());
+}
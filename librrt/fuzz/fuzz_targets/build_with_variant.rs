@@ -0,0 +1,12 @@
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+use librrt::{Builder, Tokenize};
+
+fuzz_target!(|s: &str| {
+	if let Ok(token) = Builder::build_with_variant(s) {
+		// Whatever was accepted must format to a token that parses back to itself
+		let compact = token.format_string("");
+		let again = Builder::build_with_variant(&compact).expect("a formatted token parses");
+		assert_eq!(compact, again.format_string(""));
+	}
+});
@@ -0,0 +1,9 @@
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+use librrt::clean_token_string;
+
+fuzz_target!(|s: &str| {
+	let cleaned = clean_token_string(s);
+	assert!(cleaned.chars().all(|c| c.is_ascii_uppercase() || c.is_ascii_digit()));
+	assert_eq!(cleaned, clean_token_string(&cleaned));
+});
@@ -0,0 +1,14 @@
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+use librrt::Detector;
+
+fuzz_target!(|s: &str| {
+	if let Ok((app, version, len)) = Detector::analyze(s) {
+		// The app and the version are read from the first 4 chars, which must be hex digits
+		let head = s.get(0..4).expect("a detected token starts with 4 ascii chars");
+		assert!(head.bytes().all(|b| b.is_ascii_hexdigit()), "{:?}", s);
+		assert_eq!(Some(u8::from_str_radix(&head[0..2], 16).unwrap()), app);
+		assert_eq!(&head[2..4], version.expect("a detected token has a version").to_string());
+		assert_eq!(s.len(), len);
+	}
+});
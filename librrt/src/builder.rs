@@ -4,24 +4,88 @@
 //! - does the job
 
 use crate::detector::Detector;
+use crate::envelope;
 use crate::types::*;
+use crate::utils::clean_token_string;
 use crate::versions::*;
 use crate::Error;
 use std::str::FromStr;
 
+/// The position of the secret in the cleaned token string, or of its length for V03
+const SECRET_START: usize = 15;
+
+/// Returns the position of the secret in a cleaned token string.
+/// The secret of V04 tokens comes after a case id of variable length.
+fn secret_start(version: Version, cleaned: &str) -> usize {
+	match version {
+		Version::V04 => {
+			TokenV04::parse_case_id_length(cleaned).map(|l| TokenV04::size_for(l) - 10).unwrap_or(SECRET_START)
+		}
+		_ => SECRET_START,
+	}
+}
+
+/// The static functions build any supported token. An instance of the builder
+/// only accepts the versions of its policy, all of them by default.
+#[derive(Debug, Clone)]
 pub struct Builder {
-	// tokens: Vec<Token>,
+	versions: Vec<Version>,
+}
+
+impl Default for Builder {
+	fn default() -> Self {
+		Self::new()
+	}
 }
 
 impl Builder {
 	pub fn new() -> Self {
-		// Self { tokens: Vec::new() }
-		Self {}
+		Self { versions: Version::ALL.to_vec() }
+	}
+
+	/// Only accepts the given versions, for instance to reject V00 tokens in production
+	pub fn accept(mut self, versions: &[Version]) -> Self {
+		self.versions = versions.to_vec();
+		self
+	}
+
+	pub fn accepts(&self, version: Version) -> bool {
+		self.versions.contains(&version)
+	}
+
+	/// Fails with [VersionError::NotAccepted] if the policy does not accept the version
+	pub fn check_version(&self, version: Version) -> Result<(), Error> {
+		match self.accepts(version) {
+			true => Ok(()),
+			false => Err(Error::Version(VersionError::NotAccepted(version))),
+		}
+	}
+
+	/// Like `build_any`, for the accepted versions only
+	pub fn parse(&self, s: &str) -> Result<Token, Error> {
+		self.parse_with(s, Self::build_any)
+	}
+
+	/// Like `build_for_account`, for the accepted versions only
+	pub fn parse_for_account(&self, s: &str, account: &AccountId) -> Result<Token, Error> {
+		self.parse_with(s, |s| Self::build_for_account(s, account))
+	}
+
+	fn parse_with<F: Fn(&str) -> Result<Token, Error>>(&self, s: &str, build: F) -> Result<Token, Error> {
+		// Reject early, so a token of a rejected version is not reported for its checksum or its account
+		if let Ok((_, Some(version), _)) = Detector::analyze(&clean_token_string(s)) {
+			if !envelope::is_envelope(s.trim()) {
+				self.check_version(version)?;
+			}
+		}
+		let token = build(s)?;
+		self.check_version(*token.version())?;
+		Ok(token)
 	}
 
 	/// This function return 'a' token implementing Tokenize but we lost which one.
 	/// Prefer using `build_with_variant`
-	pub fn build(s: &str) -> Option<impl Tokenize + std::fmt::Debug> {
+	pub fn build(s: &str) -> Option<impl Tokenize> {
 		let analysis = Detector::analyze(s);
 		let some_tuple = analysis.expect("Fix me, got no version 1");
 
@@ -48,33 +112,108 @@ impl Builder {
 		}
 	}
 
-	/// This function return a given token
+	/// This function return a given token. Lower case letters are read as upper case
+	/// and the other chars than 0-9 and A-Z, such as separators, are ignored.
+	/// If the checksum does not match, the confusable chars of the secret,
+	/// such as 0 for O, are mapped back to the alphabet of the version and the token is checked again.
 	pub fn build_with_variant(s: &str) -> Result<Token, Error> {
-		let analysis = Detector::analyze(s);
-		let some_tuple = analysis.expect("Fix me, got no version 1");
+		Self::retry_confusables(s, Self::build_exact)
+	}
 
-		let app = match some_tuple.0 {
-			None => panic!("Fix me, got no version 2"),
-			Some(v) => v,
-		};
+	/// Runs `build` on `s`, then on `s` with its confusables mapped back if the checksum does not match
+	fn retry_confusables<F: Fn(&str) -> Result<Token, Error>>(s: &str, build: F) -> Result<Token, Error> {
+		let s = &s.to_ascii_uppercase();
+		match build(s) {
+			Err(Error::ChecksumError(e)) => {
+				let cleaned = clean_token_string(s);
+				let version = Detector::analyze(&cleaned).ok().and_then(|a| a.1);
+				let secret_end = version.map(|v| cleaned.len().saturating_sub(v.checksum_len())).unwrap_or_default();
+				let start = version.map(|v| secret_start(v, &cleaned)).unwrap_or(SECRET_START);
+				match (version, cleaned.get(..start), cleaned.get(start..secret_end)) {
+					(Some(v), Some(head), Some(secret)) if v.alphabet().normalize(secret) != secret => {
+						let fixed = format!("{}{}{}", head, v.alphabet().normalize(secret), &cleaned[secret_end..]);
+						build(&fixed).map_err(|_| Error::ChecksumError(e))
+					}
+					_ => Err(Error::ChecksumError(e)),
+				}
+			}
+			res => res,
+		}
+	}
 
-		let version = match some_tuple.1 {
-			None => panic!("Fix me, got no version 2"),
-			Some(v) => v,
+	/// Builds the token, ignoring any separator between its fields
+	fn build_exact(s: &str) -> Result<Token, Error> {
+		let cleaned = clean_token_string(s);
+		let (app, version, size) = match Detector::analyze(&cleaned)? {
+			(Some(app), Some(version), size) => (app, version, size),
+			_ => return Err(Error::InvalidEncoding(String::from(s))),
 		};
 
-		let size = some_tuple.2;
-
 		match (app, version, size) {
-			(_x, Version::V00, 24) => Ok(Token::V00(TokenV00::from_str(s)?)),
-			(_x, Version::V01, 25) => Ok(Token::V01(TokenV01::from_str(s)?)),
-			_ => {
-				println!(
-					"This app/version set is not supported: app:{:02?} version:{:?} with length: {:?}",
-					app, version, size
-				);
-				Err(Error::InvalidEncoding(String::from(s)))
-			}
+			(_x, Version::V00, 24) => Ok(Token::V00(TokenV00::from_str(&cleaned)?)),
+			(_x, Version::V01, 25) => Ok(Token::V01(TokenV01::from_str(&cleaned)?)),
+			(_x, Version::V02, 25) => Err(Error::AccountRequired),
+			(_x, Version::V03, _) => Ok(Token::V03(TokenV03::from_str(&cleaned)?)),
+			(_x, Version::V04, _) => Ok(Token::V04(TokenV04::from_str(&cleaned)?)),
+			(_x, Version::V00, found) => Err(Error::LengthError { expected: 24, found }),
+			(_x, Version::V01, found) | (_x, Version::V02, found) => Err(Error::LengthError { expected: 25, found }),
+		}
+	}
+
+	/// Like `build_with_variant` but also accepts the tokens whose checksum covers
+	/// the account they were issued for, such as V02, and the tokens in their envelope.
+	/// The account is ignored for the versions that do not depend on it.
+	pub fn build_for_account(s: &str, account: &AccountId) -> Result<Token, Error> {
+		if envelope::is_envelope(s.trim()) {
+			return Self::unwrap_envelope(s, |token| Self::build_for_account(token, account));
+		}
+		match Detector::analyze(&clean_token_string(s))? {
+			(Some(_), Some(Version::V02), _) => Self::retry_confusables(s, |s| {
+				Ok(Token::V02(TokenV02::from_str_with_account(&clean_token_string(s), account)?))
+			}),
+			_ => Self::build_with_variant(s),
+		}
+	}
+
+	/// Builds a token from an URI such as `rrt:0001020103039TWJXBACTSPAQ`.
+	/// The scheme is case insensitive, `rrt://` is accepted and any query or fragment is ignored.
+	pub fn build_from_uri(uri: &str) -> Result<Token, Error> {
+		let invalid = || Error::InvalidEncoding(String::from(uri));
+		let (scheme, rest) = uri.trim().split_once(':').ok_or_else(invalid)?;
+		if !scheme.eq_ignore_ascii_case(URI_SCHEME) {
+			return Err(invalid());
+		}
+		let rest = rest.trim_start_matches('/');
+		let token = rest.split(['?', '#']).next().unwrap_or_default();
+		Self::build_with_variant(&clean_token_string(token))
+	}
+
+	/// Builds a token from its envelope such as `rrt1-ksm-0001020103039twjxbactspaq-...`.
+	/// The network named by the envelope must be the one of the token.
+	pub fn build_from_envelope(s: &str) -> Result<Token, Error> {
+		Self::unwrap_envelope(s, Self::build_with_variant)
+	}
+
+	/// Runs `build` on the token of the envelope `s` and checks it is for the network of the envelope
+	fn unwrap_envelope<F: Fn(&str) -> Result<Token, Error>>(s: &str, build: F) -> Result<Token, Error> {
+		let (network, token) = envelope::unwrap(s)?;
+		let token = build(&token)?;
+		match token.network() == network {
+			true => Ok(token),
+			false => Err(Error::InvalidEnvelope(format!(
+				"The token is for {}, not {}: {}",
+				token.network().short_name(),
+				network.short_name(),
+				s
+			))),
+		}
+	}
+
+	/// Builds a token given either in its compact form, with or without separators, or in its envelope
+	pub fn build_any(s: &str) -> Result<Token, Error> {
+		match envelope::is_envelope(s.trim()) {
+			true => Self::build_from_envelope(s),
+			false => Self::build_with_variant(s),
 		}
 	}
 }
@@ -84,6 +223,7 @@ mod tests_builder {
 	use super::*;
 	use crate::detector::Detector;
 	use crate::error::Error;
+	use crate::explainer::Field;
 
 	#[test]
 	fn it_returns_a_tokenize() {
@@ -91,7 +231,9 @@ mod tests_builder {
 		let analysis = Detector::analyze(s);
 		assert_eq!(Ok((Some(0), Some(Version::V00), 24)), analysis);
 		let tkn = Builder::build(s).expect("Got None where we expected Some Token_V00");
-		println!("We lost the variant but we know this is version {:?}", tkn.version());
+		// We lost the variant but we know this is version V00
+		assert_eq!(Version::V00, *tkn.version());
+		assert_eq!(s, tkn.format_string(""));
 	}
 
 	#[test]
@@ -101,14 +243,142 @@ mod tests_builder {
 		assert_eq!(Ok((Some(0), Some(Version::V00), 24)), analysis);
 
 		let tkn_variant = Builder::build_with_variant(s).expect("Got None where we expected Some Token_V00");
-		println!("RESULT: {:?}", tkn_variant);
-
 		match tkn_variant {
-			Token::V00(t) => println!("Checksum: {}", t.checksum()),
-			_ => println!("Some other version"),
+			Token::V00(t) => assert_eq!("W", t.checksum()),
+			t => panic!("Expected a V00 token, got {:?}", t),
 		};
 	}
 
+	#[test]
+	fn it_ignores_separators() {
+		let token = Builder::build_with_variant("00-01-02-01-03039-TW-JXBACTSP-AQ").unwrap();
+		assert_eq!("0001020103039TWJXBACTSPAQ", token.to_string());
+		assert!(matches!(
+			Builder::build_with_variant("00-01-02-01-03039-TW-JXBACTSP-AA"),
+			Err(Error::ChecksumError(_))
+		));
+
+		let alice = AccountId([1; 32]);
+		let v02 = TokenV02::new_with_secret(0, 2, 1, 12345, Channel::Twitter, "JXBACTSP", &alice);
+		assert_eq!(v02.to_string(), Builder::build_for_account(&v02.format_string(" - "), &alice).unwrap().to_string());
+	}
+
+	#[test]
+	fn it_reads_lower_case_letters_as_upper_case() {
+		let token = Builder::build_any("0001-02-01-03039-twjxbactspaq").unwrap();
+		assert_eq!("0001020103039TWJXBACTSPAQ", token.to_string());
+
+		let alice = AccountId([1; 32]);
+		let v02 = TokenV02::new_with_secret(0, 2, 1, 12345, Channel::Twitter, "JXBACTSP", &alice);
+		let lower = v02.to_string().to_lowercase();
+		assert_eq!(v02.to_string(), Builder::build_for_account(&lower, &alice).unwrap().to_string());
+	}
+
+	#[test]
+	fn it_maps_confusables_back() {
+		let token = TokenV01::new_with_secret(0, Version::V01, 2, 1, 12345, Channel::Twitter, "JOBACTIP").to_string();
+		let typed = format!("{}{}", &token[..15], token[15..].replace('O', "0").replace('I', "1"));
+		assert_ne!(token, typed);
+		assert_eq!(token, Builder::build_with_variant(&typed).unwrap().to_string());
+
+		let wrong = format!("{}{}", &token[..15], token[15..].replace('O', "Q"));
+		assert!(matches!(Builder::build_with_variant(&wrong), Err(Error::ChecksumError(_))));
+
+		// Crockford reads O as 0 and I or L as 1
+		let token = TokenV03::new_with_secret(0, 2, 1, 12345, Channel::Twitter, "10B4C70P").to_string();
+		let typed = format!("{}{}{}", &token[..16], token[16..24].replace('0', "O").replace('1', "L"), &token[24..]);
+		assert_eq!(token, Builder::build_with_variant(&typed).unwrap().to_string());
+
+		// The secret of V04 tokens moves with the length of the case id
+		let token = TokenV04::new_with_secret(0, 2, 1, 1 << 40, Channel::Twitter, "JOBACTIP").to_string();
+		let typed = format!("{}{}{}", &token[..22], token[22..30].replace('O', "0"), &token[30..]);
+		assert_eq!(token, Builder::build_with_variant(&typed).unwrap().to_string());
+	}
+
+	#[test]
+	fn it_round_trips_uris() {
+		let s = "0001020103039TWJXBACTSPAQ";
+		let token = Builder::build_with_variant(s).unwrap();
+		assert_eq!("rrt:0001020103039TWJXBACTSPAQ", token.to_uri());
+
+		for uri in &[
+			"rrt:0001020103039TWJXBACTSPAQ",
+			"RRT:0001020103039TWJXBACTSPAQ",
+			"rrt://0001020103039TWJXBACTSPAQ",
+			"rrt:00-01-02-01-03039-TW-JXBACTSP-AQ?source=qr",
+		] {
+			assert_eq!(s, Builder::build_from_uri(uri).unwrap().to_string());
+		}
+	}
+
+	#[test]
+	fn it_rejects_bad_uris() {
+		assert!(Builder::build_from_uri("0001020103039TWJXBACTSPAQ").is_err());
+		assert!(Builder::build_from_uri("http:0001020103039TWJXBACTSPAQ").is_err());
+		assert!(Builder::build_from_uri("rrt:0001020103039TWJXBACTSPAA").is_err());
+	}
+
+	#[test]
+	fn it_round_trips_envelopes() {
+		let s = "0001020103039TWJXBACTSPAQ";
+		let envelope = Builder::build_with_variant(s).unwrap().to_envelope();
+		assert!(envelope.starts_with("rrt1-ksm-0001020103039twjxbactspaq-"));
+		assert_eq!(s, Builder::build_from_envelope(&envelope).unwrap().to_string());
+		assert_eq!(s, Builder::build_any(&envelope.to_uppercase()).unwrap().to_string());
+		assert_eq!(s, Builder::build_any(s).unwrap().to_string());
+
+		let token = TokenV04::new_with_secret(0, 42, 1, 1 << 40, Channel::Email, "JOBACTIP");
+		assert_eq!(token.to_string(), Builder::build_from_envelope(&token.to_envelope()).unwrap().to_string());
+
+		let alice = AccountId([1; 32]);
+		let v02 = TokenV02::new_with_secret(0, 2, 1, 12345, Channel::Twitter, "JXBACTSP", &alice);
+		let s = v02.to_string();
+		let envelope = Token::V02(v02).to_envelope();
+		assert_eq!(Err(Error::AccountRequired), Builder::build_from_envelope(&envelope).map(|_| ()));
+		assert_eq!(s, Builder::build_for_account(&envelope, &alice).unwrap().to_string());
+	}
+
+	#[test]
+	fn it_rejects_envelopes_for_another_network() {
+		let envelope = crate::envelope::wrap(Network::from(0), "0001020103039TWJXBACTSPAQ");
+		assert!(matches!(Builder::build_from_envelope(&envelope), Err(Error::InvalidEnvelope(_))));
+	}
+
+	#[test]
+	fn it_returns_structured_errors() {
+		assert_eq!(
+			Err(Error::LengthError { expected: 25, found: 24 }),
+			Builder::build_with_variant("0001020103039TWJXBACTSPA").map(|_| ())
+		);
+		assert_eq!(
+			Err(Error::InvalidField { field: Field::CaseId, offset: 8, found: String::from("03G39") }),
+			Builder::build_with_variant("0001020103G39TWJXBACTSPAQ").map(|_| ())
+		);
+		assert_eq!(
+			Err(Error::InvalidField { field: Field::Network, offset: 4, found: String::from("0Z") }),
+			Builder::build_with_variant("00010Z0103039TWJXBACTSPAQ").map(|_| ())
+		);
+	}
+
+	#[test]
+	fn it_only_accepts_the_versions_of_its_policy() {
+		let builder = Builder::new().accept(&[Version::V01, Version::V02]);
+		assert!(builder.parse("0001020103039TWJXBACTSPAQ").is_ok());
+		assert_eq!(
+			Err(Error::Version(VersionError::NotAccepted(Version::V00))),
+			builder.parse("0000000012345TWRAJQFIZWW").map(|_| ())
+		);
+		let envelope = Builder::build_with_variant("0000000012345TWRAJQFIZWW").unwrap().to_envelope();
+		assert!(Builder::new().parse(&envelope).is_ok());
+		assert!(builder.parse(&envelope).is_err());
+
+		let account = AccountId([7; 32]);
+		let v02 = TokenV02::new(0, 2, 1, 12345, Channel::Twitter, &account).to_string();
+		assert!(builder.parse_for_account(&v02, &account).is_ok());
+		let builder = builder.accept(&[Version::V01]);
+		assert_eq!(Err(Error::Version(VersionError::NotAccepted(Version::V02))), builder.parse(&v02).map(|_| ()));
+	}
+
 	#[test]
 	#[ignore]
 	fn it_runs_2() {
@@ -119,8 +389,20 @@ mod tests_builder {
 
 	#[test]
 	fn it_runs_3() {
-		let s = "FF02000012345TWRAJQFIZWFX";
+		let s = "FF99000012345TWRAJQFIZWFX";
 		let analysis = Detector::analyze(s);
-		assert_eq!(Err(Error::Version(VersionError::UnsupportedVersion(2))), analysis);
+		assert_eq!(Err(Error::Version(VersionError::UnsupportedVersion(99))), analysis);
+	}
+
+	#[test]
+	fn it_needs_the_account_for_v02() {
+		let account = AccountId([7; 32]);
+		let s = TokenV02::new(0, 2, 1, 12345, Channel::Twitter, &account).to_string();
+		assert_eq!(Err(Error::AccountRequired), Builder::build_with_variant(&s).map(|_| ()));
+
+		let token = Builder::build_for_account(&s, &account).unwrap();
+		assert_eq!(Version::V02, *token.version());
+		assert!(Builder::build_for_account(&s, &AccountId([8; 32])).is_err());
+		assert!(Builder::build_for_account("0001020103039TWJXBACTSPAQ", &account).is_ok());
 	}
 }
@@ -0,0 +1,165 @@
+//! SCALE encoding of the `system.remark` calls carrying a token.
+//!
+//! The output can be pasted into the "decode call" field of any signer.
+
+use crate::{KnownNetwork, Network, Tokenize};
+use std::str::FromStr;
+
+/// The index of a call in a runtime: the index of its pallet and of the call within the pallet
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct CallIndex {
+	pub pallet: u8,
+	pub call: u8,
+}
+
+impl FromStr for CallIndex {
+	type Err = String;
+
+	/// Parses a call index given as `pallet:call`, such as `0:7`
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		let err = || format!("Invalid call index {:?}, expected pallet:call such as 0:7", s);
+		let (pallet, call) = s.split_once(':').ok_or_else(err)?;
+		Ok(CallIndex {
+			pallet: pallet.trim().parse().map_err(|_| err())?,
+			call: call.trim().parse().map_err(|_| err())?,
+		})
+	}
+}
+
+/// The calls of the System pallet that can carry a token
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum RemarkCall {
+	/// `system.remark`
+	Remark,
+	/// `system.remarkWithEvent`
+	RemarkWithEvent,
+}
+
+/// The indexes of the System pallet calls on a given network
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct CallIndexes {
+	pub system: u8,
+	pub remark: u8,
+	pub remark_with_event: u8,
+}
+
+impl Default for CallIndexes {
+	/// The System pallet is usually the first one and its calls use the `frame_system` indexes
+	fn default() -> Self {
+		Self { system: 0, remark: 0, remark_with_event: 7 }
+	}
+}
+
+impl From<Network> for CallIndexes {
+	/// All the known networks currently use the default indexes
+	fn from(network: Network) -> Self {
+		match network {
+			Network::Known(KnownNetwork::Polkadot) => CallIndexes::default(),
+			Network::Known(KnownNetwork::Kusama) => CallIndexes::default(),
+			Network::Known(KnownNetwork::Westend) => CallIndexes::default(),
+			Network::Unknown(_) => CallIndexes::default(),
+		}
+	}
+}
+
+impl CallIndexes {
+	pub fn index(&self, call: RemarkCall) -> CallIndex {
+		match call {
+			RemarkCall::Remark => CallIndex { pallet: self.system, call: self.remark },
+			RemarkCall::RemarkWithEvent => CallIndex { pallet: self.system, call: self.remark_with_event },
+		}
+	}
+}
+
+/// SCALE compact encoding of an integer
+pub fn compact_encode(n: u64) -> Vec<u8> {
+	match n {
+		0..=0x3f => vec![(n as u8) << 2],
+		0x40..=0x3fff => (((n as u16) << 2) | 0b01).to_le_bytes().to_vec(),
+		0x4000..=0x3fff_ffff => (((n as u32) << 2) | 0b10).to_le_bytes().to_vec(),
+		_ => {
+			let bytes = n.to_le_bytes();
+			let len = bytes.iter().rposition(|b| *b != 0).map(|p| p + 1).unwrap_or(1);
+			let mut res = vec![(((len - 4) as u8) << 2) | 0b11];
+			res.extend_from_slice(&bytes[..len]);
+			res
+		}
+	}
+}
+
+/// SCALE encodes a call taking a single `Vec<u8>` argument
+pub fn encode_call(index: CallIndex, remark: &[u8]) -> Vec<u8> {
+	let mut res = vec![index.pallet, index.call];
+	res.extend(compact_encode(remark.len() as u64));
+	res.extend_from_slice(remark);
+	res
+}
+
+/// Encodes bytes as a `0x` prefixed lowercase hex string
+pub fn to_hex(bytes: &[u8]) -> String {
+	let hex: String = bytes.iter().map(|b| format!("{:02x}", b)).collect();
+	format!("0x{}", hex)
+}
+
+/// Returns a link opening the decoded call in Polkadot.js Apps, if the network has a known public RPC endpoint
+pub fn polkadot_js_link(network: Network, call_data: &[u8]) -> Option<String> {
+	let rpc = match network {
+		Network::Known(KnownNetwork::Polkadot) => "wss://rpc.polkadot.io",
+		Network::Known(KnownNetwork::Kusama) => "wss://kusama-rpc.polkadot.io",
+		Network::Known(KnownNetwork::Westend) => "wss://westend-rpc.polkadot.io",
+		Network::Unknown(_) => return None,
+	};
+	Some(format!("https://polkadot.js.org/apps/?rpc={}#/extrinsics/decode/{}", rpc, to_hex(call_data)))
+}
+
+/// Returns the SCALE encoded call putting the token on chain, using the default call indexes of its network
+pub fn remark_call_data(token: &impl Tokenize, call: RemarkCall) -> Vec<u8> {
+	let index = CallIndexes::from(token.network()).index(call);
+	encode_call(index, token.format_string("").as_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::Builder;
+
+	#[test]
+	fn it_compact_encodes() {
+		assert_eq!(vec![0x00], compact_encode(0));
+		assert_eq!(vec![0x04], compact_encode(1));
+		assert_eq!(vec![0xfc], compact_encode(63));
+		assert_eq!(vec![0x01, 0x01], compact_encode(64));
+		assert_eq!(vec![0xfd, 0xff], compact_encode(16383));
+		assert_eq!(vec![0x02, 0x00, 0x01, 0x00], compact_encode(16384));
+		assert_eq!(vec![0xfe, 0xff, 0xff, 0xff], compact_encode(0x3fff_ffff));
+		assert_eq!(vec![0x03, 0x00, 0x00, 0x00, 0x40], compact_encode(0x4000_0000));
+		assert_eq!(vec![0x13, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff], compact_encode(u64::MAX));
+	}
+
+	#[test]
+	fn it_parses_call_indexes() {
+		assert_eq!(Ok(CallIndex { pallet: 0, call: 7 }), "0:7".parse());
+		assert!("0".parse::<CallIndex>().is_err());
+		assert!("0:256".parse::<CallIndex>().is_err());
+	}
+
+	#[test]
+	fn it_encodes_a_remark() {
+		// system.remark("hello") on Polkadot
+		let call = encode_call(CallIndexes::default().index(RemarkCall::Remark), b"hello");
+		assert_eq!("0x00001468656c6c6f", to_hex(&call));
+	}
+
+	#[test]
+	fn it_encodes_a_token() {
+		let token = Builder::build_with_variant("0001020103039TWJXBACTSPAQ").unwrap();
+		let call = remark_call_data(&token, RemarkCall::RemarkWithEvent);
+		assert_eq!(&[0x00, 0x07, 0x64], &call[..3]);
+		assert_eq!(b"0001020103039TWJXBACTSPAQ", &call[3..]);
+
+		let link = polkadot_js_link(token.network(), &call).unwrap();
+		assert!(link
+			.starts_with("https://polkadot.js.org/apps/?rpc=wss://kusama-rpc.polkadot.io#/extrinsics/decode/0x000764"));
+		assert!(polkadot_js_link(Network::Unknown(99), &call).is_none());
+	}
+}
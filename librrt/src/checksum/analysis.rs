@@ -0,0 +1,268 @@
+//! Measures how well a checksum detects the typical errors users make when copying a token.
+//!
+//! Each registered checksum is run over a set of token strings. For each kind of [Mistake],
+//! we alter the tokens and count how often the checksum changes. In [Mode::Exhaustive],
+//! every possible mistake of each token is tried, in [Mode::Random] a single one per token.
+
+use crate::checksum::{Checksum, ChecksumV00, ChecksumV01};
+use crate::types::{Alphabet, Channel, Version};
+use crate::versions::TokenV01;
+use rand::seq::SliceRandom;
+use rand::{CryptoRng, Rng, RngCore};
+use std::fmt::Display;
+
+/// The chars that may be typed instead of another one
+const CHARS: &[u8] = b"0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZ";
+
+/// The chi-square value above which a distribution over 26 letters (25 degrees of freedom)
+/// is not uniform, with a 5% risk of being wrong
+pub const CHI_SQUARE_CRITICAL: f64 = 37.652;
+
+/// A checksum that can be analyzed, whatever the type of its output
+pub struct Candidate {
+	pub name: &'static str,
+	pub calculate: fn(&[u8]) -> Vec<u8>,
+}
+
+/// Returns the checksums of the token versions
+pub fn candidates() -> Vec<Candidate> {
+	vec![
+		Candidate { name: "V00", calculate: |data| vec![ChecksumV00::new().calculate(data)] },
+		Candidate { name: "V01", calculate: |data| ChecksumV01::new().calculate(data).to_vec() },
+	]
+}
+
+/// The errors a user may make while copying a token
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Mistake {
+	/// A char is replaced by another one: ABC -> ABD
+	Substitution,
+	/// Two neighbours are swapped: ABC -> BAC
+	AdjacentTransposition,
+	/// Two chars separated by another one are swapped: ABC -> CBA
+	JumpTransposition,
+	/// A doubled char is replaced by another doubled char: AAB -> CCB
+	Twin,
+	/// Numbers that sound alike: 13 <-> 30, 14 <-> 40...
+	Phonetic,
+}
+
+impl Mistake {
+	pub const ALL: [Mistake; 5] = [
+		Mistake::Substitution,
+		Mistake::AdjacentTransposition,
+		Mistake::JumpTransposition,
+		Mistake::Twin,
+		Mistake::Phonetic,
+	];
+
+	/// Returns all the strings `s` may become with this mistake
+	pub fn variants(&self, s: &[u8]) -> Vec<Vec<u8>> {
+		let mut variants = Vec::new();
+		let with = |changes: &[(usize, u8)]| {
+			let mut v = s.to_vec();
+			for (i, c) in changes {
+				v[*i] = *c;
+			}
+			v
+		};
+
+		for i in 0..s.len() {
+			match self {
+				Mistake::Substitution => {
+					variants.extend(CHARS.iter().filter(|c| **c != s[i]).map(|c| with(&[(i, *c)])));
+				}
+				Mistake::AdjacentTransposition if i + 1 < s.len() && s[i] != s[i + 1] => {
+					variants.push(with(&[(i, s[i + 1]), (i + 1, s[i])]));
+				}
+				Mistake::JumpTransposition if i + 2 < s.len() && s[i] != s[i + 2] => {
+					variants.push(with(&[(i, s[i + 2]), (i + 2, s[i])]));
+				}
+				Mistake::Twin if i + 1 < s.len() && s[i] == s[i + 1] => {
+					variants.extend(CHARS.iter().filter(|c| **c != s[i]).map(|c| with(&[(i, *c), (i + 1, *c)])));
+				}
+				Mistake::Phonetic if i + 1 < s.len() => match (s[i], s[i + 1]) {
+					(b'1', d @ b'3'..=b'9') => variants.push(with(&[(i, d), (i + 1, b'0')])),
+					(d @ b'3'..=b'9', b'0') => variants.push(with(&[(i, b'1'), (i + 1, d)])),
+					_ => {}
+				},
+				_ => {}
+			}
+		}
+		variants
+	}
+}
+
+impl Display for Mistake {
+	fn fmt(&self, fmt: &mut std::fmt::Formatter<'_>) -> std::result::Result<(), std::fmt::Error> {
+		let str = match self {
+			Mistake::Substitution => "substitution",
+			Mistake::AdjacentTransposition => "adjacent",
+			Mistake::JumpTransposition => "jump",
+			Mistake::Twin => "twin",
+			Mistake::Phonetic => "phonetic",
+		};
+		write!(fmt, "{}", str)
+	}
+}
+
+/// How the mistakes are picked
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Mode {
+	/// One random mistake of each kind per token
+	Random,
+	/// All the possible mistakes of each token
+	Exhaustive,
+}
+
+/// How many of the altered tokens were detected
+#[derive(Debug, PartialEq, Clone, Copy, Default)]
+pub struct Rate {
+	pub detected: usize,
+	pub total: usize,
+}
+
+impl Rate {
+	pub fn percent(&self) -> f64 {
+		match self.total {
+			0 => 100.0,
+			total => 100.0 * self.detected as f64 / total as f64,
+		}
+	}
+}
+
+/// The analysis of a checksum
+#[derive(Debug, PartialEq, Clone)]
+pub struct Report {
+	pub name: &'static str,
+	pub samples: usize,
+	/// The worst chi-square of the letters of the checksum, one letter at a time
+	pub chi_square: f64,
+	/// The detection rate of each kind of mistake, in the order of [Mistake::ALL]
+	pub rates: Vec<(Mistake, Rate)>,
+}
+
+/// Returns `count` random V01 token strings, without their checksum
+pub fn sample_tokens<R: RngCore + CryptoRng>(rng: &mut R, count: usize) -> Vec<Vec<u8>> {
+	let channels = [Channel::Twitter, Channel::Email, Channel::Matrix];
+	(0..count)
+		.map(|_| {
+			let secret = Alphabet::Full.random_with(rng, 8);
+			let channel = *channels.choose(rng).unwrap_or(&Channel::Twitter);
+			let raw = TokenV01::format_raw(
+				0,
+				Version::V01,
+				rng.gen(),
+				rng.gen(),
+				rng.gen_range(0..=0xFFFFF),
+				channel,
+				&secret,
+			);
+			raw.into_bytes()
+		})
+		.collect()
+}
+
+/// Returns the chi-square of the distribution of `letters` over A-Z
+pub fn chi_square(letters: impl Iterator<Item = u8>) -> f64 {
+	let mut counts = [0usize; 26];
+	let mut n = 0;
+	for letter in letters.filter(|l| l.is_ascii_uppercase()) {
+		counts[(letter - b'A') as usize] += 1;
+		n += 1;
+	}
+	let expected = n as f64 / 26.0;
+	counts.iter().map(|c| (*c as f64 - expected).powi(2) / expected).sum()
+}
+
+/// Runs the analysis of `candidate` over `samples`
+pub fn analyze<R: Rng>(candidate: &Candidate, samples: &[Vec<u8>], mode: Mode, rng: &mut R) -> Report {
+	let checksums: Vec<Vec<u8>> = samples.iter().map(|s| (candidate.calculate)(s)).collect();
+	let width = checksums.iter().map(|c| c.len()).max().unwrap_or_default();
+	let chi_square =
+		(0..width).map(|i| chi_square(checksums.iter().filter_map(|c| c.get(i).copied()))).fold(0.0, f64::max);
+
+	let rates = Mistake::ALL
+		.iter()
+		.map(|mistake| {
+			let mut rate = Rate::default();
+			for (sample, checksum) in samples.iter().zip(&checksums) {
+				let variants = mistake.variants(sample);
+				let picked: Vec<&Vec<u8>> = match mode {
+					Mode::Exhaustive => variants.iter().collect(),
+					Mode::Random => variants.choose(rng).into_iter().collect(),
+				};
+				for variant in picked {
+					rate.total += 1;
+					if (candidate.calculate)(variant) != *checksum {
+						rate.detected += 1;
+					}
+				}
+			}
+			(*mistake, rate)
+		})
+		.collect();
+
+	Report { name: candidate.name, samples: samples.len(), chi_square, rates }
+}
+
+/// Formats the reports as a table
+pub fn report_table(reports: &[Report]) -> String {
+	let mut table = format!("{:<9} {:>8} {:>10}", "checksum", "samples", "chi²");
+	for mistake in Mistake::ALL.iter() {
+		table.push_str(&format!(" {:>12}", mistake.to_string()));
+	}
+	for report in reports {
+		let biased = if report.chi_square > CHI_SQUARE_CRITICAL { "*" } else { " " };
+		table.push_str(&format!("\n{:<9} {:>8} {:>9.1}{}", report.name, report.samples, report.chi_square, biased));
+		for (_, rate) in &report.rates {
+			table.push_str(&format!(" {:>11.2}%", rate.percent()));
+		}
+	}
+	table.push_str(&format!("\n* the letters are not uniformly distributed (chi² > {})", CHI_SQUARE_CRITICAL));
+	table
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use rand::rngs::StdRng;
+	use rand::SeedableRng;
+
+	fn rate(report: &Report, mistake: Mistake) -> Rate {
+		report.rates.iter().find(|(m, _)| *m == mistake).map(|(_, r)| *r).unwrap()
+	}
+
+	#[test]
+	fn it_lists_the_variants() {
+		assert_eq!(35 * 3, Mistake::Substitution.variants(b"A1B").len());
+		assert_eq!(vec![b"BAC".to_vec(), b"ACB".to_vec()], Mistake::AdjacentTransposition.variants(b"ABC"));
+		assert_eq!(vec![b"CBA".to_vec()], Mistake::JumpTransposition.variants(b"ABC"));
+		assert_eq!(35, Mistake::Twin.variants(b"AAB").len());
+		assert_eq!(vec![b"A30".to_vec()], Mistake::Phonetic.variants(b"A13"));
+		assert_eq!(vec![b"15Z".to_vec()], Mistake::Phonetic.variants(b"50Z"));
+	}
+
+	#[test]
+	fn it_calculates_the_chi_square() {
+		assert_eq!(0.0, chi_square((b'A'..=b'Z').cycle().take(26 * 10)));
+		assert_eq!(25.0 * 26.0, chi_square(std::iter::repeat_n(b'A', 26)));
+	}
+
+	#[test]
+	fn it_analyzes_the_checksums() {
+		let mut rng = StdRng::seed_from_u64(1);
+		let samples = sample_tokens(&mut rng, 200);
+		let reports: Vec<Report> =
+			candidates().iter().map(|c| analyze(c, &samples, Mode::Exhaustive, &mut rng)).collect();
+
+		// V00 is a plain sum: it never sees a swap
+		assert_eq!(0, rate(&reports[0], Mistake::AdjacentTransposition).detected);
+		assert!(rate(&reports[1], Mistake::AdjacentTransposition).percent() > 90.0);
+		assert!(rate(&reports[1], Mistake::Substitution).percent() > 95.0);
+
+		let table = report_table(&reports);
+		assert_eq!(4, table.lines().count());
+		assert!(table.lines().nth(2).unwrap().starts_with("V01"));
+	}
+}
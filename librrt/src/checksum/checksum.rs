@@ -17,9 +17,9 @@ pub enum ChecksumOutput {
 	Dual([u8; 2]),
 }
 
-impl Into<Vec<u8>> for ChecksumOutput {
-	fn into(self) -> Vec<u8> {
-		match self {
+impl From<ChecksumOutput> for Vec<u8> {
+	fn from(c: ChecksumOutput) -> Self {
+		match c {
 			ChecksumOutput::Single(s) => vec![s],
 			ChecksumOutput::Dual(d) => d.into(),
 		}
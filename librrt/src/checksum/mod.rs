@@ -2,6 +2,8 @@ pub use checksum::*;
 pub use checksum_v00::*;
 pub use checksum_v01::*;
 
+pub mod analysis;
+#[allow(clippy::module_inception)]
 mod checksum;
 mod checksum_v00;
 mod checksum_v01;
@@ -0,0 +1,308 @@
+//! A compact binary form of the tokens, for storage and on chain.
+//!
+//! The bytes are the app, version, network and index, then:
+//! - up to V03: the case id and the channel packed in 3 bytes as `case_id << 4 | channel`
+//! - V04: the channel then the case id as a LEB128 varint
+//! - V03 only: the length of the secret
+//!
+//! and finally the secret followed by the checksum, as a base 36 number.
+//! A V01 token takes 14 bytes.
+//!
+//! Decoding builds the text form and parses it, so the checksum is checked and the binary
+//! and text forms of a token always round-trip through each other.
+//! With the `codec` feature, [Token] also implements the SCALE `Encode` and `Decode` traits.
+
+use crate::builder::Builder;
+use crate::call::to_hex;
+use crate::error::Error;
+use crate::types::{AccountId, Channel, Version};
+use crate::utils::dec2hex;
+use crate::versions::{Token, TokenV04, Tokenize};
+use std::convert::TryFrom;
+
+/// The chars a token is made of, the digit of each char being its position
+const BASE36: &[u8] = b"0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZ";
+
+fn channel_code(channel: Channel) -> u8 {
+	match channel {
+		Channel::Unknown => 0,
+		Channel::Email => 1,
+		Channel::Matrix => 2,
+		Channel::Twitter => 3,
+	}
+}
+
+fn channel_from_code(code: u8) -> Option<Channel> {
+	[Channel::Unknown, Channel::Email, Channel::Matrix, Channel::Twitter].get(usize::from(code)).copied()
+}
+
+/// The number of bytes holding `chars` base 36 digits
+fn packed_len(chars: usize) -> usize {
+	let max = 36u128.pow(chars as u32) - 1;
+	(128 - max.leading_zeros() as usize).div_ceil(8)
+}
+
+/// Packs `s` as a base 36 number, or returns None if it has other chars than 0-9 and A-Z
+fn pack(s: &str, out: &mut Vec<u8>) -> Option<()> {
+	let mut value = 0u128;
+	for c in s.bytes() {
+		value = value * 36 + BASE36.iter().position(|x| *x == c)? as u128;
+	}
+	out.extend_from_slice(&value.to_be_bytes()[16 - packed_len(s.len())..]);
+	Some(())
+}
+
+fn unpack(bytes: &[u8], chars: usize) -> Option<String> {
+	let mut value = bytes.iter().fold(0u128, |v, b| (v << 8) | u128::from(*b));
+	if value >= 36u128.pow(chars as u32) {
+		return None;
+	}
+	let mut s = vec![0; chars];
+	for c in s.iter_mut().rev() {
+		*c = BASE36[(value % 36) as usize];
+		value /= 36;
+	}
+	String::from_utf8(s).ok()
+}
+
+impl Token {
+	/// Returns the binary form of the token.
+	/// Fails with [Error::InvalidSecret] if the secret has other chars than 0-9 and A-Z, which a parsed token
+	/// cannot have but a token built with `new_with_secret` may.
+	pub fn to_bytes(&self) -> Result<Vec<u8>, Error> {
+		let mut out = vec![*self.app(), *self.version() as u8, u8::from(self.network()), *self.index()];
+		let channel = channel_code(*self.channel());
+		match self.version() {
+			Version::V04 => {
+				out.push(channel);
+				let mut case_id = *self.case_id();
+				loop {
+					let byte = (case_id & 0x7F) as u8;
+					case_id >>= 7;
+					match case_id {
+						0 => break out.push(byte),
+						_ => out.push(byte | 0x80),
+					}
+				}
+			}
+			_ => out.extend_from_slice(&((*self.case_id() as u32) << 4 | u32::from(channel)).to_be_bytes()[1..]),
+		}
+		if *self.version() == Version::V03 {
+			out.push(self.secret().len() as u8);
+		}
+		pack(&format!("{}{}", self.secret(), self.checksum()), &mut out)
+			.ok_or_else(|| Error::InvalidSecret { version: *self.version(), secret: self.secret().clone() })?;
+		Ok(out)
+	}
+
+	/// Decodes a token from its binary form. V02 tokens need [Token::from_bytes_for_account].
+	pub fn from_bytes(bytes: &[u8]) -> Result<Self, Error> {
+		Self::decode_exact(bytes, Builder::build_with_variant)
+	}
+
+	/// Like `from_bytes` but also accepts the tokens whose checksum covers the account, such as V02
+	pub fn from_bytes_for_account(bytes: &[u8], account: &AccountId) -> Result<Self, Error> {
+		Self::decode_exact(bytes, |s| Builder::build_for_account(s, account))
+	}
+
+	fn decode_exact<F: Fn(&str) -> Result<Token, Error>>(bytes: &[u8], build: F) -> Result<Self, Error> {
+		let mut rest = bytes;
+		let token = Self::decode_with(
+			|n| {
+				let (read, tail) = (rest.get(..n)?, rest.get(n..)?);
+				rest = tail;
+				Some(read.to_vec())
+			},
+			build,
+		)?;
+		match rest.is_empty() {
+			true => Ok(token),
+			false => Err(Error::InvalidEncoding(to_hex(bytes))),
+		}
+	}
+
+	/// Decodes a token, pulling its bytes with `read` which returns the next `n` bytes
+	fn decode_with<R, F>(mut read: R, build: F) -> Result<Self, Error>
+	where
+		R: FnMut(usize) -> Option<Vec<u8>>,
+		F: Fn(&str) -> Result<Token, Error>,
+	{
+		let invalid = || Error::InvalidEncoding(String::from("Invalid binary token"));
+		let header = read(4).ok_or_else(invalid)?;
+		let version = Version::try_from(header[1]).map_err(|_| invalid())?;
+		let mut text: String = header.iter().map(|b| dec2hex(*b, 2)).collect();
+
+		match version {
+			Version::V04 => {
+				let channel = channel_from_code(read(1).ok_or_else(invalid)?[0]).ok_or_else(invalid)?;
+				let mut case_id = 0u64;
+				for shift in (0..64).step_by(7) {
+					let byte = read(1).ok_or_else(invalid)?[0];
+					// The last of the 10 bytes only holds the top bit of the case id
+					if shift == 63 && byte > 1 {
+						return Err(invalid());
+					}
+					case_id |= u64::from(byte & 0x7F) << shift;
+					if byte & 0x80 == 0 {
+						// Only the shortest encoding is accepted, so a token has a single binary form
+						if byte == 0 && shift > 0 {
+							return Err(invalid());
+						}
+						break;
+					}
+				}
+				let len = TokenV04::case_id_digits(case_id);
+				text += &format!("{}{}{}", dec2hex(len - 1, 1), dec2hex(case_id, len), channel);
+			}
+			_ => {
+				let packed = read(3).ok_or_else(invalid)?.iter().fold(0u32, |v, b| (v << 8) | u32::from(*b));
+				let channel = channel_from_code((packed & 0xF) as u8).ok_or_else(invalid)?;
+				text += &format!("{}{}", dec2hex(packed >> 4, 5), channel);
+			}
+		}
+
+		let secret_len = match version {
+			Version::V03 => {
+				let len = read(1).ok_or_else(invalid)?[0];
+				if !(1..=15).contains(&len) {
+					return Err(invalid());
+				}
+				text += &dec2hex(len, 1);
+				usize::from(len)
+			}
+			_ => 8,
+		};
+		let chars = secret_len + version.checksum_len();
+		text += &unpack(&read(packed_len(chars)).ok_or_else(invalid)?, chars).ok_or_else(invalid)?;
+
+		let token = build(&text)?;
+		// The confusables of the secret are fixed when building, but the bytes must encode the exact token
+		match token.format_string("") == text {
+			true => Ok(token),
+			false => Err(invalid()),
+		}
+	}
+}
+
+/// Encoding cannot fail: a token that has no binary form, see [Token::to_bytes], is encoded
+/// as its header with an invalid version so that decoding it fails.
+#[cfg(feature = "codec")]
+impl codec::Encode for Token {
+	fn encode_to<T: codec::Output + ?Sized>(&self, dest: &mut T) {
+		match self.to_bytes() {
+			Ok(bytes) => dest.write(&bytes),
+			Err(_) => dest.write(&[*self.app(), u8::MAX, u8::from(self.network()), *self.index()]),
+		}
+	}
+}
+
+#[cfg(feature = "codec")]
+impl codec::EncodeLike for Token {}
+
+/// V02 tokens cannot be decoded since their checksum depends on the account
+#[cfg(feature = "codec")]
+impl codec::Decode for Token {
+	fn decode<I: codec::Input>(input: &mut I) -> Result<Self, codec::Error> {
+		let read = |n: usize| {
+			let mut buf = vec![0; n];
+			input.read(&mut buf).ok().map(|_| buf)
+		};
+		Self::decode_with(read, Builder::build_with_variant).map_err(|_| codec::Error::from("Invalid token"))
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::{TokenV00, TokenV01, TokenV02, TokenV03, V03_DEFAULT_SECRET_LENGTH};
+
+	fn tokens() -> Vec<Token> {
+		let mut tokens = vec![
+			Token::V00(TokenV00::new(0, Version::V00, 0.into(), 1, 0xFFFFF, Channel::Twitter)),
+			Token::V01(TokenV01::new_with_secret(0, Version::V01, 2, 1, 12345, Channel::Twitter, "JXBACTSP")),
+			Token::V01(TokenV01::new(255, Version::V01, 255, 255, 0, Channel::Unknown)),
+			Token::V04(TokenV04::new(0, 42, 1, 0, Channel::Matrix)),
+			Token::V04(TokenV04::new(0, 42, 1, u64::MAX, Channel::Email)),
+		];
+		for length in 8..=15 {
+			tokens.push(Token::V03(TokenV03::new(0, 2, 1, 12345, Channel::Email, length)));
+		}
+		tokens
+	}
+
+	#[test]
+	fn it_round_trips_every_version() {
+		for token in tokens() {
+			let bytes = token.to_bytes().unwrap();
+			let decoded = Token::from_bytes(&bytes).unwrap();
+			assert_eq!(token.to_string(), decoded.to_string());
+			assert_eq!(bytes, Builder::build_with_variant(&token.to_string()).unwrap().to_bytes().unwrap());
+		}
+	}
+
+	#[test]
+	fn it_is_compact() {
+		let token = Builder::build_with_variant("0001020103039TWJXBACTSPAQ").unwrap();
+		assert_eq!(14, token.to_bytes().unwrap().len());
+		let token = Builder::build_with_variant("0000000012345TWRAJQFIZWW").unwrap();
+		assert_eq!(13, token.to_bytes().unwrap().len());
+		let token = TokenV03::new(0, 2, 1, 12345, Channel::Email, V03_DEFAULT_SECRET_LENGTH);
+		assert_eq!(18, Token::V03(token).to_bytes().unwrap().len());
+	}
+
+	#[test]
+	fn it_needs_the_account_for_v02() {
+		let account = AccountId([7; 32]);
+		let token = Token::V02(TokenV02::new(0, 2, 1, 12345, Channel::Twitter, &account));
+		let bytes = token.to_bytes().unwrap();
+		assert_eq!(Err(Error::AccountRequired), Token::from_bytes(&bytes).map(|_| ()));
+		assert_eq!(token.to_string(), Token::from_bytes_for_account(&bytes, &account).unwrap().to_string());
+	}
+
+	#[test]
+	fn it_rejects_invalid_bytes() {
+		let bytes = Builder::build_with_variant("0001020103039TWJXBACTSPAQ").unwrap().to_bytes().unwrap();
+		assert!(Token::from_bytes(&bytes[..13]).is_err());
+		assert!(Token::from_bytes(&[&bytes[..], &[0]].concat()).is_err());
+		assert!(Token::from_bytes(&[]).is_err());
+
+		let mut wrong = bytes.clone();
+		wrong[13] ^= 1;
+		assert!(matches!(Token::from_bytes(&wrong), Err(Error::ChecksumError(_))));
+
+		let mut channel = bytes;
+		channel[6] |= 0xF;
+		assert!(Token::from_bytes(&channel).is_err());
+
+		// A case id of V04 encoded with a useless trailing byte
+		let token = Token::V04(TokenV04::new(0, 42, 1, 5, Channel::Matrix));
+		let bytes = token.to_bytes().unwrap();
+		let padded = [&bytes[..5], &[bytes[5] | 0x80, 0], &bytes[6..]].concat();
+		assert!(Token::from_bytes(&padded).is_err());
+	}
+
+	#[test]
+	fn it_rejects_secrets_it_cannot_pack() {
+		let token = Token::V01(TokenV01::new_with_secret(0, Version::V01, 2, 1, 12345, Channel::Twitter, "jxbactsp"));
+		assert!(matches!(token.to_bytes(), Err(Error::InvalidSecret { version: Version::V01, .. })));
+		#[cfg(feature = "codec")]
+		{
+			use codec::{Decode, Encode};
+			assert!(Token::decode(&mut &token.encode()[..]).is_err());
+		}
+	}
+
+	#[cfg(feature = "codec")]
+	#[test]
+	fn it_implements_scale() {
+		use codec::{Decode, Encode};
+
+		let tokens = tokens();
+		let encoded = tokens.encode();
+		let decoded = Vec::<Token>::decode(&mut &encoded[..]).unwrap();
+		assert_eq!(
+			tokens.iter().map(|t| t.to_string()).collect::<Vec<_>>(),
+			decoded.iter().map(|t| t.to_string()).collect::<Vec<_>>()
+		);
+	}
+}
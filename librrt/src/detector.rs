@@ -1,5 +1,7 @@
+use crate::envelope;
 use crate::error::*;
 use crate::types::Version;
+use crate::utils::clean_token_string;
 use crate::Error::LengthError;
 use std::str::FromStr;
 
@@ -14,15 +16,27 @@ impl Detector {
 	/// the detected version and the size of the string
 	pub fn analyze(s: &str) -> Result<(Option<u8>, Option<Version>, usize), Error> {
 		match s.len() {
-			x if x < 2 => Err(LengthError(2, s.len())),
+			x if x < 4 => Err(LengthError { expected: 4, found: s.len() }),
 			_ => {
-				let app = u8::from_str_radix(&s[0..2], 16).unwrap();
-				let version_str = &s[2..4];
+				// A token is ASCII, slicing anything else could split a char
+				let invalid = || Error::InvalidEncoding(String::from(s));
+				let app = s.get(0..2).filter(|app| app.bytes().all(|b| b.is_ascii_hexdigit())).ok_or_else(invalid)?;
+				let app = u8::from_str_radix(app, 16).map_err(|_| invalid())?;
+				let version_str = s.get(2..4).ok_or_else(invalid)?;
 				let version = Version::from_str(version_str)?;
 				Ok((Some(app), Some(version), s.len()))
 			}
 		}
 	}
+
+	/// Returns the version of a token given in its compact form, with or without separators, or in its envelope
+	pub fn version(s: &str) -> Option<Version> {
+		let cleaned = match envelope::is_envelope(s.trim()) {
+			true => envelope::unwrap(s).ok()?.1,
+			false => clean_token_string(s),
+		};
+		Self::analyze(&cleaned).ok()?.1
+	}
 }
 
 #[cfg(test)]
@@ -42,10 +56,23 @@ mod tests_detector {
 		}
 	}
 
+	#[test]
+	fn it_detects_the_version_of_any_form() {
+		let token = crate::Builder::build_with_variant("0001020103039TWJXBACTSPAQ").unwrap();
+		assert_eq!(Some(Version::V01), Detector::version("00-01-02-01-03039-TW-JXBACTSP-AQ"));
+		assert_eq!(Some(Version::V01), Detector::version(&crate::Tokenize::to_envelope(&token)));
+		assert_eq!(None, Detector::version("rrt1-ksm-junk"));
+	}
+
 	#[test]
 	fn it_catches_errors() {
 		assert!(Detector::analyze("A").is_err());
+		assert_eq!(Err(LengthError { expected: 4, found: 3 }), Detector::analyze("000"));
+		assert_eq!(Err(Error::InvalidEncoding("junk".into())), Detector::analyze("junk"));
 
-		assert_eq!(Err(Error::Version(UnsupportedVersion(2))), Detector::analyze("0202000012345TWRAJQFIZWF"));
+		assert_eq!(Err(Error::Version(UnsupportedVersion(99))), Detector::analyze("0299000012345TWRAJQFIZWF"));
+		assert_eq!(Err(Error::InvalidEncoding("Ἐa".into())), Detector::analyze("Ἐa"));
+		assert_eq!(Err(Error::InvalidEncoding("0é01".into())), Detector::analyze("0é01"));
+		assert_eq!(Err(Error::InvalidEncoding("+F01".into())), Detector::analyze("+F01"));
 	}
 }
@@ -0,0 +1,119 @@
+//! A textual envelope around the compact tokens, so they are not mistaken for other codes in chats:
+//!
+//! `rrt1-ksm-0001020103039twjxbactspaq-esvvmc`
+//!
+//! It starts with `rrt1`, the `1` being the version of the envelope, followed by the short name
+//! of the network, the token and a BCH checksum over the whole string.
+//! The checksum is the one of bech32, computed over each char of the string the way bech32 does for
+//! its human readable part, since the chars of a token are not limited to the bech32 alphabet.
+//! Envelopes are written in lower case but read in any case.
+
+use crate::error::Error;
+use crate::types::Network;
+
+/// The start of every envelope
+pub const ENVELOPE_PREFIX: &str = "rrt1";
+
+/// The chars of the checksum
+const CHARSET: &[u8] = b"qpzry9x8gf2tvdw0s3jn54khce6mua7l";
+
+/// The generator of the bech32 BCH code
+const GENERATOR: [u32; 5] = [0x3b6a57b2, 0x26508e6d, 0x1ea119fa, 0x3d4233dd, 0x2a1462b3];
+
+const CHECKSUM_LEN: usize = 6;
+
+fn polymod(values: impl Iterator<Item = u8>) -> u32 {
+	values.fold(1, |chk, v| {
+		let top = chk >> 25;
+		let chk = ((chk & 0x1ffffff) << 5) ^ u32::from(v);
+		(0..5).filter(|i| (top >> i) & 1 == 1).fold(chk, |chk, i| chk ^ GENERATOR[i])
+	})
+}
+
+/// Splits each char in its high and low bits, as bech32 does for the human readable part
+fn expand(s: &str) -> impl Iterator<Item = u8> + '_ {
+	s.bytes().map(|c| c >> 5).chain(std::iter::once(0)).chain(s.bytes().map(|c| c & 31))
+}
+
+fn checksum(s: &str) -> String {
+	let pm = polymod(expand(s).chain([0; CHECKSUM_LEN])) ^ 1;
+	(0..CHECKSUM_LEN).map(|i| CHARSET[((pm >> (5 * (5 - i))) & 31) as usize] as char).collect()
+}
+
+/// Returns the envelope of a token given without separators
+pub(crate) fn wrap(network: Network, token: &str) -> String {
+	let body = format!("{}-{}-{}", ENVELOPE_PREFIX, network.short_name(), token.to_lowercase());
+	let checksum = checksum(&body);
+	format!("{}-{}", body, checksum)
+}
+
+/// Checks an envelope and returns the network it names and the token, in upper case
+pub(crate) fn unwrap(s: &str) -> Result<(Network, String), Error> {
+	let invalid = |msg: &str| Error::InvalidEnvelope(format!("{}: {}", msg, s));
+	let s = s.trim().to_lowercase();
+	let (body, checksum) = s.rsplit_once('-').ok_or_else(|| invalid("No checksum"))?;
+	let values: Option<Vec<u8>> =
+		checksum.bytes().map(|c| CHARSET.iter().position(|x| *x == c).map(|v| v as u8)).collect();
+	match values {
+		Some(values) if values.len() == CHECKSUM_LEN && polymod(expand(body).chain(values.iter().copied())) == 1 => {}
+		_ => return Err(invalid("Wrong checksum")),
+	}
+
+	let rest = body
+		.strip_prefix(ENVELOPE_PREFIX)
+		.and_then(|r| r.strip_prefix('-'))
+		.ok_or_else(|| invalid("Not an envelope"))?;
+	let (network, token) = rest.split_once('-').ok_or_else(|| invalid("No network"))?;
+	let network = Network::from_short_name(network).ok_or_else(|| invalid("Unknown network"))?;
+	Ok((network, token.to_uppercase()))
+}
+
+/// Returns true if `s` starts like an envelope
+pub(crate) fn is_envelope(s: &str) -> bool {
+	s.get(..ENVELOPE_PREFIX.len() + 1).is_some_and(|p| p.eq_ignore_ascii_case(&format!("{}-", ENVELOPE_PREFIX)))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn it_matches_bech32() {
+		// The checksum of the BIP 173 test vector `a12uel5l`, whose data part is empty
+		assert_eq!("2uel5l", checksum("a"));
+	}
+
+	#[test]
+	fn it_wraps_and_unwraps() {
+		let envelope = wrap(Network::from(2), "0001020103039TWJXBACTSPAQ");
+		assert_eq!("rrt1-ksm-0001020103039twjxbactspaq-esvvmc", envelope);
+		assert_eq!(Ok((Network::from(2), String::from("0001020103039TWJXBACTSPAQ"))), unwrap(&envelope));
+		assert!(unwrap(&envelope.to_uppercase()).is_ok());
+		assert!(is_envelope(&envelope.to_uppercase()));
+		assert!(!is_envelope("0001020103039TWJXBACTSPAQ"));
+	}
+
+	#[test]
+	fn it_detects_typos() {
+		let envelope = wrap(Network::from(2), "0001020103039TWJXBACTSPAQ");
+		for i in 0..envelope.len() {
+			for c in "0123456789abcdefghijklmnopqrstuvwxyz-".chars() {
+				if envelope[i..].starts_with(c) {
+					continue;
+				}
+				let typo = format!("{}{}{}", &envelope[..i], c, &envelope[i + 1..]);
+				assert!(unwrap(&typo).is_err(), "{} was accepted", typo);
+			}
+		}
+
+		let swapped = envelope.replace("0103039", "0130039");
+		assert!(matches!(unwrap(&swapped), Err(Error::InvalidEnvelope(_))));
+	}
+
+	#[test]
+	fn it_rejects_unknown_networks() {
+		let body = "rrt1-btc-0001020103039twjxbactspaq";
+		let envelope = format!("{}-{}", body, checksum(body));
+		assert!(matches!(unwrap(&envelope), Err(Error::InvalidEnvelope(msg)) if msg.starts_with("Unknown network")));
+	}
+}
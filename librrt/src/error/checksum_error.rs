@@ -1,5 +1,5 @@
 use crate::ChecksumOutput;
-use std::fmt::Debug;
+use std::fmt::{Debug, Display};
 
 #[derive(PartialEq)]
 pub struct ChecksumError {
@@ -12,9 +12,19 @@ impl ChecksumError {
 	pub fn new(string: String, expected: ChecksumOutput, found: ChecksumOutput) -> Self {
 		Self { string, expected, found }
 	}
+
+	/// The checksum calculated from the content of the token
+	pub fn expected(&self) -> &ChecksumOutput {
+		&self.expected
+	}
+
+	/// The checksum found in the token
+	pub fn found(&self) -> &ChecksumOutput {
+		&self.found
+	}
 }
 
-impl<'a> Debug for ChecksumError {
+impl Debug for ChecksumError {
 	fn fmt(&self, fmt: &mut std::fmt::Formatter<'_>) -> std::result::Result<(), std::fmt::Error> {
 		let found_str = &self.found.to_string();
 		let expected_str = &self.expected.to_string();
@@ -26,3 +36,11 @@ impl<'a> Debug for ChecksumError {
 		)
 	}
 }
+
+impl Display for ChecksumError {
+	fn fmt(&self, fmt: &mut std::fmt::Formatter<'_>) -> std::result::Result<(), std::fmt::Error> {
+		write!(fmt, "Wrong checksum for {}: found {}, expected {}", self.string, self.found, self.expected)
+	}
+}
+
+impl std::error::Error for ChecksumError {}
@@ -1,5 +1,8 @@
 use crate::error::checksum_error::ChecksumError;
-use crate::types::VersionError;
+use crate::explainer::Field;
+use crate::revocation::Revocation;
+use crate::types::{AccountId, Version, VersionError};
+use std::fmt::Display;
 
 /// The Errors that RRT may throw.
 #[derive(Debug, PartialEq)]
@@ -12,18 +15,81 @@ pub enum Error {
 
 	UnknownChannel(u8),
 
-	// TODO: use a srtuct to avoid having to document that
-	/// The input string does not match the format (often the length) expected
-	/// for the version. For instance, a V00 that is not 20 chars, or a V01 that is not 21 chars.
-	/// The tuple is (expected, found)
-	LengthError(usize, usize),
+	/// The input string does not have the length expected for the version.
+	/// For instance, a V00 that is not 24 chars, or a V01 that is not 25 chars.
+	LengthError {
+		expected: usize,
+		found: usize,
+	},
 
-	/// Some of the fields are encoded in hex. So those fields should be valid hexs strings.
-	/// This error be thrown forinstance for a case_id that would not be a valid hex for instance.
+	/// The string does not have the format of a token
 	InvalidEncoding(String),
 
+	/// A hex field of the token, such as the case id, is not a valid hex string.
+	/// The offset is the one of the field in the token without separators.
+	InvalidField {
+		field: Field,
+		offset: usize,
+		found: String,
+	},
+
 	/// The checksum is wrong.
 	ChecksumError(ChecksumError),
+
+	/// The string is neither a valid SS58 address nor a hex public key
+	InvalidAccount(String),
+
+	/// The checksum of this token version depends on an account, it cannot be checked without it
+	AccountRequired,
+
+	/// No account is associated with this case_id
+	UnknownCase(u64),
+
+	/// The token was remarked by another account than the one associated with its case
+	WrongSigner {
+		expected: AccountId,
+		found: AccountId,
+	},
+
+	/// The locale is not available or its file is invalid
+	InvalidLocale(String),
+
+	/// The case id does not fit in the tokens of this version
+	CaseIdOverflow {
+		version: Version,
+		max: u64,
+		found: u64,
+	},
+
+	/// The secret does not fit in the tokens of this version, by its length or its chars
+	InvalidSecret {
+		version: Version,
+		secret: String,
+	},
+
+	/// The registry file is invalid
+	InvalidRegistry(String),
+
+	/// The token targets a registrar that is not in our registry
+	UnknownRegistrar {
+		network: u8,
+		index: u8,
+	},
+
+	/// The token was revoked
+	Revoked(Revocation),
+
+	/// The revocation list file is invalid
+	InvalidRevocationList(String),
+
+	/// The signature does not match the document or was not made by a trusted key
+	InvalidSignature,
+
+	/// The signing key is invalid
+	InvalidKey(String),
+
+	/// The envelope of the token is malformed or its checksum is wrong
+	InvalidEnvelope(String),
 }
 
 impl From<VersionError> for Error {
@@ -31,3 +97,196 @@ impl From<VersionError> for Error {
 		Self::Version(err)
 	}
 }
+
+/// The classes of errors, for the callers that only need to know broadly what went wrong,
+/// such as the exit code of `rt`
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum ErrorClass {
+	/// The string is not a token, or not one we support
+	Format,
+
+	/// The token is well formed but its checksum is wrong
+	Checksum,
+
+	/// The account is invalid, missing or not the one of the case
+	Account,
+
+	/// The token does not target one of our registrars
+	Registrar,
+
+	/// The token was revoked
+	Revoked,
+
+	/// A signature or a key is invalid
+	Signature,
+
+	/// A file we were given, such as the registry, is invalid
+	Config,
+}
+
+impl Error {
+	/// A stable code such as `invalid_checksum`, for APIs and scripts to rely on rather than on the messages
+	pub fn code(&self) -> &'static str {
+		match self {
+			Error::Version(VersionError::ParseError(_)) => "invalid_version",
+			Error::Version(VersionError::UnsupportedVersion(_)) => "unsupported_version",
+			Error::Version(VersionError::NotAccepted(_)) => "version_not_accepted",
+			Error::UnknownNetwork(_) => "unknown_network",
+			Error::UnknownChannel(_) => "unknown_channel",
+			Error::LengthError { .. } => "invalid_length",
+			Error::InvalidEncoding(_) => "invalid_encoding",
+			Error::InvalidField { .. } => "invalid_field",
+			Error::ChecksumError(_) => "invalid_checksum",
+			Error::InvalidAccount(_) => "invalid_account",
+			Error::AccountRequired => "account_required",
+			Error::UnknownCase(_) => "unknown_case",
+			Error::WrongSigner { .. } => "wrong_signer",
+			Error::InvalidLocale(_) => "invalid_locale",
+			Error::CaseIdOverflow { .. } => "case_id_overflow",
+			Error::InvalidSecret { .. } => "invalid_secret",
+			Error::InvalidRegistry(_) => "invalid_registry",
+			Error::UnknownRegistrar { .. } => "unknown_registrar",
+			Error::Revoked(_) => "revoked",
+			Error::InvalidRevocationList(_) => "invalid_revocation_list",
+			Error::InvalidSignature => "invalid_signature",
+			Error::InvalidKey(_) => "invalid_key",
+			Error::InvalidEnvelope(_) => "invalid_envelope",
+		}
+	}
+
+	/// The message of the error followed by the ones of its sources, such as
+	/// `Invalid checksum: Wrong checksum for ...`, for the messages shown to users
+	pub fn report(&self) -> String {
+		let mut report = self.to_string();
+		let mut source = std::error::Error::source(self);
+		while let Some(e) = source {
+			report = format!("{}: {}", report, e);
+			source = e.source();
+		}
+		report
+	}
+
+	pub fn class(&self) -> ErrorClass {
+		match self {
+			Error::Version(_)
+			| Error::UnknownNetwork(_)
+			| Error::UnknownChannel(_)
+			| Error::LengthError { .. }
+			| Error::InvalidEncoding(_)
+			| Error::InvalidField { .. }
+			| Error::CaseIdOverflow { .. }
+			| Error::InvalidSecret { .. }
+			| Error::InvalidEnvelope(_) => ErrorClass::Format,
+			Error::ChecksumError(_) => ErrorClass::Checksum,
+			Error::InvalidAccount(_) | Error::AccountRequired | Error::UnknownCase(_) | Error::WrongSigner { .. } => {
+				ErrorClass::Account
+			}
+			Error::UnknownRegistrar { .. } => ErrorClass::Registrar,
+			Error::Revoked(_) => ErrorClass::Revoked,
+			Error::InvalidSignature | Error::InvalidKey(_) => ErrorClass::Signature,
+			Error::InvalidLocale(_) | Error::InvalidRegistry(_) | Error::InvalidRevocationList(_) => ErrorClass::Config,
+		}
+	}
+}
+
+impl Display for Error {
+	fn fmt(&self, fmt: &mut std::fmt::Formatter<'_>) -> std::result::Result<(), std::fmt::Error> {
+		match self {
+			Error::Version(_) => write!(fmt, "Invalid token version"),
+			Error::UnknownNetwork(n) => write!(fmt, "Unknown network {}", n),
+			Error::UnknownChannel(c) => write!(fmt, "Unknown channel {}", c),
+			Error::LengthError { expected, found } => {
+				write!(fmt, "The token should have {} chars but has {}", expected, found)
+			}
+			Error::InvalidEncoding(s) => write!(fmt, "{:?} is not a valid token", s),
+			Error::InvalidField { field, offset, found } => {
+				write!(fmt, "The {} {:?} at position {} is not a valid hex number", field, found, offset)
+			}
+			Error::ChecksumError(_) => write!(fmt, "Invalid checksum"),
+			Error::InvalidAccount(s) => write!(fmt, "{:?} is neither an SS58 address nor a hex public key", s),
+			Error::AccountRequired => {
+				write!(fmt, "This token is bound to an account, the account is required to check it")
+			}
+			Error::UnknownCase(id) => write!(fmt, "No account is associated with case {}", id),
+			Error::WrongSigner { expected, found } => {
+				write!(fmt, "The token was remarked by {} but its case belongs to {}", found, expected)
+			}
+			Error::InvalidLocale(s) => write!(fmt, "Invalid locale: {}", s),
+			Error::CaseIdOverflow { version, max, found } => {
+				write!(fmt, "The case id {} is larger than {}, the largest of V{} tokens", found, max, version)
+			}
+			Error::InvalidSecret { version, secret } => {
+				write!(fmt, "The secret {} cannot be used by V{} tokens", secret, version)
+			}
+			Error::InvalidRegistry(s) => write!(fmt, "Invalid registry: {}", s),
+			Error::UnknownRegistrar { network, index } => {
+				write!(fmt, "Registrar {} of network {} is not in the registry", index, network)
+			}
+			Error::Revoked(revocation) => write!(fmt, "This token was revoked: {}", revocation),
+			Error::InvalidRevocationList(s) => write!(fmt, "Invalid revocation list: {}", s),
+			Error::InvalidSignature => write!(fmt, "The signature is invalid or was not made by a trusted key"),
+			Error::InvalidKey(s) => write!(fmt, "Invalid key: {}", s),
+			Error::InvalidEnvelope(s) => write!(fmt, "Invalid envelope. {}", s),
+		}
+	}
+}
+
+impl std::error::Error for Error {
+	fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+		match self {
+			Error::Version(e) => Some(e),
+			Error::ChecksumError(e) => Some(e),
+			_ => None,
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::ChecksumOutput;
+	use std::error::Error as StdError;
+
+	#[test]
+	fn it_has_human_messages() {
+		let err = Error::LengthError { expected: 25, found: 24 };
+		assert_eq!("The token should have 25 chars but has 24", err.to_string());
+		let err = Error::InvalidField { field: Field::CaseId, offset: 8, found: String::from("0303G") };
+		assert_eq!("The case Id \"0303G\" at position 8 is not a valid hex number", err.to_string());
+		let err = Error::CaseIdOverflow { version: Version::V01, max: 0xFFFFF, found: 2_000_000 };
+		assert_eq!("The case id 2000000 is larger than 1048575, the largest of V01 tokens", err.to_string());
+	}
+
+	#[test]
+	fn it_chains_its_source() {
+		let checksum =
+			ChecksumError::new(String::from("X"), ChecksumOutput::Dual(*b"AQ"), ChecksumOutput::Dual(*b"AA"));
+		let err = Error::ChecksumError(checksum);
+		assert_eq!("Invalid checksum", err.to_string());
+		assert_eq!("Wrong checksum for X: found AA, expected AQ", err.source().unwrap().to_string());
+		assert_eq!("Invalid checksum: Wrong checksum for X: found AA, expected AQ", err.report());
+		let err = Error::Version(VersionError::UnsupportedVersion(9));
+		assert_eq!("Invalid token version", err.to_string());
+		assert_eq!("Version 9 is not supported", err.source().unwrap().to_string());
+		assert!(err.source().unwrap().source().is_none());
+		assert!(Error::AccountRequired.source().is_none());
+		assert_eq!(Error::AccountRequired.to_string(), Error::AccountRequired.report());
+
+		// Usable with `?` in functions returning a boxed error
+		let boxed: Box<dyn StdError> = Box::new(Error::AccountRequired);
+		assert_eq!("account_required", boxed.downcast_ref::<Error>().unwrap().code());
+	}
+
+	#[test]
+	fn it_has_stable_codes_and_classes() {
+		let err = Error::ChecksumError(ChecksumError::new(
+			String::new(),
+			ChecksumOutput::Single(65),
+			ChecksumOutput::Single(66),
+		));
+		assert_eq!(("invalid_checksum", ErrorClass::Checksum), (err.code(), err.class()));
+		let err = Error::Version(VersionError::NotAccepted(Version::V00));
+		assert_eq!(("version_not_accepted", ErrorClass::Format), (err.code(), err.class()));
+		assert_eq!(ErrorClass::Account, Error::AccountRequired.class());
+	}
+}
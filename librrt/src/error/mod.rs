@@ -2,4 +2,5 @@ pub use self::checksum_error::*;
 pub use self::error::*;
 
 mod checksum_error;
+#[allow(clippy::module_inception)]
 mod error;
@@ -0,0 +1,618 @@
+//! The explainer walks the fields of a candidate token string in order and
+//! reports exactly where it went wrong. Unlike the [Builder](crate::Builder),
+//! it does not stop at the first error and it tries to guess what the user
+//! did wrong, such as forgetting the checksum.
+
+use crate::builder::Builder;
+use crate::envelope;
+use crate::types::*;
+use crate::utils::clean_token_string;
+use crate::versions::*;
+use crate::Error;
+use std::fmt::Display;
+use std::str::FromStr;
+
+/// The fields of a token, in the order they appear
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Field {
+	App,
+	Version,
+	Network,
+	Index,
+	/// The length of the case id, for V04 tokens
+	CaseIdLength,
+	CaseId,
+	Channel,
+	/// The length of the secret, for V03 tokens
+	SecretLength,
+	Secret,
+	Checksum,
+}
+
+impl Display for Field {
+	fn fmt(&self, fmt: &mut std::fmt::Formatter<'_>) -> std::result::Result<(), std::fmt::Error> {
+		let str = match self {
+			Field::App => "app",
+			Field::Version => "version",
+			Field::Network => "network",
+			Field::Index => "reg_index",
+			Field::CaseIdLength => "case Id length",
+			Field::CaseId => "case Id",
+			Field::Channel => "channel",
+			Field::SecretLength => "secret length",
+			Field::Secret => "secret",
+			Field::Checksum => "checksum",
+		};
+		write!(fmt, "{}", str)
+	}
+}
+
+/// Why we think the length is wrong
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum LengthHint {
+	/// The string is exactly as long as the token without its checksum
+	MissingChecksum,
+
+	/// The length is the one of another version
+	LooksLike(Version),
+
+	/// Some chars are missing
+	MissingChars(usize),
+
+	/// There are more chars than expected
+	ExtraChars(usize),
+}
+
+/// An issue found while explaining a token.
+/// Offsets are given in the cleaned string, without separators.
+#[derive(Debug, PartialEq, Clone)]
+pub enum Issue {
+	/// Some chars are not part of [A-Z0-9] and have been ignored
+	IgnoredChars(String),
+
+	/// The version field is not supported
+	Version(VersionError),
+
+	/// A hex field could not be decoded
+	BadHex { field: Field, offset: usize, found: String },
+
+	/// The channel is none of the known ones
+	UnknownChannel { offset: usize, found: String },
+
+	/// Generated secrets only contain letters
+	SecretNotLetters { offset: usize, found: String },
+
+	/// The secret contains chars easily confused with the ones of the alphabet, such as 0 for O.
+	/// They were mapped back and the token is valid.
+	Confusables { offset: usize, found: String, mapped: String },
+
+	/// The length of the secret is not a hex digit from 8 to F
+	BadSecretLength { offset: usize, found: String },
+
+	/// The case id of a V04 token starts with a zero
+	PaddedCaseId { offset: usize, found: String },
+
+	/// The checksum can only be made of letters
+	ChecksumNotLetters { offset: usize, found: String },
+
+	/// The length does not match the version
+	WrongLength { version: Version, expected: usize, found: usize, hint: LengthHint },
+
+	/// All fields look fine but the checksum does not match
+	ChecksumMismatch { expected: String, found: String },
+
+	/// The checksum covers an account that was not given so it could not be checked
+	AccountRequired,
+
+	/// The envelope around the token is malformed, its checksum is wrong or it names another network
+	InvalidEnvelope(String),
+}
+
+impl Issue {
+	/// Returns the field the issue relates to, if any
+	pub fn field(&self) -> Option<Field> {
+		match self {
+			Issue::IgnoredChars(_) | Issue::WrongLength { .. } | Issue::InvalidEnvelope(_) => None,
+			Issue::Version(_) => Some(Field::Version),
+			Issue::BadHex { field, .. } => Some(*field),
+			Issue::UnknownChannel { .. } => Some(Field::Channel),
+			Issue::SecretNotLetters { .. } | Issue::Confusables { .. } => Some(Field::Secret),
+			Issue::BadSecretLength { .. } => Some(Field::SecretLength),
+			Issue::PaddedCaseId { .. } => Some(Field::CaseId),
+			Issue::ChecksumNotLetters { .. } | Issue::ChecksumMismatch { .. } | Issue::AccountRequired => {
+				Some(Field::Checksum)
+			}
+		}
+	}
+
+	/// Returns false for issues that do not prevent the token from being valid
+	pub fn is_fatal(&self) -> bool {
+		!matches!(self, Issue::IgnoredChars(_) | Issue::SecretNotLetters { .. } | Issue::Confusables { .. })
+	}
+}
+
+impl Display for Issue {
+	fn fmt(&self, fmt: &mut std::fmt::Formatter<'_>) -> std::result::Result<(), std::fmt::Error> {
+		match self {
+			Issue::IgnoredChars(chars) => write!(fmt, "ignored {} char(s) not in [A-Z0-9]: {:?}", chars.len(), chars),
+			Issue::Version(VersionError::UnsupportedVersion(v)) => write!(fmt, "version {:02} is not supported", v),
+			Issue::Version(VersionError::ParseError(v)) => write!(fmt, "version {:?} is not a valid version", v),
+			Issue::Version(VersionError::NotAccepted(v)) => write!(fmt, "version {} is not accepted", v),
+			Issue::BadHex { field, offset, found } => {
+				write!(fmt, "bad hex in {} at offset {}: {:?}", field, offset, found)
+			}
+			Issue::UnknownChannel { offset, found } => {
+				write!(fmt, "unknown channel {:?} at offset {}, expected one of TW, EM, MX", found, offset)
+			}
+			Issue::SecretNotLetters { offset, found } => {
+				write!(
+					fmt,
+					"secret {:?} at offset {} contains digits, generated secrets only use letters",
+					found, offset
+				)
+			}
+			Issue::Confusables { offset, found, mapped } => {
+				write!(
+					fmt,
+					"secret {:?} at offset {} was read as {:?}, mapping back the confusable chars",
+					found, offset, mapped
+				)
+			}
+			Issue::BadSecretLength { offset, found } => {
+				write!(fmt, "secret length {:?} at offset {} should be a hex digit from 8 to F", found, offset)
+			}
+			Issue::PaddedCaseId { offset, found } => {
+				write!(fmt, "case Id {:?} at offset {} should not start with a zero", found, offset)
+			}
+			Issue::ChecksumNotLetters { offset, found } => {
+				write!(fmt, "checksum {:?} at offset {} should only contain letters", found, offset)
+			}
+			Issue::WrongLength { version, expected, found, hint } => {
+				write!(fmt, "wrong length for a V{}: expected {}, got {}", version, expected, found)?;
+				match hint {
+					LengthHint::MissingChecksum => write!(fmt, ", likely missing checksum"),
+					LengthHint::LooksLike(v) => write!(
+						fmt,
+						", this is the length of a V{}: either the version field is wrong or {} char(s) are {}",
+						v,
+						(*expected as isize - *found as isize).abs(),
+						if found < expected { "missing" } else { "extra" }
+					),
+					LengthHint::MissingChars(n) => write!(fmt, ", {} char(s) missing", n),
+					LengthHint::ExtraChars(n) => write!(fmt, ", {} extra char(s)", n),
+				}
+			}
+			Issue::AccountRequired => {
+				write!(fmt, "the checksum covers the account the token was issued for, pass it to check the token")
+			}
+			Issue::ChecksumMismatch { expected, found } => {
+				write!(fmt, "checksum mismatch: found {}, expected {}", found, expected)
+			}
+			Issue::InvalidEnvelope(msg) => write!(fmt, "invalid envelope: {}", msg),
+		}
+	}
+}
+
+/// A field as found in the cleaned string
+#[derive(Debug, PartialEq, Clone)]
+pub struct FieldReport {
+	pub field: Field,
+	pub offset: usize,
+	pub raw: String,
+}
+
+/// The result of [Explainer::explain]
+#[derive(Debug, PartialEq, Clone)]
+pub struct Explanation {
+	/// The string without any char that is not part of [A-Z0-9]
+	pub cleaned: String,
+
+	/// The detected version, if any
+	pub version: Option<Version>,
+
+	/// The fields that could be located
+	pub fields: Vec<FieldReport>,
+
+	pub issues: Vec<Issue>,
+}
+
+impl Explanation {
+	/// A token is valid if no fatal issue was found
+	pub fn is_valid(&self) -> bool {
+		!self.issues.iter().any(|i| i.is_fatal())
+	}
+
+	/// Returns the issues related to a given field
+	pub fn issues_for(&self, field: Field) -> impl Iterator<Item = &Issue> {
+		self.issues.iter().filter(move |i| i.field() == Some(field))
+	}
+}
+
+/// Returns the size of the tokens and the size of their checksum for a given version.
+/// The size of a V03 depends on the length of its secret, given in the cleaned string,
+/// the size of a V04 on the length of its case id.
+fn sizes(version: Version, cleaned: &str) -> (usize, usize) {
+	match version {
+		Version::V00 => (24, 1),
+		Version::V01 | Version::V02 => (25, 2),
+		Version::V03 => {
+			let length = TokenV03::secret_length(cleaned).unwrap_or(V03_DEFAULT_SECRET_LENGTH);
+			(TokenV03::size_for(length), 2)
+		}
+		Version::V04 => (TokenV04::size_for(TokenV04::parse_case_id_length(cleaned).unwrap_or(5)), 2),
+	}
+}
+
+/// (field, offset, length) for the fields located before the checksum.
+/// All versions share this layout, V03 adds the length of the secret and V04 the length of the case id.
+const LAYOUT: [(Field, usize, usize); 6] = [
+	(Field::App, 0, 2),
+	(Field::Version, 2, 2),
+	(Field::Network, 4, 2),
+	(Field::Index, 6, 2),
+	(Field::CaseId, 8, 5),
+	(Field::Channel, 13, 2),
+];
+
+/// Returns the layout of the token, secret included
+fn layout(version: Option<Version>, cleaned: &str) -> Vec<(Field, usize, usize)> {
+	let mut layout = LAYOUT.to_vec();
+	match version {
+		Some(Version::V03) => {
+			let length = TokenV03::secret_length(cleaned).unwrap_or(V03_DEFAULT_SECRET_LENGTH);
+			layout.push((Field::SecretLength, 15, 1));
+			layout.push((Field::Secret, 16, length));
+		}
+		Some(Version::V04) => {
+			let length = TokenV04::parse_case_id_length(cleaned).unwrap_or(5);
+			layout.truncate(4);
+			layout.push((Field::CaseIdLength, 8, 1));
+			layout.push((Field::CaseId, 9, length));
+			layout.push((Field::Channel, 9 + length, 2));
+			layout.push((Field::Secret, 11 + length, 8));
+		}
+		_ => layout.push((Field::Secret, 15, 8)),
+	}
+	layout
+}
+
+#[derive(Debug)]
+pub struct Explainer {}
+
+impl Explainer {
+	/// Walks the fields of `s` and reports all the issues we can find
+	pub fn explain(s: &str) -> Explanation {
+		Self::explain_for_account(s, None)
+	}
+
+	/// Like `explain` but also checks the checksum of the tokens bound to an account, such as V02
+	pub fn explain_for_account(s: &str, account: Option<&AccountId>) -> Explanation {
+		if envelope::is_envelope(s.trim()) {
+			return Self::explain_envelope(s, account);
+		}
+
+		let cleaned = clean_token_string(s);
+		let mut explanation = Explanation { cleaned, version: None, fields: Vec::new(), issues: Vec::new() };
+
+		let ignored: String = s.chars().filter(|c| !c.is_ascii_uppercase() && !c.is_ascii_digit()).collect();
+		if !ignored.is_empty() {
+			explanation.issues.push(Issue::IgnoredChars(ignored));
+		}
+
+		let cleaned = explanation.cleaned.clone();
+		let len = cleaned.len();
+
+		// The token as the builder reads it, confusables mapped back. The explanation must agree with it.
+		let built = match account {
+			Some(account) => Builder::build_for_account(&cleaned, account),
+			None => Builder::build_with_variant(&cleaned),
+		}
+		.ok()
+		.map(|t| t.to_string());
+
+		if len >= 4 {
+			match Version::from_str(&cleaned[2..4]) {
+				Ok(v) => explanation.version = Some(v),
+				Err(e) => explanation.issues.push(Issue::Version(e)),
+			}
+		}
+
+		// Without a version, we cannot tell the size of the checksum
+		let (expected, checksum_len) =
+			explanation.version.map(|v| sizes(v, &cleaned)).unwrap_or((sizes(Version::V01, &cleaned).0, 0));
+		if let Some(version) = explanation.version {
+			if len != expected {
+				let hint = if len + checksum_len == expected {
+					LengthHint::MissingChecksum
+				} else if let Some(other) = [Version::V00, Version::V01].iter().find(|v| sizes(**v, &cleaned).0 == len)
+				{
+					LengthHint::LooksLike(*other)
+				} else if len < expected {
+					LengthHint::MissingChars(expected - len)
+				} else {
+					LengthHint::ExtraChars(len - expected)
+				};
+				explanation.issues.push(Issue::WrongLength { version, expected, found: len, hint });
+			}
+		} else if len < 4 {
+			explanation.issues.push(Issue::WrongLength {
+				version: Version::V01,
+				expected,
+				found: len,
+				hint: LengthHint::MissingChars(expected - len),
+			});
+		}
+
+		for (field, offset, size) in layout(explanation.version, &cleaned) {
+			if offset + size > len {
+				break;
+			}
+			let raw = String::from(&cleaned[offset..offset + size]);
+			match field {
+				Field::App | Field::Network | Field::Index | Field::CaseIdLength | Field::CaseId => {
+					if u64::from_str_radix(&raw, 16).is_err() {
+						explanation.issues.push(Issue::BadHex { field, offset, found: raw.clone() });
+					} else if field == Field::CaseId
+						&& explanation.version == Some(Version::V04)
+						&& raw.len() > 1 && raw.starts_with('0')
+					{
+						explanation.issues.push(Issue::PaddedCaseId { offset, found: raw.clone() });
+					}
+				}
+				Field::Channel => {
+					if Channel::from(raw.as_str()) == Channel::Unknown && raw != "XX" {
+						explanation.issues.push(Issue::UnknownChannel { offset, found: raw.clone() });
+					}
+				}
+				Field::SecretLength => {
+					if TokenV03::secret_length(&cleaned).is_none() {
+						explanation.issues.push(Issue::BadSecretLength { offset, found: raw.clone() });
+					}
+				}
+				Field::Secret => {
+					let alphabet = explanation.version.map(|v| v.alphabet()).unwrap_or_default();
+					let mapped = built.as_ref().and_then(|b| b.get(offset..offset + size)).filter(|m| *m != raw);
+					if let Some(mapped) = mapped {
+						explanation.issues.push(Issue::Confusables {
+							offset,
+							found: raw.clone(),
+							mapped: String::from(mapped),
+						});
+					} else if alphabet == Alphabet::Full && raw.chars().any(|c| c.is_ascii_digit()) {
+						explanation.issues.push(Issue::SecretNotLetters { offset, found: raw.clone() });
+					}
+				}
+				Field::Version | Field::Checksum => {}
+			}
+			explanation.fields.push(FieldReport { field, offset, raw });
+		}
+
+		let offset = expected - checksum_len;
+		if checksum_len > 0 && len >= expected {
+			let raw = String::from(&cleaned[offset..expected]);
+			if !raw.chars().all(|c| c.is_ascii_uppercase()) {
+				explanation.issues.push(Issue::ChecksumNotLetters { offset, found: raw.clone() });
+			}
+			explanation.fields.push(FieldReport { field: Field::Checksum, offset, raw });
+		}
+
+		// Only check the checksum once everything else looks fine, it would fail anyway.
+		// A token the builder accepts has a valid checksum, maybe once its confusables are mapped back.
+		if explanation.is_valid() && built.is_none() {
+			let res = match explanation.version {
+				Some(Version::V00) => TokenV00::from_str(&cleaned).map(|_| ()),
+				Some(Version::V01) => TokenV01::from_str(&cleaned).map(|_| ()),
+				Some(Version::V03) => TokenV03::from_str(&cleaned).map(|_| ()),
+				Some(Version::V04) => TokenV04::from_str(&cleaned).map(|_| ()),
+				Some(Version::V02) => match account {
+					Some(account) => TokenV02::from_str_with_account(&cleaned, account).map(|_| ()),
+					None => Err(Error::AccountRequired),
+				},
+				None => Ok(()),
+			};
+			match res {
+				Err(Error::ChecksumError(e)) => explanation
+					.issues
+					.push(Issue::ChecksumMismatch { expected: e.expected().to_string(), found: e.found().to_string() }),
+				Err(Error::AccountRequired) => explanation.issues.push(Issue::AccountRequired),
+				_ => {}
+			}
+		}
+
+		explanation
+	}
+
+	/// Explains the token of an envelope such as `rrt1-ksm-...`, once the envelope is checked
+	fn explain_envelope(s: &str, account: Option<&AccountId>) -> Explanation {
+		let (network, token) = match envelope::unwrap(s) {
+			Ok(unwrapped) => unwrapped,
+			Err(e) => {
+				let msg = match e {
+					Error::InvalidEnvelope(msg) => msg,
+					e => e.to_string(),
+				};
+				return Explanation {
+					cleaned: String::from(s.trim()),
+					version: None,
+					fields: Vec::new(),
+					issues: vec![Issue::InvalidEnvelope(msg)],
+				};
+			}
+		};
+
+		let mut explanation = Self::explain_for_account(&token, account);
+		let found = explanation
+			.fields
+			.iter()
+			.find(|f| f.field == Field::Network)
+			.and_then(|f| u8::from_str_radix(&f.raw, 16).ok())
+			.map(Network::from);
+		if let Some(found) = found.filter(|n| *n != network) {
+			explanation.issues.push(Issue::InvalidEnvelope(format!(
+				"The token is for {}, not {}: {}",
+				found.short_name(),
+				network.short_name(),
+				s.trim()
+			)));
+		}
+		explanation
+	}
+}
+
+#[cfg(test)]
+mod tests_explainer {
+	use super::*;
+
+	fn fatal_issues(s: &str) -> Vec<Issue> {
+		Explainer::explain(s).issues.into_iter().filter(|i| i.is_fatal()).collect()
+	}
+
+	#[test]
+	fn it_accepts_valid_tokens() {
+		let explanation = Explainer::explain("00-01-02-01-03039-TW-JXBACTSP-AQ");
+		assert!(explanation.is_valid());
+		assert_eq!(Some(Version::V01), explanation.version);
+		assert_eq!(8, explanation.fields.len());
+		assert_eq!(Issue::IgnoredChars("-------".into()), explanation.issues[0]);
+
+		assert!(Explainer::explain("0000000012345TWRAJQFIZWW").is_valid());
+	}
+
+	#[test]
+	fn it_reports_bad_hex() {
+		assert_eq!(
+			vec![Issue::BadHex { field: Field::Network, offset: 4, found: "0G".into() }],
+			fatal_issues("00010G0103039TWJXBACTSPAQ")
+		);
+	}
+
+	#[test]
+	fn it_reports_unknown_channels() {
+		assert_eq!(
+			vec![Issue::UnknownChannel { offset: 13, found: "XQ".into() }],
+			fatal_issues("0001020103039XQJXBACTSPAQ")
+		);
+	}
+
+	#[test]
+	fn it_reports_digits_in_secret() {
+		let explanation = Explainer::explain("0001020103039TWJXBAC1SPAQ");
+		assert!(explanation.issues.contains(&Issue::SecretNotLetters { offset: 15, found: "JXBAC1SP".into() }));
+		assert_eq!(1, explanation.issues_for(Field::Secret).count());
+	}
+
+	#[test]
+	fn it_reports_a_missing_checksum() {
+		assert_eq!(
+			vec![Issue::WrongLength {
+				version: Version::V01,
+				expected: 25,
+				found: 23,
+				hint: LengthHint::MissingChecksum
+			}],
+			fatal_issues("0001020103039TWJXBACTSP")
+		);
+	}
+
+	#[test]
+	fn it_reports_a_missing_char() {
+		let issues = fatal_issues("0001020103039TWJXBACTPAQ");
+		assert_eq!(
+			Issue::WrongLength {
+				version: Version::V01,
+				expected: 25,
+				found: 24,
+				hint: LengthHint::LooksLike(Version::V00)
+			},
+			issues[0]
+		);
+		assert!(issues[0].to_string().contains("1 char(s) are missing"));
+	}
+
+	#[test]
+	fn it_reports_checksum_mismatches() {
+		assert_eq!(
+			vec![Issue::ChecksumMismatch { expected: "AQ".into(), found: "AA".into() }],
+			fatal_issues("0001020103039TWJXBACTSPAA")
+		);
+	}
+
+	#[test]
+	fn it_reports_unsupported_versions() {
+		let explanation = Explainer::explain("0099020103039TWJXBACTSPAQ");
+		assert_eq!(None, explanation.version);
+		assert_eq!(vec![Issue::Version(VersionError::UnsupportedVersion(99))], explanation.issues);
+	}
+
+	#[test]
+	fn it_checks_v02_tokens_with_the_account() {
+		let account = AccountId([1; 32]);
+		let s = TokenV02::new_with_secret(0, 2, 1, 12345, Channel::Twitter, "JXBACTSP", &account).to_string();
+
+		assert_eq!(vec![Issue::AccountRequired], Explainer::explain(&s).issues);
+		assert!(Explainer::explain_for_account(&s, Some(&account)).issues.is_empty());
+		let issues = Explainer::explain_for_account(&s, Some(&AccountId([2; 32]))).issues;
+		assert!(matches!(issues[..], [Issue::ChecksumMismatch { .. }]));
+	}
+
+	#[test]
+	fn it_explains_v03_tokens() {
+		let s = TokenV03::new_with_secret(0, 2, 1, 12345, Channel::Twitter, "7M3K9T2XQ4HB").to_string();
+		let explanation = Explainer::explain(&s);
+		assert!(explanation.issues.is_empty());
+		let secret = explanation.fields.iter().find(|f| f.field == Field::Secret).unwrap();
+		assert_eq!((16, "7M3K9T2XQ4HB"), (secret.offset, secret.raw.as_str()));
+
+		let issues = Explainer::explain(&s[..29]).issues;
+		assert!(matches!(issues[..], [Issue::WrongLength { version: Version::V03, expected: 30, found: 29, .. }]));
+
+		let bad_length = format!("{}Z{}", &s[..15], &s[16..]);
+		assert!(Explainer::explain(&bad_length)
+			.issues
+			.contains(&Issue::BadSecretLength { offset: 15, found: String::from("Z") }));
+	}
+
+	#[test]
+	fn it_explains_v04_tokens() {
+		let s = TokenV04::new_with_secret(0, 2, 1, 1_000_000, Channel::Twitter, "JXBACTSP").to_string();
+		let explanation = Explainer::explain(&s);
+		assert!(explanation.issues.is_empty());
+		let case_id = explanation.fields.iter().find(|f| f.field == Field::CaseId).unwrap();
+		assert_eq!((9, "F4240"), (case_id.offset, case_id.raw.as_str()));
+
+		let padded = format!("{}50F4240{}", &s[..8], &s[14..]);
+		let issues = Explainer::explain(&padded).issues;
+		assert_eq!(vec![Issue::PaddedCaseId { offset: 9, found: String::from("0F4240") }], issues);
+	}
+
+	#[test]
+	fn it_maps_confusables_back_like_the_builder() {
+		let explanation = Explainer::explain("0001020103039TWJ0BACT1PVR");
+		assert!(explanation.is_valid());
+		assert_eq!(
+			vec![Issue::Confusables { offset: 15, found: "J0BACT1P".into(), mapped: "JOBACTIP".into() }],
+			explanation.issues
+		);
+	}
+
+	#[test]
+	fn it_explains_envelopes() {
+		let token = Builder::build_with_variant("0001020103039TWJXBACTSPAQ").unwrap();
+		let explanation = Explainer::explain(&token.to_envelope());
+		assert!(explanation.is_valid());
+		assert_eq!("0001020103039TWJXBACTSPAQ", explanation.cleaned);
+
+		let mut broken = token.to_envelope();
+		broken.pop();
+		broken.push('x');
+		let issues = Explainer::explain(&broken).issues;
+		assert!(matches!(issues[..], [Issue::InvalidEnvelope(_)]));
+	}
+
+	#[test]
+	fn it_does_not_panic_on_junk() {
+		for s in &["", "0", "000", "junk", "ZZZZZZZZZZZZZZZZZZZZZZZZZZZZZZ", "00010", "0003020103039TW"] {
+			assert!(!Explainer::explain(s).is_valid());
+		}
+	}
+}
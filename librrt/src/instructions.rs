@@ -0,0 +1,144 @@
+//! Renders the instructions given to a user along with a token, in the language of the user.
+//!
+//! The texts are in `locales/*.toml`. A custom locale can be loaded with [Locale::parse],
+//! its missing texts fall back to English.
+
+use crate::call::to_hex;
+use crate::error::Error;
+use crate::types::{Channel, Network};
+use crate::versions::Tokenize;
+use std::collections::HashMap;
+
+/// The built-in locales, as (language, content of the locale file)
+const LOCALES: [(&str, &str); 4] = [
+	("en", include_str!("../locales/en.toml")),
+	("fr", include_str!("../locales/fr.toml")),
+	("de", include_str!("../locales/de.toml")),
+	("zh", include_str!("../locales/zh.toml")),
+];
+
+/// The texts of a language
+#[derive(Debug, PartialEq, Clone)]
+pub struct Locale {
+	pub lang: String,
+	texts: HashMap<String, String>,
+}
+
+impl Locale {
+	/// Returns the languages of the built-in locales
+	pub fn languages() -> Vec<&'static str> {
+		LOCALES.iter().map(|(lang, _)| *lang).collect()
+	}
+
+	/// Returns a built-in locale. Tags such as `fr-CH` or `fr_FR.UTF-8` fall back to their language.
+	pub fn get(lang: &str) -> Result<Self, Error> {
+		let short = lang.split(['-', '_', '.']).next().unwrap_or_default().to_lowercase();
+		match LOCALES.iter().find(|(l, _)| *l == short) {
+			Some((l, content)) => Self::parse(l, content),
+			None => Err(Error::InvalidLocale(format!(
+				"No locale for {:?}, available: {}",
+				lang,
+				Self::languages().join(", ")
+			))),
+		}
+	}
+
+	/// Parses the content of a locale file
+	pub fn parse(lang: &str, content: &str) -> Result<Self, Error> {
+		let texts = toml::from_str(content).map_err(|e| Error::InvalidLocale(format!("{}: {}", lang, e)))?;
+		Ok(Self { lang: lang.to_string(), texts })
+	}
+
+	/// Returns the text for `key`, in English if this locale does not have it
+	fn text(&self, key: &str) -> String {
+		match self.texts.get(key) {
+			Some(text) => text.clone(),
+			None if self.lang != "en" => Self::get("en").map(|en| en.text(key)).unwrap_or_default(),
+			None => String::new(),
+		}
+	}
+}
+
+impl Default for Locale {
+	fn default() -> Self {
+		Self::get("en").expect("The en locale is valid")
+	}
+}
+
+/// Returns the name of the network, such as `Kusama`
+fn network_name(network: Network, locale: &Locale) -> String {
+	match network {
+		Network::Known(_) => String::from(network),
+		Network::Unknown(id) => locale.text("unknown_network").replace("{network_id}", &id.to_string()),
+	}
+}
+
+/// Renders the instructions for the token. The call data of the remark is included if given.
+pub fn instructions(token: &impl Tokenize, locale: &Locale, call_data: Option<&[u8]>) -> String {
+	let channel = match token.channel() {
+		Channel::Email => "email",
+		Channel::Matrix => "matrix",
+		Channel::Twitter => "twitter",
+		Channel::Unknown => "other",
+	};
+	let mut keys = vec!["title", "", "token", "token_sep", "", channel, "remark"];
+	if call_data.is_some() {
+		keys.push("call_data");
+	}
+
+	let text = keys.iter().map(|key| locale.text(key)).collect::<Vec<_>>().join("\n");
+	text.replace("{token}", &token.format_string(""))
+		.replace("{token_sep}", &token.format_string("-"))
+		.replace("{case_id}", &token.case_id().to_string())
+		.replace("{network}", &network_name(token.network(), locale))
+		.replace("{call_data}", &call_data.map(to_hex).unwrap_or_default())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::{remark_call_data, Builder, RemarkCall};
+
+	#[test]
+	fn it_loads_the_builtin_locales() {
+		for lang in Locale::languages() {
+			let locale = Locale::get(lang).unwrap();
+			let en = Locale::default();
+			let mut keys: Vec<_> = locale.texts.keys().collect();
+			let mut en_keys: Vec<_> = en.texts.keys().collect();
+			keys.sort();
+			en_keys.sort();
+			assert_eq!(en_keys, keys, "{} does not have the same texts as en", lang);
+		}
+		assert_eq!("fr", Locale::get("fr_FR.UTF-8").unwrap().lang);
+		assert!(matches!(Locale::get("xx"), Err(Error::InvalidLocale(_))));
+	}
+
+	#[test]
+	fn it_renders_instructions() {
+		let token = Builder::build_with_variant("0001020103039TWJXBACTSPAQ").unwrap();
+		let call_data = remark_call_data(&token, RemarkCall::Remark);
+
+		let text = instructions(&token, &Locale::default(), Some(&call_data));
+		assert!(text.starts_with("Identity verification, case 12345\n"));
+		assert!(text.contains("0001020103039TWJXBACTSPAQ"));
+		assert!(text.contains("00-01-02-01-03039-TW-JXBACTSP-AQ"));
+		assert!(text.contains("direct message on Twitter"));
+		assert!(text.contains("on Kusama"));
+		assert!(text.contains("0x000064"));
+
+		let text = instructions(&token, &Locale::get("fr").unwrap(), None);
+		assert!(text.contains("Publiez le même jeton sur Kusama"));
+		assert!(!text.contains("0x"));
+	}
+
+	#[test]
+	fn it_falls_back_to_english() {
+		let locale = Locale::parse("pirate", "token = \"Yer token be {token}\"").unwrap();
+		let token = Builder::build_with_variant("0000000012345TWRAJQFIZWW").unwrap();
+		let text = instructions(&token, &locale, None);
+		assert!(text.contains("Yer token be 0000000012345TWRAJQFIZWW"));
+		assert!(text.contains("on Polkadot"));
+		assert!(Locale::parse("bad", "token = 1").is_err());
+	}
+}
@@ -1,14 +1,35 @@
 pub use builder::*;
+pub use call::*;
 pub use checksum::*;
 pub use detector::*;
+pub use envelope::ENVELOPE_PREFIX;
 pub use error::*;
+pub use explainer::*;
+pub use instructions::*;
+pub use registry::*;
+pub use revocation::*;
+pub use scanner::*;
+pub use signed_token::*;
 pub use types::*;
+pub use utils::clean_token_string;
+pub use verifier::*;
 pub use versions::*;
 
 mod builder;
+mod call;
 mod checksum;
+mod codec;
 mod detector;
+mod envelope;
 mod error;
+mod explainer;
+mod instructions;
+mod registry;
+mod revocation;
+mod scanner;
+mod signed_token;
+pub mod signing;
 mod types;
 mod utils;
+mod verifier;
 mod versions;
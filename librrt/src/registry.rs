@@ -0,0 +1,168 @@
+//! The registry lists the registrars we operate, on one or more networks.
+//!
+//! It is loaded from a JSON file such as:
+//!
+//! ```json
+//! [
+//!   { "network": 2, "index": 1, "name": "Chevdor", "account": "HNZata7iMYWmk5RvZRTiAsSDhV8366zq2YGb3tLH5Upf74F", "fee": 40000000000 },
+//!   { "network": 0, "index": 3, "name": "Chevdor", "account": "15oF4uVJwmo4TdGW7VfQxNLavjCXviqxT9S1MgbjMNHr6Sp5" }
+//! ]
+//! ```
+
+use crate::error::Error;
+use crate::types::{AccountId, Network};
+use crate::versions::Tokenize;
+use serde::Deserialize;
+use std::fs;
+use std::path::Path;
+
+/// A registrar we operate
+#[derive(Debug, PartialEq, Clone)]
+pub struct Registrar {
+	pub network: u8,
+
+	/// The index of the registrar on its network
+	pub index: u8,
+
+	pub name: String,
+
+	/// The account of the registrar
+	pub account: AccountId,
+
+	/// The fee of a judgement, in the smallest unit of the network
+	pub fee: u128,
+}
+
+/// A registrar as found in the registry file
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawRegistrar {
+	network: u8,
+	index: u8,
+	name: String,
+	account: String,
+	#[serde(default)]
+	fee: u128,
+}
+
+impl Registrar {
+	/// Returns true if the token targets this registrar
+	pub fn issued(&self, token: &impl Tokenize) -> bool {
+		u8::from(token.network()) == self.network && *token.index() == self.index
+	}
+}
+
+/// The registrars we operate. There is at most one registrar per network and index.
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct Registry {
+	registrars: Vec<Registrar>,
+}
+
+impl Registry {
+	pub fn new(registrars: Vec<Registrar>) -> Result<Self, Error> {
+		for (i, r) in registrars.iter().enumerate() {
+			if registrars[..i].iter().any(|other| (other.network, other.index) == (r.network, r.index)) {
+				return Err(Error::InvalidRegistry(format!(
+					"Registrar {} of {} is listed more than once",
+					r.index,
+					String::from(Network::from(r.network))
+				)));
+			}
+		}
+		Ok(Self { registrars })
+	}
+
+	/// Parses the content of a registry file
+	pub fn parse(json: &str) -> Result<Self, Error> {
+		let raw: Vec<RawRegistrar> = serde_json::from_str(json).map_err(|e| Error::InvalidRegistry(e.to_string()))?;
+		let registrars = raw
+			.into_iter()
+			.map(|r| {
+				let account = r.account.parse().map_err(|_| {
+					Error::InvalidRegistry(format!("Invalid account {:?} for registrar {:?}", r.account, r.name))
+				})?;
+				Ok(Registrar { network: r.network, index: r.index, name: r.name, account, fee: r.fee })
+			})
+			.collect::<Result<Vec<_>, Error>>()?;
+		Self::new(registrars)
+	}
+
+	/// Loads a registry file
+	pub fn load(path: &Path) -> Result<Self, Error> {
+		let content = fs::read_to_string(path)
+			.map_err(|e| Error::InvalidRegistry(format!("Cannot read {}: {}", path.display(), e)))?;
+		Self::parse(&content).map_err(|e| match e {
+			Error::InvalidRegistry(msg) => Error::InvalidRegistry(format!("{}: {}", path.display(), msg)),
+			e => e,
+		})
+	}
+
+	pub fn registrars(&self) -> &[Registrar] {
+		&self.registrars
+	}
+
+	/// Returns our registrar with the given index on the network
+	pub fn get(&self, network: u8, index: u8) -> Option<&Registrar> {
+		self.registrars.iter().find(|r| r.network == network && r.index == index)
+	}
+
+	/// Returns the registrars with the given name, one per network
+	pub fn by_name<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a Registrar> {
+		self.registrars.iter().filter(move |r| r.name == name)
+	}
+
+	/// Returns the registrar the token was issued for, if it is one of ours
+	pub fn check(&self, token: &impl Tokenize) -> Result<&Registrar, Error> {
+		let network = u8::from(token.network());
+		self.get(network, *token.index()).ok_or(Error::UnknownRegistrar { network, index: *token.index() })
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::{Channel, TokenV01, Version};
+
+	const REGISTRY: &str = r#"[
+		{ "network": 2, "index": 1, "name": "Chevdor", "account": "HNZata7iMYWmk5RvZRTiAsSDhV8366zq2YGb3tLH5Upf74F", "fee": 40000000000 },
+		{ "network": 0, "index": 3, "name": "Chevdor", "account": "15oF4uVJwmo4TdGW7VfQxNLavjCXviqxT9S1MgbjMNHr6Sp5" }
+	]"#;
+
+	#[test]
+	fn it_parses_a_registry() {
+		let registry = Registry::parse(REGISTRY).unwrap();
+		assert_eq!(2, registry.registrars().len());
+
+		let kusama = registry.get(2, 1).unwrap();
+		assert_eq!(40_000_000_000, kusama.fee);
+		assert_eq!(registry.get(0, 3).unwrap().account, kusama.account);
+		assert_eq!(0, registry.get(0, 3).unwrap().fee);
+		assert!(registry.get(2, 3).is_none());
+		assert_eq!(2, registry.by_name("Chevdor").count());
+	}
+
+	#[test]
+	fn it_checks_the_registrar_of_tokens() {
+		let registry = Registry::parse(REGISTRY).unwrap();
+		let token = TokenV01::new(0, Version::V01, 2, 1, 12345, Channel::Twitter);
+		assert_eq!(1, registry.check(&token).unwrap().index);
+
+		let token = TokenV01::new(0, Version::V01, 2, 3, 12345, Channel::Twitter);
+		assert_eq!(Err(Error::UnknownRegistrar { network: 2, index: 3 }), registry.check(&token));
+	}
+
+	#[test]
+	fn it_rejects_invalid_registries() {
+		assert!(matches!(Registry::parse("{}"), Err(Error::InvalidRegistry(_))));
+		assert!(matches!(
+			Registry::parse(r#"[{ "network": 2, "index": 1, "name": "x", "account": "junk" }]"#),
+			Err(Error::InvalidRegistry(_))
+		));
+
+		let twice = r#"[
+			{ "network": 2, "index": 1, "name": "a", "account": "HNZata7iMYWmk5RvZRTiAsSDhV8366zq2YGb3tLH5Upf74F" },
+			{ "network": 2, "index": 1, "name": "b", "account": "HNZata7iMYWmk5RvZRTiAsSDhV8366zq2YGb3tLH5Upf74F" }
+		]"#;
+		assert!(matches!(Registry::parse(twice), Err(Error::InvalidRegistry(_))));
+	}
+}
@@ -0,0 +1,347 @@
+//! Revocation lists invalidate tokens whose secret leaked, for instance when a user
+//! posts their token publicly before remarking it.
+//!
+//! A list is saved locally as JSON. It can be exported signed so that other
+//! verifier instances import it after checking who signed it.
+
+use crate::call::to_hex;
+use crate::error::Error;
+use crate::signing::{self, Keypair, Scheme};
+use crate::types::{AccountId, Channel, Network};
+use crate::utils::clean_token_string;
+use crate::versions::Tokenize;
+use serde::{Deserialize, Serialize};
+use std::fmt::Display;
+use std::fs;
+use std::path::Path;
+
+/// Signed along the entries of a revocation list
+const DOMAIN: &[u8] = b"rrt-revocations:";
+
+/// What is revoked
+#[derive(Debug, PartialEq, Clone)]
+pub enum Revocation {
+	/// A single token, without separators
+	Token(String),
+
+	/// All the tokens of a case. Case ids are only unique to a registrar,
+	/// so the case is that of the registrar `index` on `network`.
+	Case { network: u8, index: u8, case_id: u64 },
+
+	/// The tokens of a case sent on a channel
+	CaseChannel { network: u8, index: u8, case_id: u64, channel: Channel },
+}
+
+impl Revocation {
+	/// Returns true if the token is revoked by this revocation
+	pub fn matches(&self, token: &impl Tokenize) -> bool {
+		let same_case = |network: u8, index: u8, case_id: u64| {
+			network == u8::from(token.network()) && index == *token.index() && case_id == *token.case_id()
+		};
+		match self {
+			Revocation::Token(t) => *t == token.format_string(""),
+			Revocation::Case { network, index, case_id } => same_case(*network, *index, *case_id),
+			Revocation::CaseChannel { network, index, case_id, channel } => {
+				same_case(*network, *index, *case_id) && channel == token.channel()
+			}
+		}
+	}
+}
+
+impl Display for Revocation {
+	fn fmt(&self, fmt: &mut std::fmt::Formatter<'_>) -> std::result::Result<(), std::fmt::Error> {
+		let registrar =
+			|network: u8, index: u8| format!("registrar {} of {}", index, String::from(Network::from(network)));
+		match self {
+			Revocation::Token(t) => write!(fmt, "token {}", t),
+			Revocation::Case { network, index, case_id } => {
+				write!(fmt, "case {} of {}", case_id, registrar(*network, *index))
+			}
+			Revocation::CaseChannel { network, index, case_id, channel } => {
+				write!(fmt, "case {} of {} on {}", case_id, registrar(*network, *index), channel)
+			}
+		}
+	}
+}
+
+/// A revocation and why and when it was made
+#[derive(Debug, PartialEq, Clone)]
+pub struct Entry {
+	pub revocation: Revocation,
+	pub reason: Option<String>,
+	/// Unix timestamp, in seconds
+	pub revoked_at: u64,
+}
+
+/// An entry as saved in the JSON files
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawEntry {
+	#[serde(skip_serializing_if = "Option::is_none")]
+	token: Option<String>,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	network: Option<u8>,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	index: Option<u8>,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	case_id: Option<u64>,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	channel: Option<String>,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	reason: Option<String>,
+	revoked_at: u64,
+}
+
+impl From<&Entry> for RawEntry {
+	fn from(e: &Entry) -> Self {
+		let (token, case, channel) = match &e.revocation {
+			Revocation::Token(t) => (Some(t.clone()), None, None),
+			Revocation::Case { network, index, case_id } => (None, Some((*network, *index, *case_id)), None),
+			Revocation::CaseChannel { network, index, case_id, channel } => {
+				(None, Some((*network, *index, *case_id)), Some(channel.to_string()))
+			}
+		};
+		Self {
+			token,
+			network: case.map(|c| c.0),
+			index: case.map(|c| c.1),
+			case_id: case.map(|c| c.2),
+			channel,
+			reason: e.reason.clone(),
+			revoked_at: e.revoked_at,
+		}
+	}
+}
+
+impl RawEntry {
+	fn into_entry(self) -> Result<Entry, Error> {
+		let case = match (self.network, self.index, self.case_id) {
+			(Some(network), Some(index), Some(case_id)) => Some((network, index, case_id)),
+			(None, None, None) => None,
+			_ => {
+				return Err(Error::InvalidRevocationList(String::from(
+					"A case is given by its network, its registrar index and its case id",
+				)))
+			}
+		};
+		let revocation = match (self.token, case, self.channel) {
+			(Some(token), None, None) => Revocation::Token(clean_token_string(&token)),
+			(None, Some((network, index, case_id)), None) => Revocation::Case { network, index, case_id },
+			(None, Some((network, index, case_id)), Some(channel)) => match Channel::from(channel.as_str()) {
+				Channel::Unknown => return Err(Error::InvalidRevocationList(format!("Unknown channel {:?}", channel))),
+				channel => Revocation::CaseChannel { network, index, case_id, channel },
+			},
+			_ => {
+				return Err(Error::InvalidRevocationList(String::from(
+					"An entry revokes either a token, a case or a case on a channel",
+				)))
+			}
+		};
+		Ok(Entry { revocation, reason: self.reason, revoked_at: self.revoked_at })
+	}
+}
+
+/// A revocation list signed by its issuer
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SignedList {
+	#[serde(default)]
+	scheme: String,
+	signer: String,
+	signature: String,
+	entries: Vec<RawEntry>,
+}
+
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct RevocationList {
+	entries: Vec<Entry>,
+}
+
+impl RevocationList {
+	pub fn entries(&self) -> &[Entry] {
+		&self.entries
+	}
+
+	/// Adds a revocation. Returns false if it was already revoked.
+	pub fn revoke(&mut self, revocation: Revocation, reason: Option<String>, revoked_at: u64) -> bool {
+		if self.entries.iter().any(|e| e.revocation == revocation) {
+			return false;
+		}
+		self.entries.push(Entry { revocation, reason, revoked_at });
+		true
+	}
+
+	/// Adds the entries of `other` that are not in this list yet. Returns the number of entries added.
+	pub fn merge(&mut self, other: RevocationList) -> usize {
+		other.entries.into_iter().filter(|e| self.revoke(e.revocation.clone(), e.reason.clone(), e.revoked_at)).count()
+	}
+
+	/// Returns the entry revoking the token, if any
+	pub fn find(&self, token: &impl Tokenize) -> Option<&Entry> {
+		self.entries.iter().find(|e| e.revocation.matches(token))
+	}
+
+	/// Fails with [Error::Revoked] if the token is revoked
+	pub fn check(&self, token: &impl Tokenize) -> Result<(), Error> {
+		match self.find(token) {
+			Some(entry) => Err(Error::Revoked(entry.revocation.clone())),
+			None => Ok(()),
+		}
+	}
+
+	fn raw_entries(&self) -> Vec<RawEntry> {
+		self.entries.iter().map(RawEntry::from).collect()
+	}
+
+	fn from_raw(entries: Vec<RawEntry>) -> Result<Self, Error> {
+		let mut list = Self::default();
+		for entry in entries {
+			let entry = entry.into_entry()?;
+			list.revoke(entry.revocation, entry.reason, entry.revoked_at);
+		}
+		Ok(list)
+	}
+
+	/// Parses a list saved with [RevocationList::to_json]
+	pub fn parse(json: &str) -> Result<Self, Error> {
+		Self::from_raw(serde_json::from_str(json).map_err(|e| Error::InvalidRevocationList(e.to_string()))?)
+	}
+
+	pub fn to_json(&self) -> String {
+		serde_json::to_string_pretty(&self.raw_entries()).expect("entries serialize")
+	}
+
+	/// Loads a list saved locally. A missing file is an empty list.
+	pub fn load(path: &Path) -> Result<Self, Error> {
+		match path.exists() {
+			true => Self::parse(&fs::read_to_string(path).map_err(|e| Error::InvalidRevocationList(e.to_string()))?),
+			false => Ok(Self::default()),
+		}
+	}
+
+	pub fn save(&self, path: &Path) -> Result<(), Error> {
+		fs::write(path, self.to_json())
+			.map_err(|e| Error::InvalidRevocationList(format!("Cannot write {}: {}", path.display(), e)))
+	}
+
+	/// Exports the list, signed with `key`
+	pub fn sign(&self, key: &Keypair) -> String {
+		let entries = self.raw_entries();
+		let message = serde_json::to_vec(&entries).expect("entries serialize");
+		let signed = SignedList {
+			scheme: key.scheme().to_string(),
+			signer: key.public().to_string(),
+			signature: to_hex(&key.sign(DOMAIN, &message)),
+			entries,
+		};
+		serde_json::to_string_pretty(&signed).expect("signed list serializes")
+	}
+
+	/// Imports a list exported with [RevocationList::sign]. It must be signed by one of the `trusted` keys.
+	/// Lists without a scheme are signed with ed25519.
+	pub fn from_signed(json: &str, trusted: &[AccountId]) -> Result<Self, Error> {
+		let signed: SignedList = serde_json::from_str(json).map_err(|e| Error::InvalidRevocationList(e.to_string()))?;
+		let scheme = match signed.scheme.as_str() {
+			"" => Scheme::Ed25519,
+			s => s.parse().map_err(Error::InvalidRevocationList)?,
+		};
+		let signer: AccountId = signed.signer.parse()?;
+		if !trusted.contains(&signer) {
+			return Err(Error::InvalidSignature);
+		}
+
+		let signature =
+			signed.signature.strip_prefix("0x").and_then(signing::from_hex).ok_or(Error::InvalidSignature)?;
+		let message = serde_json::to_vec(&signed.entries).expect("entries serialize");
+		signing::verify(scheme, &signer, DOMAIN, &message, &signature)?;
+		Self::from_raw(signed.entries)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::{TokenV01, Version};
+
+	fn token(case_id: u64, channel: Channel) -> TokenV01 {
+		TokenV01::new_with_secret(0, Version::V01, 2, 1, case_id, channel, "JXBACTSP")
+	}
+
+	fn case(case_id: u64) -> Revocation {
+		Revocation::Case { network: 2, index: 1, case_id }
+	}
+
+	fn case_channel(case_id: u64, channel: Channel) -> Revocation {
+		Revocation::CaseChannel { network: 2, index: 1, case_id, channel }
+	}
+
+	#[test]
+	fn it_revokes_tokens_cases_and_channels() {
+		let mut list = RevocationList::default();
+		assert!(list.revoke(Revocation::Token(token(1, Channel::Twitter).to_string()), None, 0));
+		assert!(list.revoke(case(2), Some(String::from("leaked")), 0));
+		assert!(list.revoke(case_channel(3, Channel::Email), None, 0));
+		assert!(!list.revoke(case(2), None, 1));
+
+		assert_eq!(Err(Error::Revoked(case(2))), list.check(&token(2, Channel::Matrix)));
+		assert!(list.check(&token(1, Channel::Twitter)).is_err());
+		assert!(list.check(&token(1, Channel::Email)).is_ok());
+		assert!(list.check(&token(3, Channel::Email)).is_err());
+		assert!(list.check(&token(3, Channel::Twitter)).is_ok());
+	}
+
+	#[test]
+	fn it_scopes_cases_to_their_registrar() {
+		let mut list = RevocationList::default();
+		list.revoke(case(2), None, 0);
+		assert!(list.check(&TokenV01::new(0, Version::V01, 2, 1, 2, Channel::Email)).is_err());
+		assert!(list.check(&TokenV01::new(0, Version::V01, 0, 1, 2, Channel::Email)).is_ok());
+		assert!(list.check(&TokenV01::new(0, Version::V01, 2, 3, 2, Channel::Email)).is_ok());
+		assert_eq!("case 2 of registrar 1 of Kusama", case(2).to_string());
+		assert_eq!("case 3 of registrar 1 of Kusama on EM", case_channel(3, Channel::Email).to_string());
+	}
+
+	#[test]
+	fn it_saves_and_parses_lists() {
+		let mut list = RevocationList::default();
+		list.revoke(Revocation::Token(String::from("0001020103039TWJXBACTSPAQ")), None, 10);
+		list.revoke(case_channel(3, Channel::Email), Some(String::from("posted on twitter")), 20);
+		assert_eq!(list, RevocationList::parse(&list.to_json()).unwrap());
+
+		assert!(RevocationList::parse(r#"[{ "revoked_at": 0 }]"#).is_err());
+		assert!(RevocationList::parse(
+			r#"[{ "network": 2, "index": 1, "case_id": 1, "channel": "ZZ", "revoked_at": 0 }]"#
+		)
+		.is_err());
+		assert!(RevocationList::parse(r#"[{ "case_id": 1, "revoked_at": 0 }]"#).is_err());
+		assert!(RevocationList::parse(r#"[{ "network": 2, "index": 1, "case_id": 1, "revoked_at": 0 }]"#).is_ok());
+	}
+
+	#[test]
+	fn it_imports_signed_lists() {
+		for scheme in [Scheme::Ed25519, Scheme::Sr25519] {
+			let key = Keypair::generate(scheme);
+			let mut list = RevocationList::default();
+			list.revoke(case(7), Some(String::from("leaked")), 10);
+			let signed = list.sign(&key);
+
+			assert_eq!(Ok(list.clone()), RevocationList::from_signed(&signed, &[key.public()]));
+			assert_eq!(Err(Error::InvalidSignature), RevocationList::from_signed(&signed, &[AccountId([1; 32])]));
+
+			let tampered = signed.replace("\"case_id\": 7", "\"case_id\": 8");
+			assert_ne!(signed, tampered);
+			assert_eq!(Err(Error::InvalidSignature), RevocationList::from_signed(&tampered, &[key.public()]));
+		}
+	}
+
+	#[test]
+	fn it_merges_lists() {
+		let mut list = RevocationList::default();
+		list.revoke(case(1), None, 0);
+		let mut other = RevocationList::default();
+		other.revoke(case(1), None, 5);
+		other.revoke(case(2), None, 5);
+		assert_eq!(1, list.merge(other));
+		assert_eq!(2, list.entries().len());
+	}
+}
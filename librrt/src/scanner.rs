@@ -0,0 +1,215 @@
+use crate::builder::Builder;
+use crate::detector::Detector;
+use crate::envelope;
+use crate::types::{AccountId, Version};
+use crate::versions::{Token, TokenV03, TokenV04, Tokenize};
+
+/// Separators that may be found between the fields of a token
+const SEPARATORS: [char; 3] = ['-', '_', '.'];
+
+/// The sizes of the tokens we look for, the longest first: V03 tokens are 26 to 33 chars
+/// and V04 tokens 22 to 37 chars
+const SIZES: [usize; 16] = [37, 36, 35, 34, 33, 32, 31, 30, 29, 28, 27, 26, 25, 24, 23, 22];
+
+/// A token found in a text
+#[derive(Debug)]
+pub struct Found {
+	pub token: Token,
+	/// Byte offset of the token in the text
+	pub offset: usize,
+	/// The token as it appears in the text, separators included
+	pub raw: String,
+}
+
+/// The Scanner finds valid tokens in a free text such as a remark.
+#[derive(Debug)]
+pub struct Scanner {}
+
+impl Scanner {
+	/// Returns all the valid tokens found in `text`, in order.
+	/// Tokens may use separators but must be upper case, unless they are in an envelope.
+	/// V02 tokens can only be checked with their account and are not found, see `scan_for_account`.
+	pub fn scan(text: &str) -> Vec<Found> {
+		Self::scan_with(text, None)
+	}
+
+	/// Like `scan`, also finding the V02 tokens issued for `account`, such as the signer of a remark
+	pub fn scan_for_account(text: &str, account: &AccountId) -> Vec<Found> {
+		Self::scan_with(text, Some(account))
+	}
+
+	fn scan_with(text: &str, account: Option<&AccountId>) -> Vec<Found> {
+		let envelopes = Self::scan_envelopes(text);
+		let mut found = Vec::new();
+		let mut run: Vec<(usize, char)> = Vec::new();
+
+		for (offset, c) in text.char_indices().chain(std::iter::once((text.len(), ' '))) {
+			match c {
+				'A'..='Z' | '0'..='9' => run.push((offset, c)),
+				c if SEPARATORS.contains(&c) && !run.is_empty() => {}
+				_ => {
+					Self::scan_run(text, &run, account, &mut found);
+					run.clear();
+				}
+			}
+		}
+
+		// The tokens inside the envelopes were found with their envelope
+		found.retain(|f| !envelopes.iter().any(|e| (e.offset..e.offset + e.raw.len()).contains(&f.offset)));
+		found.extend(envelopes);
+		found.sort_by_key(|f| f.offset);
+		found
+	}
+
+	/// Returns the valid envelopes such as `rrt1-ksm-...` found in `text`
+	fn scan_envelopes(text: &str) -> Vec<Found> {
+		let starts = text.char_indices().filter(|(offset, _)| {
+			envelope::is_envelope(&text[*offset..])
+				&& !text[..*offset].chars().next_back().is_some_and(|c| c.is_ascii_alphanumeric())
+		});
+		starts
+			.filter_map(|(offset, _)| {
+				let len = text[offset..]
+					.find(|c: char| !c.is_ascii_alphanumeric() && c != '-')
+					.unwrap_or(text.len() - offset);
+				let raw = text[offset..offset + len].trim_end_matches('-');
+				let token = Builder::build_from_envelope(raw).ok()?;
+				Some(Found { token, offset, raw: String::from(raw) })
+			})
+			.collect()
+	}
+
+	/// Looks for tokens in a run of token chars, `run` holds the offset of each char
+	fn scan_run(text: &str, run: &[(usize, char)], account: Option<&AccountId>, found: &mut Vec<Found>) {
+		let chars: String = run.iter().map(|(_, c)| c).collect();
+		let mut start = 0;
+
+		'outer: while start + SIZES[SIZES.len() - 1] <= chars.len() {
+			for size in SIZES.iter().filter(|size| start + *size <= chars.len()) {
+				let candidate = &chars[start..start + size];
+				let supported = match Detector::analyze(candidate) {
+					Ok((Some(_), Some(Version::V00), 24)) | Ok((Some(_), Some(Version::V01), 25)) => true,
+					Ok((Some(_), Some(Version::V02), 25)) => account.is_some(),
+					Ok((Some(_), Some(Version::V03), size)) => {
+						TokenV03::secret_length(candidate).map(TokenV03::size_for) == Some(size)
+					}
+					Ok((Some(_), Some(Version::V04), size)) => {
+						TokenV04::parse_case_id_length(candidate).map(TokenV04::size_for) == Some(size)
+					}
+					_ => false,
+				};
+				if !supported {
+					continue;
+				}
+				let token = match account {
+					Some(account) => Builder::build_for_account(candidate, account),
+					None => Builder::build_with_variant(candidate),
+				};
+				if let Ok(token) = token {
+					let (from, to) = (run[start].0, run[start + size - 1].0 + 1);
+					found.push(Found { token, offset: from, raw: String::from(&text[from..to]) });
+					start += size;
+					continue 'outer;
+				}
+			}
+			start += 1;
+		}
+	}
+}
+
+impl Found {
+	/// Returns the token without separators
+	pub fn token_string(&self) -> String {
+		self.token.format_string("")
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn it_finds_tokens_in_text() {
+		let found = Scanner::scan("Hello, my token is 0001020103039TWJXBACTSPAQ, thanks!");
+		assert_eq!(1, found.len());
+		assert_eq!("0001020103039TWJXBACTSPAQ", found[0].token_string());
+		assert_eq!(19, found[0].offset);
+	}
+
+	#[test]
+	fn it_finds_tokens_with_separators() {
+		let text = "00-01-02-01-03039-TW-JXBACTSP-AQ and 0000000012345TWRAJQFIZWW";
+		let found = Scanner::scan(text);
+		assert_eq!(2, found.len());
+		assert_eq!("00-01-02-01-03039-TW-JXBACTSP-AQ", found[0].raw);
+		assert_eq!("0000000012345TWRAJQFIZWW", found[1].token_string());
+	}
+
+	#[test]
+	fn it_finds_glued_tokens() {
+		let found = Scanner::scan("XX0001020103039TWJXBACTSPAQ0000000012345TWRAJQFIZWW");
+		assert_eq!(2, found.len());
+		assert_eq!(2, found[0].offset);
+	}
+
+	#[test]
+	fn it_finds_v03_tokens() {
+		let token = TokenV03::new(0, 2, 1, 12345, crate::Channel::Email, 12).format_string("-");
+		let found = Scanner::scan(&format!("Remark: {} 0001020103039TWJXBACTSPAQ", token));
+		assert_eq!(2, found.len());
+		assert_eq!(token, found[0].raw);
+		assert_eq!(Version::V03, *found[0].token.version());
+	}
+
+	#[test]
+	fn it_finds_v04_tokens() {
+		let small = TokenV04::new(0, 2, 1, 7, crate::Channel::Email).to_string();
+		let large = TokenV04::new(0, 2, 1, u64::MAX, crate::Channel::Email).format_string("-");
+		let found = Scanner::scan(&format!("{} {}", large, small));
+		assert_eq!(2, found.len());
+		assert_eq!(u64::MAX, *found[0].token.case_id());
+		assert_eq!(small, found[1].raw);
+	}
+
+	#[test]
+	fn it_finds_v02_tokens_of_the_account() {
+		let (alice, bob) = (AccountId([1; 32]), AccountId([2; 32]));
+		let token = crate::TokenV02::new(0, 2, 1, 12345, crate::Channel::Email, &alice).format_string("-");
+		let text = format!("{} 0001020103039TWJXBACTSPAQ", token);
+		assert_eq!(1, Scanner::scan(&text).len());
+		assert_eq!(1, Scanner::scan_for_account(&text, &bob).len());
+
+		let found = Scanner::scan_for_account(&text, &alice);
+		assert_eq!(2, found.len());
+		assert_eq!(token, found[0].raw);
+		assert_eq!(Version::V02, *found[0].token.version());
+	}
+
+	#[test]
+	fn it_finds_envelopes() {
+		let token = Builder::build_with_variant("0001020103039TWJXBACTSPAQ").unwrap();
+		let text = format!(
+			"Mine is {}, and {}. 0000000012345TWRAJQFIZWW",
+			token.to_envelope(),
+			token.to_envelope().to_uppercase()
+		);
+		let found = Scanner::scan(&text);
+		assert_eq!(3, found.len());
+		assert_eq!(token.to_envelope(), found[0].raw);
+		assert_eq!(8, found[0].offset);
+		assert_eq!("0001020103039TWJXBACTSPAQ", found[1].token_string());
+		assert_eq!(token.to_envelope().to_uppercase(), found[1].raw);
+		assert_eq!("0000000012345TWRAJQFIZWW", found[2].token_string());
+
+		let typo = token.to_envelope().replace("039tw", "093tw");
+		assert!(Scanner::scan(&typo).is_empty());
+	}
+
+	#[test]
+	fn it_ignores_invalid_tokens() {
+		assert!(Scanner::scan("0001020103039TWJXBACTSPAA").is_empty());
+		assert!(Scanner::scan("").is_empty());
+		assert!(Scanner::scan("0001020103039twjxbactspaq").is_empty());
+		assert!(Scanner::scan("0001ZZ0103039TWJXBACTSPAQ 0000ZZ0012345TWRAJQFIZWW").is_empty());
+	}
+}
@@ -0,0 +1,163 @@
+//! Tokens authenticated by the registrar.
+//!
+//! The checksum of a token only catches typos: anyone can produce a valid token.
+//! A registrar may hand out a signed token instead, which anyone can check offline
+//! with the public key of the registrar, such as:
+//!
+//! `0001020103039TWJXBACTSPAQ.E1Q2W...`
+//!
+//! The token is followed by a `.`, a letter for the scheme (`E` for ed25519, `S` for sr25519)
+//! and the signature in Crockford base32. The signature is not truncated since a truncated
+//! public key signature could not be verified.
+
+use crate::builder::Builder;
+use crate::error::Error;
+use crate::registry::{Registrar, Registry};
+use crate::signing::{self, Keypair, Scheme};
+use crate::types::{AccountId, Alphabet};
+use crate::utils::{clean_token_string, from_base32, to_base32};
+use crate::versions::{Token, Tokenize};
+use std::convert::TryInto;
+use std::fmt::Display;
+
+/// Signed along the token
+const DOMAIN: &[u8] = b"rrt-token:";
+
+/// Separates the token from its signature
+pub const SIGNATURE_SEPARATOR: char = '.';
+
+/// A token and the signature of its registrar
+#[derive(Debug)]
+pub struct SignedToken {
+	pub token: Token,
+	pub scheme: Scheme,
+	pub signature: [u8; 64],
+}
+
+impl SignedToken {
+	/// Signs the token with the key of the registrar
+	pub fn sign(token: Token, key: &Keypair) -> Self {
+		let signature = key.sign(DOMAIN, token.format_string("").as_bytes());
+		Self { token, scheme: key.scheme(), signature }
+	}
+
+	/// Parses a signed token. The token may contain separators.
+	pub fn parse(s: &str) -> Result<Self, Error> {
+		Self::parse_with(s, Builder::build_with_variant)
+	}
+
+	/// Like `parse` but also accepts the tokens whose checksum depends on the account, such as V02
+	pub fn parse_for_account(s: &str, account: &AccountId) -> Result<Self, Error> {
+		Self::parse_with(s, |t| Builder::build_for_account(t, account))
+	}
+
+	fn parse_with<F: Fn(&str) -> Result<Token, Error>>(s: &str, build: F) -> Result<Self, Error> {
+		let invalid = || Error::InvalidEncoding(String::from(s));
+		let (token, signature) = s.trim().rsplit_once(SIGNATURE_SEPARATOR).ok_or_else(invalid)?;
+		let signature = Alphabet::Crockford.normalize(&signature.trim().to_uppercase());
+		let scheme = match signature.chars().next() {
+			Some('E') => Scheme::Ed25519,
+			Some('S') => Scheme::Sr25519,
+			_ => return Err(invalid()),
+		};
+		let signature = from_base32(&signature[1..]).and_then(|s| s.try_into().ok()).ok_or_else(invalid)?;
+		Ok(Self { token: build(&clean_token_string(token))?, scheme, signature })
+	}
+
+	/// Checks that the token was signed by `signer`
+	pub fn verify(&self, signer: &AccountId) -> Result<(), Error> {
+		signing::verify(self.scheme, signer, DOMAIN, self.token.format_string("").as_bytes(), &self.signature)
+	}
+
+	/// Checks that the token was signed by the registrar it targets, and returns that registrar
+	pub fn verify_with_registry<'a>(&self, registry: &'a Registry) -> Result<&'a Registrar, Error> {
+		let registrar = registry.check(&self.token)?;
+		self.verify(&registrar.account)?;
+		Ok(registrar)
+	}
+}
+
+impl Display for SignedToken {
+	fn fmt(&self, fmt: &mut std::fmt::Formatter<'_>) -> std::result::Result<(), std::fmt::Error> {
+		let scheme = match self.scheme {
+			Scheme::Ed25519 => 'E',
+			Scheme::Sr25519 => 'S',
+		};
+		write!(fmt, "{}{}{}{}", self.token, SIGNATURE_SEPARATOR, scheme, to_base32(&self.signature))
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::{Channel, Registrar, TokenV01, TokenV02, Version};
+
+	fn token(case_id: u64) -> Token {
+		Token::V01(TokenV01::new_with_secret(0, Version::V01, 2, 1, case_id, Channel::Twitter, "JXBACTSP"))
+	}
+
+	#[test]
+	fn it_signs_and_verifies_tokens() {
+		for scheme in [Scheme::Ed25519, Scheme::Sr25519] {
+			let key = Keypair::generate(scheme);
+			let signed = SignedToken::sign(token(12345), &key).to_string();
+			assert!(signed.starts_with(&format!("{}.", token(12345))));
+
+			let parsed = SignedToken::parse(&signed).unwrap();
+			assert_eq!(signed, parsed.to_string());
+			assert_eq!(Ok(()), parsed.verify(&key.public()));
+			assert_eq!(Err(Error::InvalidSignature), parsed.verify(&Keypair::generate(scheme).public()));
+		}
+	}
+
+	#[test]
+	fn it_rejects_forged_tokens() {
+		let key = Keypair::generate(Scheme::Ed25519);
+		let signed = SignedToken::sign(token(12345), &key);
+		let forged = SignedToken { token: token(12346), ..signed };
+		assert_eq!(Err(Error::InvalidSignature), forged.verify(&key.public()));
+
+		let signed = SignedToken::sign(token(12345), &key).to_string();
+		let (token, signature) = signed.split_once('.').unwrap();
+		assert!(SignedToken::parse(token).is_err());
+		assert!(SignedToken::parse(&format!("{}.X{}", token, &signature[1..])).is_err());
+		assert!(SignedToken::parse(&format!("{}.{}", token, &signature[..50])).is_err());
+	}
+
+	#[test]
+	fn it_parses_signatures_typed_by_hand() {
+		let key = Keypair::generate(Scheme::Sr25519);
+		let signed = SignedToken::sign(token(12345), &key).to_string();
+		let (token, signature) = signed.split_once('.').unwrap();
+		let typed = format!("{} . {}", token, signature.to_lowercase().replace('0', "o").replace('1', "l"));
+		assert_eq!(Ok(()), SignedToken::parse(&typed).unwrap().verify(&key.public()));
+	}
+
+	#[test]
+	fn it_verifies_with_the_registry() {
+		let key = Keypair::generate(Scheme::Ed25519);
+		let registrar = Registrar { network: 2, index: 1, name: String::from("test"), account: key.public(), fee: 0 };
+		let registry = Registry::new(vec![registrar.clone()]).unwrap();
+
+		let signed = SignedToken::sign(token(12345), &key);
+		assert_eq!(Ok(&registrar), signed.verify_with_registry(&registry));
+
+		let other = Keypair::generate(Scheme::Ed25519);
+		let signed = SignedToken::sign(token(12345), &other);
+		assert_eq!(Err(Error::InvalidSignature), signed.verify_with_registry(&registry));
+		assert!(matches!(
+			signed.verify_with_registry(&Registry::default()),
+			Err(Error::UnknownRegistrar { network: 2, index: 1 })
+		));
+	}
+
+	#[test]
+	fn it_parses_signed_tokens_for_accounts() {
+		let key = Keypair::generate(Scheme::Ed25519);
+		let account = AccountId([7; 32]);
+		let token = Token::V02(TokenV02::new_with_secret(0, 2, 1, 12345, Channel::Twitter, "JXBACTSP", &account));
+		let signed = SignedToken::sign(token, &key).to_string();
+		assert_eq!(Err(Error::AccountRequired), SignedToken::parse(&signed).map(|_| ()));
+		assert_eq!(Ok(()), SignedToken::parse_for_account(&signed, &account).unwrap().verify(&key.public()));
+	}
+}
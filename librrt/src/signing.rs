@@ -0,0 +1,184 @@
+//! Signatures of the registrars, over tokens or over the documents they share such as revocation lists.
+//!
+//! Both signature schemes of Substrate accounts are supported, so a registrar may sign
+//! with the key of its account. The public key of a signer is given as an [AccountId],
+//! so it can be written as an SS58 address or in hex like any other account.
+
+use crate::error::Error;
+use crate::types::AccountId;
+use ed25519_dalek::{Signer, Verifier};
+use rand::rngs::OsRng;
+use rand::RngCore;
+use schnorrkel::{ExpansionMode, MiniSecretKey};
+use std::convert::TryInto;
+use std::fmt::Display;
+use std::str::FromStr;
+
+/// The signing context of sr25519 signatures, the one of Substrate
+const SR25519_CONTEXT: &[u8] = b"substrate";
+
+/// A signature scheme
+#[derive(Debug, PartialEq, Clone, Copy, Default)]
+pub enum Scheme {
+	#[default]
+	Ed25519,
+	Sr25519,
+}
+
+impl FromStr for Scheme {
+	type Err = String;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		match s {
+			"ed25519" => Ok(Scheme::Ed25519),
+			"sr25519" => Ok(Scheme::Sr25519),
+			_ => Err(format!("Unknown scheme {:?}, expected ed25519 or sr25519", s)),
+		}
+	}
+}
+
+impl Display for Scheme {
+	fn fmt(&self, fmt: &mut std::fmt::Formatter<'_>) -> std::result::Result<(), std::fmt::Error> {
+		match self {
+			Scheme::Ed25519 => write!(fmt, "ed25519"),
+			Scheme::Sr25519 => write!(fmt, "sr25519"),
+		}
+	}
+}
+
+/// A signing key and the scheme it signs with. Both are derived from a 32 bytes seed
+/// as Substrate does, so a seed gives the same account as with `subkey`.
+pub struct Keypair {
+	scheme: Scheme,
+	seed: [u8; 32],
+}
+
+impl Keypair {
+	pub fn from_seed(scheme: Scheme, seed: [u8; 32]) -> Self {
+		Self { scheme, seed }
+	}
+
+	/// Generates a new key using the CSPRNG of the OS
+	pub fn generate(scheme: Scheme) -> Self {
+		let mut seed = [0u8; 32];
+		OsRng.fill_bytes(&mut seed);
+		Self::from_seed(scheme, seed)
+	}
+
+	/// Parses a key written with `to_secret_string()`, such as `sr25519:0x...`.
+	/// A key without a scheme is an ed25519 key.
+	pub fn parse(s: &str) -> Result<Self, Error> {
+		let s = s.trim();
+		let (scheme, hex) = match s.split_once(':') {
+			Some((scheme, hex)) => (scheme.parse().map_err(Error::InvalidKey)?, hex),
+			None => (Scheme::Ed25519, s),
+		};
+		let bytes = from_hex(hex.strip_prefix("0x").unwrap_or(hex))
+			.ok_or_else(|| Error::InvalidKey(String::from("not hex")))?;
+		let seed = bytes.try_into().map_err(|_| Error::InvalidKey(String::from("a key is 32 bytes")))?;
+		Ok(Self::from_seed(scheme, seed))
+	}
+
+	pub fn scheme(&self) -> Scheme {
+		self.scheme
+	}
+
+	/// Returns the public key
+	pub fn public(&self) -> AccountId {
+		match self.scheme {
+			Scheme::Ed25519 => AccountId(ed25519_dalek::SigningKey::from_bytes(&self.seed).verifying_key().to_bytes()),
+			Scheme::Sr25519 => AccountId(self.sr25519().public.to_bytes()),
+		}
+	}
+
+	fn sr25519(&self) -> schnorrkel::Keypair {
+		MiniSecretKey::from_bytes(&self.seed).expect("a seed is 32 bytes").expand_to_keypair(ExpansionMode::Ed25519)
+	}
+
+	/// Signs `message`. The domain is signed along the message so a signature
+	/// made for one kind of document cannot be replayed for another.
+	pub fn sign(&self, domain: &[u8], message: &[u8]) -> [u8; 64] {
+		let data = [domain, message].concat();
+		match self.scheme {
+			Scheme::Ed25519 => ed25519_dalek::SigningKey::from_bytes(&self.seed).sign(&data).to_bytes(),
+			Scheme::Sr25519 => self.sr25519().sign_simple(SR25519_CONTEXT, &data).to_bytes(),
+		}
+	}
+
+	/// Writes the scheme and the seed, such as `sr25519:0x...`. It must be kept secret,
+	/// which is why a key has no `Display`: it cannot end up in a log by accident.
+	pub fn to_secret_string(&self) -> String {
+		let seed: String = self.seed.iter().map(|b| format!("{:02x}", b)).collect();
+		format!("{}:0x{}", self.scheme, seed)
+	}
+}
+
+/// Decodes a hex string without prefix
+pub(crate) fn from_hex(s: &str) -> Option<Vec<u8>> {
+	if !s.len().is_multiple_of(2) {
+		return None;
+	}
+	(0..s.len()).step_by(2).map(|i| u8::from_str_radix(s.get(i..i + 2)?, 16).ok()).collect()
+}
+
+/// Checks a signature made with [Keypair::sign]
+pub fn verify(
+	scheme: Scheme,
+	signer: &AccountId,
+	domain: &[u8],
+	message: &[u8],
+	signature: &[u8],
+) -> Result<(), Error> {
+	let data = [domain, message].concat();
+	match scheme {
+		Scheme::Ed25519 => {
+			let key =
+				ed25519_dalek::VerifyingKey::from_bytes(signer.as_bytes()).map_err(|_| Error::InvalidSignature)?;
+			let signature = ed25519_dalek::Signature::from_slice(signature).map_err(|_| Error::InvalidSignature)?;
+			key.verify(&data, &signature).map_err(|_| Error::InvalidSignature)
+		}
+		Scheme::Sr25519 => {
+			let key = schnorrkel::PublicKey::from_bytes(signer.as_bytes()).map_err(|_| Error::InvalidSignature)?;
+			let signature = schnorrkel::Signature::from_bytes(signature).map_err(|_| Error::InvalidSignature)?;
+			key.verify_simple(SR25519_CONTEXT, &data, &signature).map_err(|_| Error::InvalidSignature)
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn it_signs_and_verifies() {
+		for scheme in [Scheme::Ed25519, Scheme::Sr25519] {
+			let key = Keypair::generate(scheme);
+			let signature = key.sign(b"test", b"message");
+			assert_eq!(Ok(()), verify(scheme, &key.public(), b"test", b"message", &signature));
+			assert_eq!(Err(Error::InvalidSignature), verify(scheme, &key.public(), b"other", b"message", &signature));
+			assert_eq!(Err(Error::InvalidSignature), verify(scheme, &key.public(), b"test", b"massage", &signature));
+			let other = Keypair::generate(scheme).public();
+			assert_eq!(Err(Error::InvalidSignature), verify(scheme, &other, b"test", b"message", &signature));
+		}
+	}
+
+	#[test]
+	fn it_does_not_mix_schemes() {
+		let key = Keypair::generate(Scheme::Ed25519);
+		let signature = key.sign(b"test", b"message");
+		assert!(verify(Scheme::Sr25519, &key.public(), b"test", b"message", &signature).is_err());
+	}
+
+	#[test]
+	fn it_parses_keys() {
+		let key = Keypair::generate(Scheme::Sr25519);
+		let parsed = Keypair::parse(&key.to_secret_string()).unwrap();
+		assert_eq!((key.scheme(), key.public()), (parsed.scheme(), parsed.public()));
+
+		let key = Keypair::parse("0x0101010101010101010101010101010101010101010101010101010101010101\n").unwrap();
+		assert_eq!(Scheme::Ed25519, key.scheme());
+		assert!(Keypair::parse("0101").is_err());
+		assert!(Keypair::parse("junk").is_err());
+		assert!(Keypair::parse("rsa:0x0101010101010101010101010101010101010101010101010101010101010101").is_err());
+	}
+}
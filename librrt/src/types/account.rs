@@ -0,0 +1,126 @@
+use crate::error::Error;
+use crate::signing;
+use blake2::{Blake2b512, Digest};
+use std::convert::TryFrom;
+use std::fmt::Display;
+use std::str::FromStr;
+
+const SS58_PREFIX: &[u8] = b"SS58PRE";
+const CHECKSUM_LEN: usize = 2;
+
+/// The public key of a Substrate account
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub struct AccountId(pub [u8; 32]);
+
+fn ss58_checksum(data: &[u8]) -> [u8; CHECKSUM_LEN] {
+	let mut hasher = Blake2b512::new();
+	hasher.update(SS58_PREFIX);
+	hasher.update(data);
+	let hash = hasher.finalize();
+	[hash[0], hash[1]]
+}
+
+impl AccountId {
+	pub fn as_bytes(&self) -> &[u8; 32] {
+		&self.0
+	}
+
+	/// Decodes an SS58 address and returns the account with the network prefix of the address
+	pub fn from_ss58(s: &str) -> Result<(Self, u16), Error> {
+		let invalid = || Error::InvalidAccount(String::from(s));
+		let data = bs58::decode(s).into_vec().map_err(|_| invalid())?;
+
+		let (prefix, prefix_len) = match data.first() {
+			Some(b) if *b < 64 => (*b as u16, 1),
+			Some(b) if *b < 128 && data.len() > 1 => {
+				let lower = (b << 2) | (data[1] >> 6);
+				let upper = data[1] & 0b0011_1111;
+				(lower as u16 | (upper as u16) << 8, 2)
+			}
+			_ => return Err(invalid()),
+		};
+		if data.len() != prefix_len + 32 + CHECKSUM_LEN {
+			return Err(invalid());
+		}
+
+		let (payload, checksum) = data.split_at(prefix_len + 32);
+		if ss58_checksum(payload) != checksum {
+			return Err(invalid());
+		}
+
+		let mut account = [0u8; 32];
+		account.copy_from_slice(&payload[prefix_len..]);
+		Ok((Self(account), prefix))
+	}
+
+	/// Encodes the account as an SS58 address for the given network prefix
+	pub fn to_ss58(&self, prefix: u16) -> String {
+		let mut data = match prefix {
+			0..=63 => vec![prefix as u8],
+			_ => vec![
+				((prefix & 0b1111_1100) >> 2) as u8 | 0b0100_0000,
+				(prefix >> 8) as u8 | ((prefix & 0b11) << 6) as u8,
+			],
+		};
+		data.extend_from_slice(&self.0);
+		let checksum = ss58_checksum(&data);
+		data.extend_from_slice(&checksum);
+		bs58::encode(data).into_string()
+	}
+}
+
+impl FromStr for AccountId {
+	type Err = Error;
+
+	/// Parses an SS58 address, or a public key given in hex with a `0x` prefix
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		let hex = match s.strip_prefix("0x") {
+			Some(hex) => hex,
+			None => return Self::from_ss58(s).map(|(account, _)| account),
+		};
+
+		let invalid = || Error::InvalidAccount(String::from(s));
+		let bytes = signing::from_hex(hex).ok_or_else(invalid)?;
+		let account = <[u8; 32]>::try_from(bytes.as_slice()).map_err(|_| invalid())?;
+		Ok(Self(account))
+	}
+}
+
+impl Display for AccountId {
+	fn fmt(&self, fmt: &mut std::fmt::Formatter<'_>) -> std::result::Result<(), std::fmt::Error> {
+		write!(fmt, "0x")?;
+		self.0.iter().try_for_each(|b| write!(fmt, "{:02x}", b))
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	const ALICE: &str = "0xd43593c715fdd31c61141abd04a99fd6822c8558854ccde39a5684e7a56da27d";
+
+	#[test]
+	fn it_decodes_ss58() {
+		let alice = AccountId::from_str(ALICE).unwrap();
+		assert_eq!(Ok((alice, 42)), AccountId::from_ss58("5GrwvaEF5zXb26Fz9rcQpDWS57CtERHpNehXCPcNoHGKutQY"));
+		assert_eq!(Ok((alice, 0)), AccountId::from_ss58("15oF4uVJwmo4TdGW7VfQxNLavjCXviqxT9S1MgbjMNHr6Sp5"));
+		assert_eq!(Ok((alice, 2)), AccountId::from_ss58("HNZata7iMYWmk5RvZRTiAsSDhV8366zq2YGb3tLH5Upf74F"));
+		assert_eq!(ALICE, alice.to_string());
+	}
+
+	#[test]
+	fn it_round_trips_ss58() {
+		let alice = AccountId::from_str(ALICE).unwrap();
+		for prefix in &[0u16, 2, 42, 63, 64, 1284, 16383] {
+			assert_eq!(Ok((alice, *prefix)), AccountId::from_ss58(&alice.to_ss58(*prefix)));
+		}
+	}
+
+	#[test]
+	fn it_rejects_bad_accounts() {
+		assert!(AccountId::from_str("5GrwvaEF5zXb26Fz9rcQpDWS57CtERHpNehXCPcNoHGKutQZ").is_err());
+		assert!(AccountId::from_str("0xd435").is_err());
+		assert!(AccountId::from_str("not an account").is_err());
+		assert!(AccountId::from_str(&format!("0x{}", "a€".repeat(16))).is_err());
+	}
+}
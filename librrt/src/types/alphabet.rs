@@ -0,0 +1,133 @@
+use rand::rngs::OsRng;
+use rand::{CryptoRng, Rng, RngCore};
+use std::fmt::Display;
+use std::str::FromStr;
+
+/// Pairs of characters users mix up when copying a token, as (typed, meant)
+const CONFUSABLES: [(char, char); 6] = [('0', 'O'), ('1', 'I'), ('1', 'L'), ('O', '0'), ('I', '1'), ('L', '1')];
+
+/// The characters a secret is drawn from
+#[derive(Debug, PartialEq, Clone, Copy, Default)]
+pub enum Alphabet {
+	/// A-Z, as used by the first versions
+	#[default]
+	Full,
+
+	/// A-Z without I, L and O which are easily confused with 1 and 0
+	NoConfusables,
+
+	/// The Crockford base32 alphabet: 0-9 and A-Z without I, L, O and U.
+	/// When parsing, I and L are read as 1 and O as 0.
+	Crockford,
+}
+
+impl Alphabet {
+	pub fn chars(&self) -> &'static str {
+		match self {
+			Alphabet::Full => "ABCDEFGHIJKLMNOPQRSTUVWXYZ",
+			Alphabet::NoConfusables => "ABCDEFGHJKMNPQRSTUVWXYZ",
+			Alphabet::Crockford => "0123456789ABCDEFGHJKMNPQRSTVWXYZ",
+		}
+	}
+
+	pub fn contains(&self, c: char) -> bool {
+		self.chars().contains(c)
+	}
+
+	/// Whether every char of this alphabet is in `other`, so that its secrets are valid where `other` is expected
+	pub fn is_subset_of(&self, other: Alphabet) -> bool {
+		self.chars().chars().all(|c| other.contains(c))
+	}
+
+	/// Returns a random string of `length` chars of this alphabet, using the CSPRNG of the OS
+	pub fn random(&self, length: usize) -> String {
+		self.random_with(&mut OsRng, length)
+	}
+
+	/// Like `random` but draws the chars from the given RNG, which allows deterministic tests
+	pub fn random_with<R: RngCore + CryptoRng>(&self, rng: &mut R, length: usize) -> String {
+		let chars = self.chars().as_bytes();
+		(0..length).map(|_| chars[rng.gen_range(0..chars.len())] as char).collect()
+	}
+
+	/// The entropy in bits of a random secret of `length` chars
+	pub fn entropy(&self, length: usize) -> f64 {
+		length as f64 * (self.chars().len() as f64).log2()
+	}
+
+	/// Replaces the chars that are not part of the alphabet by the one they are
+	/// likely confused with, if the alphabet has it. Other chars are kept as is.
+	pub fn normalize(&self, s: &str) -> String {
+		s.chars()
+			.map(|c| match self.contains(c) {
+				true => c,
+				false => {
+					CONFUSABLES.iter().find(|(typed, meant)| *typed == c && self.contains(*meant)).map_or(c, |p| p.1)
+				}
+			})
+			.collect()
+	}
+}
+
+impl FromStr for Alphabet {
+	type Err = String;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		match s {
+			"full" => Ok(Alphabet::Full),
+			"no-confusables" => Ok(Alphabet::NoConfusables),
+			"crockford" => Ok(Alphabet::Crockford),
+			_ => Err(format!("Unknown alphabet {:?}, expected full, no-confusables or crockford", s)),
+		}
+	}
+}
+
+impl Display for Alphabet {
+	fn fmt(&self, fmt: &mut std::fmt::Formatter<'_>) -> std::result::Result<(), std::fmt::Error> {
+		match self {
+			Alphabet::Full => write!(fmt, "full"),
+			Alphabet::NoConfusables => write!(fmt, "no-confusables"),
+			Alphabet::Crockford => write!(fmt, "crockford"),
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn it_generates_secrets_from_the_alphabet() {
+		for alphabet in &[Alphabet::Full, Alphabet::NoConfusables, Alphabet::Crockford] {
+			let secret = alphabet.random(64);
+			assert_eq!(64, secret.len());
+			assert!(secret.chars().all(|c| alphabet.contains(c)), "{} is not {}", secret, alphabet);
+			assert_eq!(Ok(*alphabet), alphabet.to_string().parse());
+		}
+		assert!(!Alphabet::NoConfusables.random(256).contains(['I', 'L', 'O']));
+	}
+
+	#[test]
+	fn it_knows_its_subsets() {
+		assert!(Alphabet::NoConfusables.is_subset_of(Alphabet::Full));
+		assert!(Alphabet::Full.is_subset_of(Alphabet::Full));
+		assert!(!Alphabet::Full.is_subset_of(Alphabet::NoConfusables));
+		assert!(!Alphabet::NoConfusables.is_subset_of(Alphabet::Crockford));
+		assert!(!Alphabet::Crockford.is_subset_of(Alphabet::Full));
+	}
+
+	#[test]
+	fn it_calculates_the_entropy() {
+		assert!((Alphabet::Full.entropy(8) - 37.6).abs() < 0.01);
+		assert!((Alphabet::NoConfusables.entropy(8) - 36.19).abs() < 0.01);
+		assert_eq!(40.0, Alphabet::Crockford.entropy(8));
+	}
+
+	#[test]
+	fn it_maps_confusables_back() {
+		assert_eq!("JXBACOIP", Alphabet::Full.normalize("JXBAC01P"));
+		assert_eq!("01X10U", Alphabet::Crockford.normalize("OLXI0U"));
+		assert_eq!("AB01", Alphabet::NoConfusables.normalize("AB01"));
+		assert_eq!("ABIO", Alphabet::NoConfusables.normalize("ABIO"));
+	}
+}
@@ -1,8 +1,12 @@
+pub use self::account::*;
+pub use self::alphabet::*;
 pub use self::channel::*;
 pub use self::data::*;
 pub use self::network::*;
 pub use self::version::*;
 
+mod account;
+mod alphabet;
 mod channel;
 mod data;
 mod network;
@@ -13,6 +13,39 @@ pub enum KnownNetwork {
 	Westend = 42,
 }
 
+impl KnownNetwork {
+	/// The short name of the network, as used in the token envelopes
+	pub fn short_name(&self) -> &'static str {
+		match self {
+			KnownNetwork::Polkadot => "dot",
+			KnownNetwork::Kusama => "ksm",
+			KnownNetwork::Westend => "wnd",
+		}
+	}
+}
+
+impl Network {
+	/// The short name of the network such as `ksm`, or `net7` for an unknown network
+	pub fn short_name(&self) -> String {
+		match self {
+			Network::Known(n) => String::from(n.short_name()),
+			Network::Unknown(n) => format!("net{}", n),
+		}
+	}
+
+	/// Returns the network of a short name, case insensitive
+	pub fn from_short_name(s: &str) -> Option<Network> {
+		let s = s.to_lowercase();
+		match s.strip_prefix("net") {
+			Some(n) => n.parse::<u8>().ok().map(Network::from).filter(|n| n.short_name() == s),
+			_ => [KnownNetwork::Polkadot, KnownNetwork::Kusama, KnownNetwork::Westend]
+				.iter()
+				.find(|n| n.short_name() == s)
+				.map(|n| Network::Known(*n)),
+		}
+	}
+}
+
 impl From<&str> for Network {
 	fn from(n: &str) -> Self {
 		let n = u8::from_str_radix(n, 16).unwrap_or_else(|_| panic!("Failed parsing {} as u8", n));
@@ -31,9 +64,9 @@ impl From<u8> for Network {
 	}
 }
 
-impl Into<String> for Network {
-	fn into(self) -> String {
-		match self {
+impl From<Network> for String {
+	fn from(n: Network) -> Self {
+		match n {
 			Network::Known(KnownNetwork::Polkadot) => String::from("Polkadot"),
 			Network::Known(KnownNetwork::Kusama) => String::from("Kusama"),
 			Network::Known(KnownNetwork::Westend) => String::from("Westend"),
@@ -42,9 +75,9 @@ impl Into<String> for Network {
 	}
 }
 
-impl Into<u8> for Network {
-	fn into(self) -> u8 {
-		match self {
+impl From<Network> for u8 {
+	fn from(n: Network) -> Self {
+		match n {
 			Network::Known(KnownNetwork::Polkadot) => 0,
 			Network::Known(KnownNetwork::Kusama) => 2,
 			Network::Known(KnownNetwork::Westend) => 42,
@@ -58,3 +91,23 @@ impl Display for Network {
 		write!(fmt, "{:?}", self)
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn it_round_trips_short_names() {
+		for n in [0, 2, 7, 42, 255] {
+			let network = Network::from(n);
+			assert_eq!(Some(network), Network::from_short_name(&network.short_name()));
+		}
+		assert_eq!("ksm", Network::from(2).short_name());
+		assert_eq!(Some(Network::from(2)), Network::from_short_name("KSM"));
+		// Known networks only have their short name
+		assert_eq!(None, Network::from_short_name("net2"));
+		assert_eq!(None, Network::from_short_name("net07"));
+		assert_eq!(None, Network::from_short_name("net256"));
+		assert_eq!(None, Network::from_short_name("btc"));
+	}
+}
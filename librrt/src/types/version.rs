@@ -1,8 +1,11 @@
 //! This file contains the list of the supported versions
-use std::convert::Into;
+use crate::error::Error;
+use crate::types::Alphabet;
+use crate::versions::{V03_DEFAULT_SECRET_LENGTH, V03_SECRET_LENGTHS};
 use std::convert::TryFrom;
 use std::fmt::Display;
 use std::fmt::LowerHex;
+use std::ops::RangeInclusive;
 use std::str::FromStr;
 
 #[derive(Debug, PartialEq, Clone, Copy)]
@@ -13,19 +16,30 @@ pub enum Version {
 
 	/// This version aims at improving the robustness against position swapping using the Fletcher 16 algorithm.
 	V01 = 0x01,
-	// This version does not exist yet. It will likely be reserved for a Blake implementation test.
-	// V02 = 0x02,
+
+	/// Same layout as V01 but the checksum also covers the public key of the account being judged.
+	/// The account is not part of the token so a token remarked by another account fails the check.
+	V02 = 0x02,
+
+	/// A V01 with a longer secret of 8 to 15 chars from the Crockford alphabet. The length of the secret is part of the token.
+	V03 = 0x03,
+
+	/// A V01 whose case id has a variable length, preceded by its length, so any u64 fits.
+	V04 = 0x04,
 	// ...
 	//
 	//VFF = 0xFF,
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Clone)]
 pub enum VersionError {
 	/// The version in the token string is not supported
 	ParseError(String),
 
 	UnsupportedVersion(u8),
+
+	/// The version is supported but not accepted by the policy of the [Builder](crate::Builder)
+	NotAccepted(Version),
 }
 
 impl FromStr for Version {
@@ -41,27 +55,93 @@ impl FromStr for Version {
 		match &v {
 			&"00" => Ok(Version::V00),
 			&"01" => Ok(Version::V01),
-			// &"02" => Version::V02,
+			&"02" => Ok(Version::V02),
+			&"03" => Ok(Version::V03),
+			&"04" => Ok(Version::V04),
 			v if v.parse::<u8>().is_ok() => Err(VersionError::UnsupportedVersion(v.parse().unwrap())),
 			_ => Err(VersionError::ParseError(String::from(vstr))),
 		}
 	}
 }
 
+impl Version {
+	/// All the supported versions, the oldest first
+	pub const ALL: [Version; 5] = [Version::V00, Version::V01, Version::V02, Version::V03, Version::V04];
+
+	/// The alphabet of the secrets this version generates
+	pub fn alphabet(&self) -> Alphabet {
+		match self {
+			Version::V00 | Version::V01 | Version::V02 | Version::V04 => Alphabet::Full,
+			Version::V03 => Alphabet::Crockford,
+		}
+	}
+
+	/// The number of chars of the checksum
+	pub fn checksum_len(&self) -> usize {
+		match self {
+			Version::V00 => 1,
+			Version::V01 | Version::V02 | Version::V03 | Version::V04 => 2,
+		}
+	}
+
+	/// The largest case id this version can encode
+	pub fn max_case_id(&self) -> u64 {
+		match self {
+			Version::V00 | Version::V01 | Version::V02 | Version::V03 => 0xFFFFF,
+			Version::V04 => u64::MAX,
+		}
+	}
+
+	/// Checks that the case id fits in the tokens of this version
+	pub fn check_case_id(&self, case_id: u64) -> Result<(), Error> {
+		match case_id <= self.max_case_id() {
+			true => Ok(()),
+			false => Err(Error::CaseIdOverflow { version: *self, max: self.max_case_id(), found: case_id }),
+		}
+	}
+
+	/// The lengths of the secrets this version supports
+	pub fn secret_lengths(&self) -> RangeInclusive<usize> {
+		match self {
+			Version::V00 | Version::V01 | Version::V02 | Version::V04 => 8..=8,
+			Version::V03 => V03_SECRET_LENGTHS,
+		}
+	}
+
+	/// The length of the secrets this version generates
+	pub fn default_secret_length(&self) -> usize {
+		match self {
+			Version::V00 | Version::V01 | Version::V02 | Version::V04 => 8,
+			Version::V03 => V03_DEFAULT_SECRET_LENGTH,
+		}
+	}
+
+	/// Checks that the secret fits in the tokens of this version, by its length and its chars
+	pub fn check_secret(&self, secret: &str) -> Result<(), Error> {
+		match self.secret_lengths().contains(&secret.len()) && secret.chars().all(|c| self.alphabet().contains(c)) {
+			true => Ok(()),
+			false => Err(Error::InvalidSecret { version: *self, secret: String::from(secret) }),
+		}
+	}
+}
+
 impl TryFrom<u8> for Version {
-	type Error = String;
+	type Error = VersionError;
 	fn try_from(value: u8) -> Result<Self, Self::Error> {
-		return match value {
+		match value {
 			0 => Ok(Version::V00),
 			1 => Ok(Version::V01),
-			_ => Err(format!("Version unknown: {}", value)),
-		};
+			2 => Ok(Version::V02),
+			3 => Ok(Version::V03),
+			4 => Ok(Version::V04),
+			_ => Err(VersionError::UnsupportedVersion(value)),
+		}
 	}
 }
 
-impl Into<String> for Version {
-	fn into(self) -> String {
-		format!("{:02?}", self)
+impl From<Version> for String {
+	fn from(v: Version) -> Self {
+		format!("{:02?}", v)
 	}
 }
 
@@ -71,6 +151,18 @@ impl LowerHex for Version {
 	}
 }
 
+impl Display for VersionError {
+	fn fmt(&self, fmt: &mut std::fmt::Formatter<'_>) -> std::result::Result<(), std::fmt::Error> {
+		match self {
+			VersionError::ParseError(s) => write!(fmt, "{:?} is not a valid version", s),
+			VersionError::UnsupportedVersion(v) => write!(fmt, "Version {} is not supported", v),
+			VersionError::NotAccepted(v) => write!(fmt, "V{} tokens are not accepted", v),
+		}
+	}
+}
+
+impl std::error::Error for VersionError {}
+
 impl Display for Version {
 	fn fmt(&self, fmt: &mut std::fmt::Formatter<'_>) -> std::result::Result<(), std::fmt::Error> {
 		write!(fmt, "{:02?}", *self as u32)
@@ -112,4 +204,26 @@ mod tests_rrt {
 		assert_eq!(Version::from_str("01000012345TWBABAEFGH"), Ok(Version::V00));
 		assert_eq!(Version::from_str("01010112345TWBABAEFGH"), Ok(Version::V01));
 	}
+
+	#[test]
+	fn it_checks_case_ids() {
+		assert_eq!(Ok(()), Version::V01.check_case_id(0xFFFFF));
+		assert_eq!(
+			Err(Error::CaseIdOverflow { version: Version::V01, max: 0xFFFFF, found: 1_000_000_000 }),
+			Version::V01.check_case_id(1_000_000_000)
+		);
+		assert_eq!(Ok(()), Version::V04.check_case_id(u64::MAX));
+	}
+
+	#[test]
+	fn it_checks_secrets() {
+		assert_eq!(Ok(()), Version::V01.check_secret("JXBACTSP"));
+		assert_eq!(Ok(()), Version::V03.check_secret("10B4C70P3Q"));
+		assert!(Version::V01.check_secret("JXBACTS").is_err());
+		assert!(Version::V01.check_secret("10B4C70P").is_err());
+		assert_eq!(
+			Err(Error::InvalidSecret { version: Version::V03, secret: String::from("JOBACTIP") }),
+			Version::V03.check_secret("JOBACTIP")
+		);
+	}
 }
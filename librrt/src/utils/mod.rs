@@ -1,3 +1,4 @@
 pub use utils::*;
 
+#[allow(clippy::module_inception)]
 mod utils;
@@ -1,17 +1,21 @@
-use rand::Rng;
+use crate::error::Error;
+use crate::explainer::Field;
 use std::fmt::UpperHex;
+use std::ops::Range;
 use std::str;
 
 pub fn dec2hex<T: UpperHex>(x: T, width: usize) -> String {
 	format!("{:0width$X}", x, width = width)
 }
 
-/// Generate a random string of 'length' chars
-/// The string is made of ascii chars from 65 to 90 (CAPS).
-pub fn gen_random_string(length: usize) -> String {
-	let mut rng = rand::thread_rng();
-	let chars: Vec<u8> = (0..length).map(|_| rng.gen_range(65..=90)).collect();
-	String::from(str::from_utf8(&chars).unwrap())
+/// Decodes the hex `field` found at `range` of a cleaned token string
+pub(crate) fn hex_field(s: &str, field: Field, range: Range<usize>) -> Result<u64, Error> {
+	let raw = &s[range.clone()];
+	u64::from_str_radix(raw, 16).map_err(|_| Error::InvalidField {
+		field,
+		offset: range.start,
+		found: String::from(raw),
+	})
 }
 
 /// This function removes any char that is not part of [A-Z0-9]
@@ -19,6 +23,47 @@ pub fn clean_token_string(s: &str) -> String {
 	s.chars().filter(|c| (*c >= 'A' && *c <= 'Z') || (*c >= '0' && *c <= '9')).collect()
 }
 
+/// The Crockford base32 alphabet
+const BASE32: &[u8] = b"0123456789ABCDEFGHJKMNPQRSTVWXYZ";
+
+/// Encodes bytes in Crockford base32, without padding
+pub fn to_base32(bytes: &[u8]) -> String {
+	let mut out = String::with_capacity((bytes.len() * 8).div_ceil(5));
+	let (mut buffer, mut bits) = (0u16, 0);
+	for b in bytes {
+		buffer = (buffer << 8) | u16::from(*b);
+		bits += 8;
+		while bits >= 5 {
+			bits -= 5;
+			out.push(BASE32[usize::from((buffer >> bits) & 0x1F)] as char);
+		}
+	}
+	if bits > 0 {
+		out.push(BASE32[usize::from((buffer << (5 - bits)) & 0x1F)] as char);
+	}
+	out
+}
+
+/// Decodes a string encoded with [to_base32]. It must only contain chars of the alphabet.
+pub fn from_base32(s: &str) -> Option<Vec<u8>> {
+	let mut out = Vec::with_capacity(s.len() * 5 / 8);
+	let (mut buffer, mut bits) = (0u16, 0);
+	for c in s.bytes() {
+		let value = BASE32.iter().position(|x| *x == c)? as u16;
+		buffer = ((buffer << 5) | value) & 0x0FFF;
+		bits += 5;
+		if bits >= 8 {
+			bits -= 8;
+			out.push((buffer >> bits) as u8);
+		}
+	}
+	// The trailing bits are only padding
+	match bits < 5 && buffer & ((1 << bits) - 1) == 0 {
+		true => Some(out),
+		false => None,
+	}
+}
+
 #[cfg(test)]
 mod tests {
 	use super::*;
@@ -35,8 +80,16 @@ mod tests {
 	}
 
 	#[test]
-	fn it_generate_a_8_chars_token() {
-		let token = &gen_random_string(8);
-		assert_eq!(token.len(), 8);
+	fn it_encodes_base32() {
+		assert_eq!("", to_base32(&[]));
+		assert_eq!("Z0", to_base32(&[0xF8]));
+		assert_eq!("CSQPYRK1E8", to_base32(b"foobar"));
+		for len in 0..70 {
+			let bytes: Vec<u8> = (0..len).map(|i| (i * 37) as u8).collect();
+			assert_eq!(Some(bytes.clone()), from_base32(&to_base32(&bytes)));
+		}
+		assert_eq!(103, to_base32(&[0xFF; 64]).len());
+		assert_eq!(None, from_base32("Z1"));
+		assert_eq!(None, from_base32("U0"));
 	}
 }
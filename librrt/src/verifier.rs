@@ -0,0 +1,121 @@
+//! The verifier checks that a token was remarked by the account being judged.
+//!
+//! A valid token copied and remarked by another account is otherwise
+//! indistinguishable from the legitimate one.
+
+use crate::builder::Builder;
+use crate::error::Error;
+use crate::revocation::RevocationList;
+use crate::types::AccountId;
+use crate::versions::{Token, Tokenize};
+use std::collections::HashMap;
+
+/// Gives the account associated with a case, usually the account requesting a judgement.
+/// Case ids are only unique to a registrar, so a case is given with the network and index of its registrar.
+pub trait CaseStore {
+	fn account(&self, network: u8, index: u8, case_id: u64) -> Option<AccountId>;
+}
+
+impl CaseStore for HashMap<(u8, u8, u64), AccountId> {
+	fn account(&self, network: u8, index: u8, case_id: u64) -> Option<AccountId> {
+		self.get(&(network, index, case_id)).copied()
+	}
+}
+
+pub struct Verifier<'a, S: CaseStore> {
+	store: &'a S,
+	revocations: Option<&'a RevocationList>,
+}
+
+impl<'a, S: CaseStore> Verifier<'a, S> {
+	pub fn new(store: &'a S) -> Self {
+		Self { store, revocations: None }
+	}
+
+	/// Also rejects the tokens revoked by `revocations`
+	pub fn with_revocations(mut self, revocations: &'a RevocationList) -> Self {
+		self.revocations = Some(revocations);
+		self
+	}
+
+	/// Checks the token and that it was remarked by the account associated with its case.
+	/// For the versions binding the token to the account, such as V02, the checksum
+	/// is calculated with the signer so a copied token fails even before the lookup.
+	pub fn verify(&self, token: &str, signer: &AccountId) -> Result<Token, Error> {
+		let token = Builder::build_for_account(token, signer)?;
+		if let Some(revocations) = self.revocations {
+			revocations.check(&token)?;
+		}
+		let case_id = *token.case_id();
+
+		match self.store.account(u8::from(token.network()), *token.index(), case_id) {
+			None => Err(Error::UnknownCase(case_id)),
+			Some(expected) if expected != *signer => Err(Error::WrongSigner { expected, found: *signer }),
+			Some(_) => Ok(token),
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::{Channel, Revocation, TokenV02};
+
+	const ALICE: AccountId = AccountId([1; 32]);
+	const EVE: AccountId = AccountId([6; 32]);
+
+	fn store() -> HashMap<(u8, u8, u64), AccountId> {
+		vec![((2, 1, 12345), ALICE)].into_iter().collect()
+	}
+
+	#[test]
+	fn it_verifies_the_signer() {
+		let store = store();
+		let verifier = Verifier::new(&store);
+		let token = "0001020103039TWJXBACTSPAQ";
+
+		assert!(verifier.verify(token, &ALICE).is_ok());
+		assert_eq!(Err(Error::WrongSigner { expected: ALICE, found: EVE }), verifier.verify(token, &EVE).map(|_| ()));
+	}
+
+	#[test]
+	fn it_rejects_revoked_tokens() {
+		let store = store();
+		let mut revocations = RevocationList::default();
+		let revocation = Revocation::CaseChannel { network: 2, index: 1, case_id: 12345, channel: Channel::Twitter };
+		revocations.revoke(revocation.clone(), None, 0);
+		let verifier = Verifier::new(&store).with_revocations(&revocations);
+
+		let res = verifier.verify("0001020103039TWJXBACTSPAQ", &ALICE).map(|_| ());
+		assert_eq!(Err(Error::Revoked(revocation)), res);
+	}
+
+	#[test]
+	fn it_rejects_unknown_cases() {
+		let store = HashMap::new();
+		let verifier = Verifier::new(&store);
+		let res = verifier.verify("0001020103039TWJXBACTSPAQ", &ALICE).map(|_| ());
+		assert_eq!(Err(Error::UnknownCase(12345)), res);
+	}
+
+	#[test]
+	fn it_looks_up_the_case_of_the_registrar_of_the_token() {
+		let store: HashMap<_, _> = vec![((2, 2, 12345), ALICE), ((0, 1, 12345), ALICE)].into_iter().collect();
+		let verifier = Verifier::new(&store);
+		let res = verifier.verify("0001020103039TWJXBACTSPAQ", &ALICE).map(|_| ());
+		assert_eq!(Err(Error::UnknownCase(12345)), res);
+
+		let store: HashMap<_, _> = vec![((2, 1, 12345), ALICE), ((2, 2, 12345), EVE)].into_iter().collect();
+		assert!(Verifier::new(&store).verify("0001020103039TWJXBACTSPAQ", &ALICE).is_ok());
+	}
+
+	#[test]
+	fn it_rejects_copied_v02_tokens() {
+		let store = store();
+		let verifier = Verifier::new(&store);
+		let token = TokenV02::new(0, 2, 1, 12345, Channel::Twitter, &ALICE).to_string();
+
+		assert!(verifier.verify(&token, &ALICE).is_ok());
+		assert!(matches!(verifier.verify(&token, &EVE), Err(Error::ChecksumError(_))));
+	}
+}
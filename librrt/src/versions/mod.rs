@@ -1,7 +1,13 @@
 pub use rrtoken::*;
 pub use token_v00::*;
 pub use token_v01::*;
+pub use token_v02::*;
+pub use token_v03::*;
+pub use token_v04::*;
 
 mod rrtoken;
 mod token_v00;
 mod token_v01;
+mod token_v02;
+mod token_v03;
+mod token_v04;
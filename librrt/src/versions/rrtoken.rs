@@ -1,21 +1,116 @@
-use crate::types::Version;
+use crate::error::Error;
+use crate::types::{AccountId, Version};
 use crate::utils::dec2hex;
 use crate::versions::*;
 use crate::Channel;
 use crate::Network;
 use enum_dispatch::enum_dispatch;
+use rand::rngs::OsRng;
+use rand::{CryptoRng, RngCore};
 use std::fmt::Display;
 
+/// The scheme of the RRT URIs such as `rrt:0001020103039TWJXBACTSPAQ`
+pub const URI_SCHEME: &str = "rrt";
+
 #[enum_dispatch]
 #[derive(Debug)]
 pub enum Token {
 	V00(TokenV00),
 	V01(TokenV01),
+	V02(TokenV02),
+	V03(TokenV03),
+	V04(TokenV04),
 }
 
 impl Display for Token {
 	fn fmt(&self, fmt: &mut std::fmt::Formatter<'_>) -> std::result::Result<(), std::fmt::Error> {
-		write!(fmt, "{}", self)
+		write!(fmt, "{}", self.format_string(""))
+	}
+}
+
+impl Token {
+	/// Generates a token of `version` with a random secret of the default length of that version,
+	/// using the CSPRNG of the OS. See [Token::new_with_secret] for the errors.
+	pub fn new(
+		version: Version,
+		app: u8,
+		network: u8,
+		index: u8,
+		case_id: u64,
+		channel: Channel,
+		account: Option<&AccountId>,
+	) -> Result<Token, Error> {
+		Self::new_with_rng(version, app, network, index, case_id, channel, account, &mut OsRng)
+	}
+
+	/// Like `new` but draws the secret from the given RNG, which allows deterministic tests
+	#[allow(clippy::too_many_arguments)]
+	pub fn new_with_rng<R: RngCore + CryptoRng>(
+		version: Version,
+		app: u8,
+		network: u8,
+		index: u8,
+		case_id: u64,
+		channel: Channel,
+		account: Option<&AccountId>,
+		rng: &mut R,
+	) -> Result<Token, Error> {
+		let secret = version.alphabet().random_with(rng, version.default_secret_length());
+		Self::new_with_secret(version, app, network, index, case_id, channel, &secret, account)
+	}
+
+	/// Builds a token of `version` with the given secret. Fails with [Error::CaseIdOverflow] or
+	/// [Error::InvalidSecret] when they do not fit in that version, and with [Error::AccountRequired]
+	/// for the versions bound to an account, such as V02, when no account is given.
+	#[allow(clippy::too_many_arguments)]
+	pub fn new_with_secret(
+		version: Version,
+		app: u8,
+		network: u8,
+		index: u8,
+		case_id: u64,
+		channel: Channel,
+		secret: &str,
+		account: Option<&AccountId>,
+	) -> Result<Token, Error> {
+		Ok(match version {
+			Version::V00 => Token::V00(TokenV00::try_new_with_token(
+				app,
+				version,
+				Network::from(network),
+				index,
+				case_id,
+				channel,
+				secret,
+			)?),
+			Version::V01 => {
+				Token::V01(TokenV01::try_new_with_secret(app, version, network, index, case_id, channel, secret)?)
+			}
+			Version::V02 => {
+				let account = account.ok_or(Error::AccountRequired)?;
+				Token::V02(TokenV02::try_new_with_secret(app, network, index, case_id, channel, secret, account)?)
+			}
+			Version::V03 => Token::V03(TokenV03::try_new_with_secret(app, network, index, case_id, channel, secret)?),
+			Version::V04 => Token::V04(TokenV04::try_new_with_secret(app, network, index, case_id, channel, secret)?),
+		})
+	}
+
+	/// Re-issues the token in another version, keeping its app, network, index, case id, channel and secret.
+	/// Fails if the case id or the secret do not fit in the tokens of that version.
+	/// V02 tokens need [Token::convert_to_for_account].
+	pub fn convert_to(&self, version: Version) -> Result<Token, Error> {
+		self.convert(version, None)
+	}
+
+	/// Like `convert_to` but also converts to the versions bound to an account, such as V02
+	pub fn convert_to_for_account(&self, version: Version, account: &AccountId) -> Result<Token, Error> {
+		self.convert(version, Some(account))
+	}
+
+	fn convert(&self, version: Version, account: Option<&AccountId>) -> Result<Token, Error> {
+		let network = u8::from(self.network());
+		let (app, index, case_id, channel) = (*self.app(), *self.index(), *self.case_id(), *self.channel());
+		Self::new_with_secret(version, app, network, index, case_id, channel, self.secret(), account)
 	}
 }
 
@@ -28,7 +123,6 @@ pub trait Tokenize: std::fmt::Debug + std::fmt::Display {
 	/// Returns true if both the version and the length of the candidate string
 	/// match the implementation.
 	// fn is_candidate(&self, s: &str) -> bool;
-
 	fn app(&self) -> &u8;
 	fn version(&self) -> &Version;
 
@@ -53,7 +147,7 @@ pub trait Tokenize: std::fmt::Debug + std::fmt::Display {
 		format!(
 			// 01 00 02 01 02B21 TW 12345678 75
 			"{APP}{S}{VV}{S}{NET}{S}{RG}{S}{CASE}{S}{CH}{S}{_SECRET_}{S}{C}",
-			APP = dec2hex(*self.app() as u8, 2),
+			APP = dec2hex(*self.app(), 2),
 			VV = dec2hex(*self.version() as u8, 2),
 			RG = dec2hex(*self.index(), 2),
 			NET = dec2hex(Into::<u8>::into(self.network()), 2),
@@ -64,6 +158,18 @@ pub trait Tokenize: std::fmt::Debug + std::fmt::Display {
 			C = self.checksum(),
 		)
 	}
+
+	/// Returns the token as an URI such as `rrt:0001020103039TWJXBACTSPAQ`.
+	/// See [Builder::build_from_uri](crate::Builder::build_from_uri) for the reverse operation.
+	fn to_uri(&self) -> String {
+		format!("{}:{}", URI_SCHEME, self.format_string(""))
+	}
+
+	/// Returns the token in its envelope such as `rrt1-ksm-0001020103039twjxbactspaq-...`.
+	/// See [Builder::build_from_envelope](crate::Builder::build_from_envelope) for the reverse operation.
+	fn to_envelope(&self) -> String {
+		crate::envelope::wrap(self.network(), &self.format_string(""))
+	}
 }
 
 #[macro_export]
@@ -74,3 +180,78 @@ macro_rules! gen_getter {
 		}
 	};
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::Builder;
+	use rand::rngs::StdRng;
+	use rand::SeedableRng;
+
+	#[test]
+	fn it_builds_tokens_of_any_version() {
+		for version in Version::ALL {
+			let token = Token::new(version, 0, 2, 1, 12345, Channel::Email, Some(&AccountId([1; 32]))).unwrap();
+			assert_eq!(version, *token.version());
+			assert_eq!(version.default_secret_length(), token.secret().len());
+			assert!(Builder::build_for_account(&token.to_string(), &AccountId([1; 32])).is_ok());
+		}
+
+		let overflow = Token::new(Version::V01, 0, 2, 1, 0x100000, Channel::Email, None).map(|_| ());
+		assert_eq!(Err(Error::CaseIdOverflow { version: Version::V01, max: 0xFFFFF, found: 0x100000 }), overflow);
+		assert!(Token::new(Version::V04, 0, 2, 1, 0x100000, Channel::Email, None).is_ok());
+		assert_eq!(Err(Error::AccountRequired), Token::new(Version::V02, 0, 2, 1, 7, Channel::Email, None).map(|_| ()));
+
+		let secret = Token::new_with_secret(Version::V01, 0, 2, 1, 7, Channel::Email, "0123ABCD", None).map(|_| ());
+		assert!(matches!(secret, Err(Error::InvalidSecret { .. })));
+	}
+
+	#[test]
+	fn it_uses_the_injected_rng() {
+		let account = AccountId([1; 32]);
+		for version in Version::ALL {
+			let token = |seed| {
+				let rng = &mut StdRng::seed_from_u64(seed);
+				Token::new_with_rng(version, 0, 2, 1, 12345, Channel::Email, Some(&account), rng).unwrap().to_string()
+			};
+			assert_eq!(token(42), token(42));
+			assert_ne!(token(42), token(43));
+		}
+	}
+
+	#[test]
+	fn it_converts_between_versions() {
+		let v00 = Builder::build_with_variant("0000000012345TWRAJQFIZWW").unwrap();
+		let v01 = v00.convert_to(Version::V01).unwrap();
+		assert_eq!("0001000012345TWRAJQFIZW", &v01.to_string()[..23]);
+		assert!(Builder::build_with_variant(&v01.to_string()).is_ok());
+
+		let v04 = v01.convert_to(Version::V04).unwrap();
+		assert_eq!((v01.case_id(), v01.secret()), (v04.case_id(), v04.secret()));
+		assert_eq!(v01.to_string(), v04.convert_to(Version::V01).unwrap().to_string());
+		assert_eq!(v00.to_string(), v04.convert_to(Version::V00).unwrap().to_string());
+	}
+
+	#[test]
+	fn it_errors_when_a_field_does_not_fit() {
+		let large = Token::V04(TokenV04::new_with_secret(0, 2, 1, 1 << 40, Channel::Twitter, "JXBACTSP"));
+		assert!(matches!(large.convert_to(Version::V01), Err(Error::CaseIdOverflow { .. })));
+
+		// I is not part of the Crockford alphabet of V03, nor are the 12 chars of a V03 secret supported by V01
+		let v01 = Token::V01(TokenV01::new_with_secret(0, Version::V01, 2, 1, 12345, Channel::Twitter, "JXBICTSP"));
+		assert!(matches!(v01.convert_to(Version::V03), Err(Error::InvalidSecret { .. })));
+		let v03 = Token::V03(TokenV03::new(0, 2, 1, 12345, Channel::Twitter, 12));
+		assert!(matches!(v03.convert_to(Version::V01), Err(Error::InvalidSecret { .. })));
+	}
+
+	#[test]
+	fn it_needs_the_account_for_v02() {
+		let account = AccountId([7; 32]);
+		let v01 = Builder::build_with_variant("0001020103039TWJXBACTSPAQ").unwrap();
+		assert_eq!(Err(Error::AccountRequired), v01.convert_to(Version::V02).map(|_| ()));
+
+		let v02 = v01.convert_to_for_account(Version::V02, &account).unwrap();
+		assert!(Builder::build_for_account(&v02.to_string(), &account).is_ok());
+		assert_eq!(v01.to_string(), v02.convert_to(Version::V01).unwrap().to_string());
+	}
+}
@@ -1,6 +1,8 @@
 use crate::error::Error;
 use crate::utils::*;
 use crate::*;
+use rand::rngs::OsRng;
+use rand::{CryptoRng, RngCore};
 use std::convert::From;
 use std::fmt::Display;
 use std::str;
@@ -76,13 +78,43 @@ impl Tokenize for TokenV00 {
 }
 
 impl TokenV00 {
-	/// Generate a new token and return a new RRT
+	/// Generate a new token and return a new RRT, using the CSPRNG of the OS
 	pub fn new(app: u8, version: Version, network: Network, index: u8, case_id: u64, channel: Channel) -> Self {
-		let token = &gen_random_string(8);
-		Self::new_with_token(app, version, network, index, case_id, channel, token)
+		Self::new_with_rng(app, version, network, index, case_id, channel, &mut OsRng)
 	}
 
-	/// Unlike ::new(...), here you must pass the token
+	/// Like ::new(...) but draws the secret from the given RNG
+	pub fn new_with_rng<R: RngCore + CryptoRng>(
+		app: u8,
+		version: Version,
+		network: Network,
+		index: u8,
+		case_id: u64,
+		channel: Channel,
+		rng: &mut R,
+	) -> Self {
+		let secret = Version::V00.alphabet().random_with(rng, 8);
+		Self::new_with_token(app, version, network, index, case_id, channel, &secret)
+	}
+
+	/// Like `new_with_token` but fails with [Error::CaseIdOverflow] or [Error::InvalidSecret]
+	/// when the case id or the secret do not fit in a V00 token, rather than panicking
+	pub fn try_new_with_token(
+		app: u8,
+		version: Version,
+		network: Network,
+		index: u8,
+		case_id: u64,
+		channel: Channel,
+		secret: &str,
+	) -> Result<Self, Error> {
+		Version::V00.check_case_id(case_id)?;
+		Version::V00.check_secret(secret)?;
+		Ok(Self::new_with_token(app, version, network, index, case_id, channel, secret))
+	}
+
+	/// Unlike ::new(...), here you must pass the token.
+	/// Panics if the case id or the secret do not fit, see `try_new_with_token`.
 	pub fn new_with_token(
 		app: u8,
 		version: Version,
@@ -93,6 +125,7 @@ impl TokenV00 {
 		secret: &str,
 	) -> Self {
 		assert!(secret.len() == 8, "The passed secret does not have the right length");
+		assert!(case_id <= 0xFFFFF, "The case id does not fit in 5 hex chars, see Version::check_case_id");
 
 		let algo = ChecksumV00::new();
 		let raw = TokenV00::format_raw(app, version, network, index, case_id, channel, secret);
@@ -112,7 +145,7 @@ impl TokenV00 {
 	) -> String {
 		format!(
 			"{APP}{VV}{RG}{NET}{CASE}{CH}{_SECRET_}",
-			APP = dec2hex(app as u8, 2),
+			APP = dec2hex(app, 2),
 			VV = dec2hex(version as u8, 2),
 			RG = dec2hex(index, 2),
 			NET = dec2hex(Into::<u8>::into(network), 2),
@@ -160,18 +193,16 @@ impl FromStr for TokenV00 {
 	fn from_str(s: &str) -> Result<Self, Self::Err> {
 		let s = clean_token_string(s);
 		if s.len() < TOKEN_V00_SIZE {
-			return Err(Error::LengthError(TOKEN_V00_SIZE, s.len()));
+			return Err(Error::LengthError { expected: TOKEN_V00_SIZE, found: s.len() });
 		}
 
 		// 01_02_01_00_12345_TW_BABAEFGH_K (V00)
 		// 0  2  4  6  8     13 15       24
-		// TODO: handle errors better
-		let app = u8::from_str_radix(&s[0..2], 16).unwrap_or_else(|_| panic!("Bad token {}, length={}", s, s.len()));
+		let app = hex_field(&s, Field::App, 0..2)? as u8;
 		let version = Version::from_str(&s[2..4])?;
-		let network = Network::from(&s[4..6]);
-		let index = u8::from_str_radix(&s[6..8], 16).unwrap_or_else(|_| panic!("Bad token {}, length={}", s, s.len()));
-		let case_id =
-			u64::from_str_radix(&s[8..13], 16).unwrap_or_else(|_| panic!("Bad token {}, length={}", s, s.len()));
+		let network = Network::from(hex_field(&s, Field::Network, 4..6)? as u8);
+		let index = hex_field(&s, Field::Index, 6..8)? as u8;
+		let case_id = hex_field(&s, Field::CaseId, 8..13)?;
 		let channel = Channel::from(&s[13..15]);
 		let secret = String::from(&s[15..23]);
 		let checksum_str = s.chars().nth_back(0).unwrap_or_else(|| panic!("Bad token {}, length={}", s, s.len())) as u8;
@@ -307,9 +338,15 @@ mod tests_rrt {
 	#[test]
 	fn it_print_a_rrt_in_various_ways() {
 		let rrt = TokenV00::new(APP, VERSION, CHAIN, 1, 12345, Channel::Twitter);
-		println!("{}", rrt);
-		println!("{}", rrt.format_string("_"));
-		println!("{:?}", rrt);
-		println!("{:#?}", rrt);
+		let compact = rrt.to_string();
+		assert_eq!(compact, rrt.format_string(""));
+		assert_eq!(TOKEN_V00_SIZE, compact.len());
+
+		let separated = rrt.format_string("_");
+		assert_eq!(8, separated.split('_').count());
+		assert_eq!(compact, separated.replace('_', ""));
+
+		assert!(format!("{:?}", rrt).contains("case_id: 12345"));
+		assert!(format!("{:#?}", rrt).contains("channel: Twitter"));
 	}
 }
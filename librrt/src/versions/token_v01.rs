@@ -3,6 +3,8 @@ use crate::error::Error;
 use crate::utils::*;
 use crate::versions::rrtoken::Tokenize;
 use crate::*;
+use rand::rngs::OsRng;
+use rand::{CryptoRng, RngCore};
 use std::convert::From;
 use std::fmt::{Debug, Display};
 use std::str;
@@ -61,18 +63,17 @@ impl FromStr for TokenV01 {
 	fn from_str(s: &str) -> Result<Self, Self::Err> {
 		let s = clean_token_string(s);
 		if s.len() < TOKEN_V01_SIZE {
-			return Err(Error::LengthError(TOKEN_V01_SIZE, s.len()));
+			return Err(Error::LengthError { expected: TOKEN_V01_SIZE, found: s.len() });
 		}
 
 		// 01_02_01_00_12345_TW_BABAEFGH_KX (V01)
 		// 0  2  4  6  8     13 15       24
-		// TODO: handle errors better
-		let app = u8::from_str_radix(&s[0..2], 16).unwrap();
+		let app = hex_field(&s, Field::App, 0..2)? as u8;
 		let version = Version::from_str(&s[2..4])?;
-		let network = u8::from_str_radix(&s[4..6], 16).unwrap();
+		let network = hex_field(&s, Field::Network, 4..6)? as u8;
 		// let network = Network::from(network_index);
-		let index = u8::from_str_radix(&s[6..8], 16).unwrap();
-		let case_id = u64::from_str_radix(&s[8..13], 16).unwrap();
+		let index = hex_field(&s, Field::Index, 6..8)? as u8;
+		let case_id = hex_field(&s, Field::CaseId, 8..13)?;
 		let channel = Channel::from(&s[13..15]);
 		let secret = String::from(&s[15..23]);
 		let checksum_str = Self::extract_checksum(&s)?;
@@ -119,13 +120,26 @@ impl Tokenize for TokenV01 {
 }
 
 impl TokenV01 {
-	/// Generate a new token and return a new RRT
+	/// Generate a new token and return a new RRT, using the CSPRNG of the OS
 	pub fn new(app: u8, version: Version, network: u8, index: u8, case_id: u64, channel: Channel) -> Self {
-		let token = &gen_random_string(8);
-		Self::new_with_secret(app, version, network, index, case_id, channel, &String::from(token))
+		Self::new_with_rng(app, version, network, index, case_id, channel, &mut OsRng)
 	}
 
-	fn format_raw(
+	/// Like ::new(...) but draws the secret from the given RNG
+	pub fn new_with_rng<R: RngCore + CryptoRng>(
+		app: u8,
+		version: Version,
+		network: u8,
+		index: u8,
+		case_id: u64,
+		channel: Channel,
+		rng: &mut R,
+	) -> Self {
+		let secret = Version::V01.alphabet().random_with(rng, 8);
+		Self::new_with_secret(app, version, network, index, case_id, channel, &secret)
+	}
+
+	pub(crate) fn format_raw(
 		app: u8,
 		version: Version,
 		network: u8,
@@ -136,17 +150,34 @@ impl TokenV01 {
 	) -> String {
 		format!(
 			"{APP}{VV}{NET}{RG}{CASE}{CH}{_SECRET_}",
-			APP = dec2hex(app as u8, 2),
+			APP = dec2hex(app, 2),
 			VV = dec2hex(version as u8, 2),
 			RG = dec2hex(index, 2),
-			NET = dec2hex(network as u8, 2),
+			NET = dec2hex(network, 2),
 			CASE = dec2hex(case_id, 5),
 			CH = &channel.to_string(),
 			_SECRET_ = secret,
 		)
 	}
 
-	/// Unlike ::new(...), here you must pass the secret
+	/// Like `new_with_secret` but fails with [Error::CaseIdOverflow] or [Error::InvalidSecret]
+	/// when the case id or the secret do not fit in a V01 token, rather than panicking
+	pub fn try_new_with_secret(
+		app: u8,
+		version: Version,
+		network: u8,
+		index: u8,
+		case_id: u64,
+		channel: Channel,
+		secret: &str,
+	) -> Result<Self, Error> {
+		Version::V01.check_case_id(case_id)?;
+		Version::V01.check_secret(secret)?;
+		Ok(Self::new_with_secret(app, version, network, index, case_id, channel, secret))
+	}
+
+	/// Unlike ::new(...), here you must pass the secret.
+	/// Panics if the case id or the secret do not fit, see `try_new_with_secret`.
 	pub fn new_with_secret(
 		app: u8,
 		version: Version,
@@ -157,6 +188,7 @@ impl TokenV01 {
 		secret: &str,
 	) -> Self {
 		assert!(secret.len() == 8, "The passed secret does not have the right length");
+		assert!(case_id <= 0xFFFFF, "The case id does not fit in 5 hex chars, see Version::check_case_id");
 
 		let algo = ChecksumV01::new();
 		let raw = TokenV01::format_raw(app, version, network, index, case_id, channel, secret);
@@ -165,10 +197,10 @@ impl TokenV01 {
 		Self { app, version, network, index, case_id, channel, secret: String::from(secret), checksum }
 	}
 
-	fn extract_checksum(s: &str) -> Result<[u8; 2], Error> {
+	pub(crate) fn extract_checksum(s: &str) -> Result<[u8; 2], Error> {
 		// TODO: can do better...
 		if s.len() < TOKEN_V01_SIZE {
-			return Err(Error::LengthError(25, s.len()));
+			return Err(Error::LengthError { expected: 25, found: s.len() });
 		}
 		let a = s.as_bytes()[TOKEN_V01_SIZE - 2];
 		let b = s.as_bytes()[TOKEN_V01_SIZE - 1];
@@ -326,10 +358,16 @@ mod tests_rrt {
 	#[test]
 	fn it_print_a_rrt_in_various_ways() {
 		let rrt = TokenV01::new(APP, VERSION, CHAIN, 1, 12345, Channel::Twitter);
-		println!("{}", rrt);
-		println!("{}", rrt.format_string("_"));
-		println!("{:?}", rrt);
-		println!("{:#?}", rrt);
+		let compact = rrt.to_string();
+		assert_eq!(compact, rrt.format_string(""));
+		assert_eq!(TOKEN_V01_SIZE, compact.len());
+
+		let separated = rrt.format_string("_");
+		assert_eq!(8, separated.split('_').count());
+		assert_eq!(compact, separated.replace('_', ""));
+
+		assert!(format!("{:?}", rrt).contains("case_id: 12345"));
+		assert!(format!("{:#?}", rrt).contains("channel: Twitter"));
 	}
 
 	/// Works for strings with separators
@@ -361,7 +399,7 @@ mod tests_rrt {
 	fn it_generates_a_token_with_valid_checksum() {
 		let s = "0001020103039TWJXBACTSPAQ";
 		let token = TokenV01::from_str(s).expect("Invalid token");
-		let checksum_str = TokenV01::extract_checksum(&s).unwrap();
+		let checksum_str = TokenV01::extract_checksum(s).unwrap();
 		assert_eq!([65, 81], checksum_str);
 		assert_eq!(String::from_utf8_lossy(&[65, 81]), token.checksum());
 	}
@@ -370,7 +408,7 @@ mod tests_rrt {
 	fn it_generates_a_token_with_valid_checksum2() {
 		let s = "00010001004D2TWBQTDFFLZZB";
 		let token = TokenV01::from_str(s).expect("Invalid token");
-		let checksum_str = TokenV01::extract_checksum(&s).unwrap();
+		let checksum_str = TokenV01::extract_checksum(s).unwrap();
 		assert_eq!([90, 66], checksum_str);
 		assert_eq!(String::from_utf8_lossy(&[90, 66]), token.checksum());
 	}
@@ -379,6 +417,6 @@ mod tests_rrt {
 	fn it_generates_token_for_unknown_networks() {
 		let s = "0001030103039TWJXBACTSPSX";
 		let token = TokenV01::from_str(s).expect("Invalid token");
-		assert_eq!(03u8, (token.network()).into());
+		assert_eq!(3u8, u8::from(token.network()));
 	}
 }
@@ -0,0 +1,212 @@
+use crate::checksum::*;
+use crate::error::Error;
+use crate::utils::*;
+use crate::versions::rrtoken::Tokenize;
+use crate::*;
+use rand::rngs::OsRng;
+use rand::{CryptoRng, RngCore};
+use std::fmt::{Debug, Display};
+use std::str;
+use std::str::FromStr;
+
+const TOKEN_V02_SIZE: usize = 25;
+
+/// A V02 token has the same layout as a [TokenV01]:
+/// 00-02-02-01-03039-TW-JXBACTSP-XX
+///
+/// Its checksum however also covers the public key of the account the token
+/// is issued for. The account is not part of the token, so checking a V02 token
+/// requires knowing the account that remarked it.
+///
+/// The binding is a 2 letters checksum: about 676 tries are enough to grind an
+/// account for which a copied token passes, so it does not prove the signer.
+/// ```
+/// use librrt::*;
+/// let account = AccountId([0; 32]);
+/// let token = TokenV02::new(0, 0, 1, 12345, Channel::Email, &account);
+/// assert!(TokenV02::from_str_with_account(&token.to_string(), &account).is_ok());
+/// ```
+#[derive(Debug)]
+pub struct TokenV02 {
+	/// A numerical index representing the App
+	app: u8,
+
+	/// RRT Token version, always V02
+	version: Version,
+
+	/// Network
+	network: u8,
+
+	/// Registrar index 0..255
+	index: u8,
+
+	/// The case_id of our process
+	case_id: u64,
+
+	/// The channel
+	channel: Channel,
+
+	/// The random token
+	secret: String,
+
+	checksum: [u8; 2],
+}
+
+impl Display for TokenV02 {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::result::Result<(), std::fmt::Error> {
+		write!(f, "{}", self.format_string(""))
+	}
+}
+
+impl Tokenize for TokenV02 {
+	fn size_of(&self) -> usize {
+		TOKEN_V02_SIZE
+	}
+
+	gen_getter!(app, &u8);
+	gen_getter!(version, &Version);
+	gen_getter!(channel, &Channel);
+	gen_getter!(index, &u8);
+	gen_getter!(case_id, &u64);
+	gen_getter!(secret, &String);
+
+	fn network(&self) -> Network {
+		Network::from(self.network)
+	}
+
+	fn checksum(&self) -> String {
+		String::from_utf8_lossy(&self.checksum).into_owned()
+	}
+}
+
+impl TokenV02 {
+	/// Generate a new token for the given account, using the CSPRNG of the OS
+	pub fn new(app: u8, network: u8, index: u8, case_id: u64, channel: Channel, account: &AccountId) -> Self {
+		Self::new_with_rng(app, network, index, case_id, channel, account, &mut OsRng)
+	}
+
+	/// Like ::new(...) but draws the secret from the given RNG
+	pub fn new_with_rng<R: RngCore + CryptoRng>(
+		app: u8,
+		network: u8,
+		index: u8,
+		case_id: u64,
+		channel: Channel,
+		account: &AccountId,
+		rng: &mut R,
+	) -> Self {
+		let secret = Version::V02.alphabet().random_with(rng, 8);
+		Self::new_with_secret(app, network, index, case_id, channel, &secret, account)
+	}
+
+	/// Like `new_with_secret` but fails with [Error::CaseIdOverflow] or [Error::InvalidSecret]
+	/// when the case id or the secret do not fit in a V02 token, rather than panicking
+	pub fn try_new_with_secret(
+		app: u8,
+		network: u8,
+		index: u8,
+		case_id: u64,
+		channel: Channel,
+		secret: &str,
+		account: &AccountId,
+	) -> Result<Self, Error> {
+		Version::V02.check_case_id(case_id)?;
+		Version::V02.check_secret(secret)?;
+		Ok(Self::new_with_secret(app, network, index, case_id, channel, secret, account))
+	}
+
+	/// Unlike ::new(...), here you must pass the secret.
+	/// Panics if the case id or the secret do not fit, see `try_new_with_secret`.
+	pub fn new_with_secret(
+		app: u8,
+		network: u8,
+		index: u8,
+		case_id: u64,
+		channel: Channel,
+		secret: &str,
+		account: &AccountId,
+	) -> Self {
+		assert!(secret.len() == 8, "The passed secret does not have the right length");
+		assert!(case_id <= 0xFFFFF, "The case id does not fit in 5 hex chars, see Version::check_case_id");
+
+		let version = Version::V02;
+		let raw = TokenV01::format_raw(app, version, network, index, case_id, channel, secret);
+		let checksum = Self::calculate(&raw, account);
+		Self { app, version, network, index, case_id, channel, secret: String::from(secret), checksum }
+	}
+
+	/// The Fletcher 16 checksum of the V01, calculated over the token and the account public key
+	pub fn calculate(raw: &str, account: &AccountId) -> [u8; 2] {
+		let data: Vec<u8> = raw.as_bytes().iter().chain(account.as_bytes()).copied().collect();
+		ChecksumV01::new().calculate(&data)
+	}
+
+	/// Parses a token and checks that it was issued for the given account
+	pub fn from_str_with_account(s: &str, account: &AccountId) -> Result<Self, Error> {
+		let s = clean_token_string(s);
+		if s.len() != TOKEN_V02_SIZE {
+			return Err(Error::LengthError { expected: TOKEN_V02_SIZE, found: s.len() });
+		}
+
+		let app = hex_field(&s, Field::App, 0..2)? as u8;
+		let version = Version::from_str(&s[2..4])?;
+		if version != Version::V02 {
+			return Err(Error::InvalidEncoding(s));
+		}
+		let network = hex_field(&s, Field::Network, 4..6)? as u8;
+		let index = hex_field(&s, Field::Index, 6..8)? as u8;
+		let case_id = hex_field(&s, Field::CaseId, 8..13)?;
+		let channel = Channel::from(&s[13..15]);
+		let secret = String::from(&s[15..23]);
+		let found = TokenV01::extract_checksum(&s)?;
+
+		let checksum = Self::calculate(&s[..23], account);
+		match checksum == found {
+			true => Ok(Self { app, version, network, index, case_id, channel, secret, checksum }),
+			false => Err(Error::ChecksumError(ChecksumError::new(
+				s,
+				ChecksumOutput::Dual(checksum),
+				ChecksumOutput::Dual(found),
+			))),
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests_rrt {
+	use super::*;
+
+	const ALICE: AccountId = AccountId([1; 32]);
+	const BOB: AccountId = AccountId([2; 32]);
+
+	#[test]
+	fn it_makes_a_rrt() {
+		let token = TokenV02::new(0, 2, 1, 12345, Channel::Twitter, &ALICE);
+		assert_eq!(TOKEN_V02_SIZE, token.to_string().len());
+		assert_eq!("00020201", &token.to_string()[..8]);
+	}
+
+	#[test]
+	fn it_binds_the_token_to_the_account() {
+		let token = TokenV02::new_with_secret(0, 2, 1, 12345, Channel::Twitter, "JXBACTSP", &ALICE);
+		let s = token.to_string();
+
+		let parsed = TokenV02::from_str_with_account(&s, &ALICE).unwrap();
+		assert_eq!(12345, *parsed.case_id());
+		assert_eq!(token.checksum(), parsed.checksum());
+		assert!(matches!(TokenV02::from_str_with_account(&s, &BOB), Err(Error::ChecksumError(_))));
+	}
+
+	#[test]
+	fn it_differs_from_v01() {
+		let v01 = TokenV01::new_with_secret(0, Version::V02, 2, 1, 12345, Channel::Twitter, "JXBACTSP");
+		let v02 = TokenV02::new_with_secret(0, 2, 1, 12345, Channel::Twitter, "JXBACTSP", &ALICE);
+		assert_ne!(v01.checksum(), v02.checksum());
+	}
+
+	#[test]
+	fn it_rejects_other_versions() {
+		assert!(TokenV02::from_str_with_account("0001020103039TWJXBACTSPAQ", &ALICE).is_err());
+		assert!(TokenV02::from_str_with_account("0002020103039TWJXBACTSP", &ALICE).is_err());
+	}
+}
@@ -0,0 +1,256 @@
+use crate::checksum::*;
+use crate::error::Error;
+use crate::utils::*;
+use crate::versions::rrtoken::Tokenize;
+use crate::*;
+use rand::rngs::OsRng;
+use rand::{CryptoRng, RngCore};
+use std::fmt::{Debug, Display};
+use std::ops::RangeInclusive;
+use std::str;
+use std::str::FromStr;
+
+/// The lengths supported for the secret of a V03 token
+pub const V03_SECRET_LENGTHS: RangeInclusive<usize> = 8..=15;
+
+/// The length of the secret when none is given, about 60 bits of entropy
+pub const V03_DEFAULT_SECRET_LENGTH: usize = 12;
+
+/// The offset of the secret length in the token string
+const LENGTH_OFFSET: usize = 15;
+
+/// A V03 token has a longer secret drawn from the [Crockford](Alphabet::Crockford) alphabet.
+/// The char following the channel gives the length of the secret, in hex, from 8 to F:
+/// 00-03-02-01-03039-TW-C-7M3K9T2XQ4HB-XX
+///
+/// The checksum is calculated as for a [TokenV01].
+/// ```
+/// use librrt::*;
+/// let token = TokenV03::new(0, 2, 1, 12345, Channel::Email, 12);
+/// assert_eq!(30, token.to_string().len());
+/// ```
+#[derive(Debug)]
+pub struct TokenV03 {
+	/// A numerical index representing the App
+	app: u8,
+
+	/// RRT Token version, always V03
+	version: Version,
+
+	/// Network
+	network: u8,
+
+	/// Registrar index 0..255
+	index: u8,
+
+	/// The case_id of our process
+	case_id: u64,
+
+	/// The channel
+	channel: Channel,
+
+	/// The random token
+	secret: String,
+
+	checksum: [u8; 2],
+}
+
+impl Display for TokenV03 {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::result::Result<(), std::fmt::Error> {
+		write!(f, "{}", self.format_string(""))
+	}
+}
+
+impl Tokenize for TokenV03 {
+	fn size_of(&self) -> usize {
+		Self::size_for(self.secret.len())
+	}
+
+	gen_getter!(app, &u8);
+	gen_getter!(version, &Version);
+	gen_getter!(channel, &Channel);
+	gen_getter!(index, &u8);
+	gen_getter!(case_id, &u64);
+	gen_getter!(secret, &String);
+
+	fn network(&self) -> Network {
+		Network::from(self.network)
+	}
+
+	fn checksum(&self) -> String {
+		String::from_utf8_lossy(&self.checksum).into_owned()
+	}
+
+	/// Like the other versions, with the length of the secret before the secret
+	fn format_string(&self, sep: &str) -> String {
+		format!(
+			"{APP}{S}{VV}{S}{NET}{S}{RG}{S}{CASE}{S}{CH}{S}{L}{S}{_SECRET_}{S}{C}",
+			APP = dec2hex(self.app, 2),
+			VV = dec2hex(self.version as u8, 2),
+			NET = dec2hex(self.network, 2),
+			RG = dec2hex(self.index, 2),
+			CASE = dec2hex(self.case_id, 5),
+			CH = &self.channel.to_string(),
+			L = dec2hex(self.secret.len(), 1),
+			_SECRET_ = self.secret,
+			S = sep,
+			C = self.checksum(),
+		)
+	}
+}
+
+impl TokenV03 {
+	/// Generate a new token whose secret has `length` chars, using the CSPRNG of the OS
+	pub fn new(app: u8, network: u8, index: u8, case_id: u64, channel: Channel, length: usize) -> Self {
+		Self::new_with_rng(app, network, index, case_id, channel, length, &mut OsRng)
+	}
+
+	/// Like ::new(...) but draws the secret from the given RNG
+	pub fn new_with_rng<R: RngCore + CryptoRng>(
+		app: u8,
+		network: u8,
+		index: u8,
+		case_id: u64,
+		channel: Channel,
+		length: usize,
+		rng: &mut R,
+	) -> Self {
+		let secret = Alphabet::Crockford.random_with(rng, length);
+		Self::new_with_secret(app, network, index, case_id, channel, &secret)
+	}
+
+	/// Like `new_with_secret` but fails with [Error::CaseIdOverflow] or [Error::InvalidSecret]
+	/// when the case id or the secret do not fit in a V03 token, rather than panicking
+	pub fn try_new_with_secret(
+		app: u8,
+		network: u8,
+		index: u8,
+		case_id: u64,
+		channel: Channel,
+		secret: &str,
+	) -> Result<Self, Error> {
+		Version::V03.check_case_id(case_id)?;
+		Version::V03.check_secret(secret)?;
+		Ok(Self::new_with_secret(app, network, index, case_id, channel, secret))
+	}
+
+	/// Unlike ::new(...), here you must pass the secret.
+	/// Panics if the case id or the secret do not fit, see `try_new_with_secret`.
+	pub fn new_with_secret(app: u8, network: u8, index: u8, case_id: u64, channel: Channel, secret: &str) -> Self {
+		assert!(V03_SECRET_LENGTHS.contains(&secret.len()), "The passed secret does not have a supported length");
+		assert!(case_id <= 0xFFFFF, "The case id does not fit in 5 hex chars, see Version::check_case_id");
+
+		let version = Version::V03;
+		let raw = Self::format_raw(app, network, index, case_id, channel, secret);
+		let checksum = ChecksumV01::new().calculate(raw.as_bytes());
+		Self { app, version, network, index, case_id, channel, secret: String::from(secret), checksum }
+	}
+
+	/// The size of a token whose secret has `length` chars
+	pub fn size_for(length: usize) -> usize {
+		LENGTH_OFFSET + 1 + length + 2
+	}
+
+	/// Returns the length of the secret given in a cleaned token string, if it is supported
+	pub fn secret_length(s: &str) -> Option<usize> {
+		let length = usize::from_str_radix(s.get(LENGTH_OFFSET..LENGTH_OFFSET + 1)?, 16).ok()?;
+		Some(length).filter(|l| V03_SECRET_LENGTHS.contains(l))
+	}
+
+	fn format_raw(app: u8, network: u8, index: u8, case_id: u64, channel: Channel, secret: &str) -> String {
+		format!(
+			"{APP}{VV}{NET}{RG}{CASE}{CH}{L}{_SECRET_}",
+			APP = dec2hex(app, 2),
+			VV = dec2hex(Version::V03 as u8, 2),
+			NET = dec2hex(network, 2),
+			RG = dec2hex(index, 2),
+			CASE = dec2hex(case_id, 5),
+			CH = &channel.to_string(),
+			L = dec2hex(secret.len(), 1),
+			_SECRET_ = secret,
+		)
+	}
+}
+
+impl FromStr for TokenV03 {
+	type Err = Error;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		let s = clean_token_string(s);
+		let length = match Self::secret_length(&s) {
+			Some(length) => length,
+			None if s.len() <= LENGTH_OFFSET => {
+				let expected = Self::size_for(V03_DEFAULT_SECRET_LENGTH);
+				return Err(Error::LengthError { expected, found: s.len() });
+			}
+			None => return Err(Error::InvalidEncoding(s)),
+		};
+		let size = Self::size_for(length);
+		if s.len() != size {
+			return Err(Error::LengthError { expected: size, found: s.len() });
+		}
+
+		let app = hex_field(&s, Field::App, 0..2)? as u8;
+		if Version::from_str(&s[2..4])? != Version::V03 {
+			return Err(Error::InvalidEncoding(s));
+		}
+		let network = hex_field(&s, Field::Network, 4..6)? as u8;
+		let index = hex_field(&s, Field::Index, 6..8)? as u8;
+		let case_id = hex_field(&s, Field::CaseId, 8..13)?;
+		let channel = Channel::from(&s[13..15]);
+		let secret = &s[LENGTH_OFFSET + 1..size - 2];
+
+		let token = Self::new_with_secret(app, network, index, case_id, channel, secret);
+		let found = [s.as_bytes()[size - 2], s.as_bytes()[size - 1]];
+		match token.checksum == found {
+			true => Ok(token),
+			false => Err(Error::ChecksumError(ChecksumError::new(
+				s,
+				ChecksumOutput::Dual(token.checksum),
+				ChecksumOutput::Dual(found),
+			))),
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests_rrt {
+	use super::*;
+	use rand::rngs::StdRng;
+	use rand::SeedableRng;
+
+	#[test]
+	fn it_makes_tokens_of_any_supported_length() {
+		for length in V03_SECRET_LENGTHS {
+			let token = TokenV03::new(0, 2, 1, 12345, Channel::Twitter, length);
+			let s = token.to_string();
+			assert_eq!(TokenV03::size_for(length), s.len());
+			assert!(token.secret().chars().all(|c| Alphabet::Crockford.contains(c)));
+
+			let parsed = TokenV03::from_str(&s).unwrap();
+			assert_eq!(token.secret(), parsed.secret());
+			assert_eq!(12345, *parsed.case_id());
+		}
+	}
+
+	#[test]
+	fn it_uses_the_injected_rng() {
+		let a = TokenV03::new_with_rng(0, 2, 1, 12345, Channel::Email, 12, &mut StdRng::seed_from_u64(42));
+		let b = TokenV03::new_with_rng(0, 2, 1, 12345, Channel::Email, 12, &mut StdRng::seed_from_u64(42));
+		assert_eq!(a.to_string(), b.to_string());
+		assert_eq!("00-03-02-01-03039-EM-C", &a.format_string("-")[..22]);
+	}
+
+	#[test]
+	fn it_rejects_bad_tokens() {
+		let s = TokenV03::new_with_secret(0, 2, 1, 12345, Channel::Twitter, "7M3K9T2XQ4HB").to_string();
+		assert!(TokenV03::from_str(&s).is_ok());
+		assert_eq!(Err(Error::LengthError { expected: 30, found: 29 }), TokenV03::from_str(&s[..29]).map(|_| ()));
+		assert!(matches!(TokenV03::from_str(&s.replace("7M3", "7M4")), Err(Error::ChecksumError(_))));
+
+		// The length of the secret must be 8 to F
+		let short = format!("{}7{}", &s[..15], &s[16..]);
+		assert!(matches!(TokenV03::from_str(&short), Err(Error::InvalidEncoding(_))));
+		assert!(TokenV03::from_str("0001020103039TWJXBACTSPAQ").is_err());
+	}
+}
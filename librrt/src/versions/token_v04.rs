@@ -0,0 +1,246 @@
+use crate::checksum::*;
+use crate::error::Error;
+use crate::utils::*;
+use crate::versions::rrtoken::Tokenize;
+use crate::*;
+use rand::rngs::OsRng;
+use rand::{CryptoRng, RngCore};
+use std::fmt::{Debug, Display};
+use std::str;
+use std::str::FromStr;
+
+/// The offset of the length of the case id in the token string
+const LENGTH_OFFSET: usize = 8;
+
+/// A V04 token is a [TokenV01] whose case id may use up to 16 hex chars, so any `u64` fits.
+/// The case id is written without leading zeros and is preceded by its number of chars
+/// minus one, as a hex digit:
+/// 00-04-02-01-4-F4240-TW-JXBACTSP-XX
+///
+/// ```
+/// use librrt::*;
+/// let token = TokenV04::new(0, 2, 1, u64::MAX, Channel::Email);
+/// assert_eq!(37, token.to_string().len());
+/// ```
+#[derive(Debug)]
+pub struct TokenV04 {
+	/// A numerical index representing the App
+	app: u8,
+
+	/// RRT Token version, always V04
+	version: Version,
+
+	/// Network
+	network: u8,
+
+	/// Registrar index 0..255
+	index: u8,
+
+	/// The case_id of our process
+	case_id: u64,
+
+	/// The channel
+	channel: Channel,
+
+	/// The random token
+	secret: String,
+
+	checksum: [u8; 2],
+}
+
+impl Display for TokenV04 {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::result::Result<(), std::fmt::Error> {
+		write!(f, "{}", self.format_string(""))
+	}
+}
+
+impl Tokenize for TokenV04 {
+	fn size_of(&self) -> usize {
+		Self::size_for(Self::case_id_digits(self.case_id))
+	}
+
+	gen_getter!(app, &u8);
+	gen_getter!(version, &Version);
+	gen_getter!(channel, &Channel);
+	gen_getter!(index, &u8);
+	gen_getter!(case_id, &u64);
+	gen_getter!(secret, &String);
+
+	fn network(&self) -> Network {
+		Network::from(self.network)
+	}
+
+	fn checksum(&self) -> String {
+		String::from_utf8_lossy(&self.checksum).into_owned()
+	}
+
+	/// Like the other versions, with the length of the case id before the case id
+	fn format_string(&self, sep: &str) -> String {
+		let len = Self::case_id_digits(self.case_id);
+		format!(
+			"{APP}{S}{VV}{S}{NET}{S}{RG}{S}{L}{S}{CASE}{S}{CH}{S}{_SECRET_}{S}{C}",
+			APP = dec2hex(self.app, 2),
+			VV = dec2hex(self.version as u8, 2),
+			NET = dec2hex(self.network, 2),
+			RG = dec2hex(self.index, 2),
+			L = dec2hex(len - 1, 1),
+			CASE = dec2hex(self.case_id, len),
+			CH = &self.channel.to_string(),
+			_SECRET_ = self.secret,
+			S = sep,
+			C = self.checksum(),
+		)
+	}
+}
+
+impl TokenV04 {
+	/// Generate a new token and return a new RRT, using the CSPRNG of the OS
+	pub fn new(app: u8, network: u8, index: u8, case_id: u64, channel: Channel) -> Self {
+		Self::new_with_rng(app, network, index, case_id, channel, &mut OsRng)
+	}
+
+	/// Like ::new(...) but draws the secret from the given RNG
+	pub fn new_with_rng<R: RngCore + CryptoRng>(
+		app: u8,
+		network: u8,
+		index: u8,
+		case_id: u64,
+		channel: Channel,
+		rng: &mut R,
+	) -> Self {
+		let secret = Version::V04.alphabet().random_with(rng, 8);
+		Self::new_with_secret(app, network, index, case_id, channel, &secret)
+	}
+
+	/// Like `new_with_secret` but fails with [Error::InvalidSecret] when the secret
+	/// does not fit in a V04 token, rather than panicking. Any case id fits.
+	pub fn try_new_with_secret(
+		app: u8,
+		network: u8,
+		index: u8,
+		case_id: u64,
+		channel: Channel,
+		secret: &str,
+	) -> Result<Self, Error> {
+		Version::V04.check_secret(secret)?;
+		Ok(Self::new_with_secret(app, network, index, case_id, channel, secret))
+	}
+
+	/// Unlike ::new(...), here you must pass the secret.
+	/// Panics if the secret does not fit, see `try_new_with_secret`.
+	pub fn new_with_secret(app: u8, network: u8, index: u8, case_id: u64, channel: Channel, secret: &str) -> Self {
+		assert!(secret.len() == 8, "The passed secret does not have the right length");
+
+		let mut token = Self {
+			app,
+			version: Version::V04,
+			network,
+			index,
+			case_id,
+			channel,
+			secret: String::from(secret),
+			checksum: [0; 2],
+		};
+		let raw = token.format_string("");
+		token.checksum = ChecksumV01::new().calculate(&raw.as_bytes()[..raw.len() - 2]);
+		token
+	}
+
+	/// The number of hex digits of a case id, without leading zeros
+	pub(crate) fn case_id_digits(case_id: u64) -> usize {
+		(64 - case_id.leading_zeros() as usize).div_ceil(4).max(1)
+	}
+
+	/// The size of a token whose case id has `length` chars
+	pub fn size_for(length: usize) -> usize {
+		LENGTH_OFFSET + 1 + length + 2 + 8 + 2
+	}
+
+	/// Reads the number of chars of the case id from the length field of a cleaned token string
+	pub fn parse_case_id_length(s: &str) -> Option<usize> {
+		usize::from_str_radix(s.get(LENGTH_OFFSET..LENGTH_OFFSET + 1)?, 16).ok().map(|l| l + 1)
+	}
+}
+
+impl FromStr for TokenV04 {
+	type Err = Error;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		let s = clean_token_string(s);
+		let length = match Self::parse_case_id_length(&s) {
+			Some(length) => length,
+			None if s.len() <= LENGTH_OFFSET => {
+				return Err(Error::LengthError { expected: Self::size_for(5), found: s.len() })
+			}
+			None => return Err(Error::InvalidEncoding(s)),
+		};
+		let size = Self::size_for(length);
+		if s.len() != size {
+			return Err(Error::LengthError { expected: size, found: s.len() });
+		}
+
+		let app = hex_field(&s, Field::App, 0..2)? as u8;
+		if Version::from_str(&s[2..4])? != Version::V04 {
+			return Err(Error::InvalidEncoding(s));
+		}
+		let network = hex_field(&s, Field::Network, 4..6)? as u8;
+		let index = hex_field(&s, Field::Index, 6..8)? as u8;
+		let case = LENGTH_OFFSET + 1;
+		let case_id = hex_field(&s, Field::CaseId, case..case + length)?;
+		let channel = Channel::from(&s[case + length..case + length + 2]);
+		let secret = &s[size - 10..size - 2];
+
+		// The case id must be written without leading zeros, there is a single valid string per token
+		if Self::case_id_digits(case_id) != length {
+			return Err(Error::InvalidEncoding(s));
+		}
+
+		let token = Self::new_with_secret(app, network, index, case_id, channel, secret);
+		let found = [s.as_bytes()[size - 2], s.as_bytes()[size - 1]];
+		match token.checksum == found {
+			true => Ok(token),
+			false => Err(Error::ChecksumError(ChecksumError::new(
+				s,
+				ChecksumOutput::Dual(token.checksum),
+				ChecksumOutput::Dual(found),
+			))),
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests_rrt {
+	use super::*;
+
+	#[test]
+	fn it_encodes_any_case_id() {
+		for case_id in [0, 1, 0xF, 0x10, 12345, 0xFFFFF, 0x100000, u32::MAX as u64, u64::MAX] {
+			let token = TokenV04::new(0, 2, 1, case_id, Channel::Twitter);
+			let s = token.to_string();
+			assert_eq!(token.size_of(), s.len());
+
+			let parsed = TokenV04::from_str(&s).unwrap();
+			assert_eq!(case_id, *parsed.case_id());
+			assert_eq!(token.secret(), parsed.secret());
+		}
+	}
+
+	#[test]
+	fn it_formats_the_case_id_length() {
+		let token = TokenV04::new_with_secret(0, 2, 1, 1_000_000, Channel::Twitter, "JXBACTSP");
+		assert_eq!("00-04-02-01-4-F4240-TW-JXBACTSP-", &token.format_string("-")[..32]);
+		assert_eq!(22, TokenV04::new(0, 2, 1, 0, Channel::Email).size_of());
+	}
+
+	#[test]
+	fn it_rejects_bad_tokens() {
+		let s = TokenV04::new_with_secret(0, 2, 1, 1_000_000, Channel::Twitter, "JXBACTSP").to_string();
+		assert_eq!(Err(Error::LengthError { expected: 26, found: 25 }), TokenV04::from_str(&s[..25]).map(|_| ()));
+		assert!(matches!(TokenV04::from_str(&s.replace("JXB", "JXC")), Err(Error::ChecksumError(_))));
+
+		// Leading zeros are not allowed
+		let padded = format!("{}50F4240{}", &s[..8], &s[14..]);
+		assert!(matches!(TokenV04::from_str(&padded), Err(Error::InvalidEncoding(_))));
+		assert!(TokenV04::from_str("0001020103039TWJXBACTSPAQ").is_err());
+	}
+}
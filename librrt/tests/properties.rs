@@ -0,0 +1,182 @@
+//! Properties every token must have, whatever its version and its fields.
+
+use librrt::*;
+use proptest::prelude::*;
+
+fn channel() -> impl Strategy<Value = Channel> {
+	prop_oneof![Just(Channel::Email), Just(Channel::Matrix), Just(Channel::Twitter)]
+}
+
+fn secret(alphabet: Alphabet, lengths: std::ops::RangeInclusive<usize>) -> impl Strategy<Value = String> {
+	let chars: Vec<char> = alphabet.chars().chars().collect();
+	prop::collection::vec(prop::sample::select(chars), lengths).prop_map(|s| s.into_iter().collect())
+}
+
+/// The case ids V00 to V03 can hold
+fn short_case_id() -> impl Strategy<Value = u64> {
+	0..=0xFFFFFu64
+}
+
+/// Any valid token of any version, and the account it was issued for
+fn token() -> impl Strategy<Value = (Token, AccountId)> {
+	let account = any::<[u8; 32]>().prop_map(AccountId);
+	let v00 = (any::<u8>(), any::<u8>(), any::<u8>(), short_case_id(), channel(), secret(Alphabet::Full, 8..=8))
+		.prop_map(|(app, net, idx, case_id, ch, secret)| {
+			Token::V00(TokenV00::new_with_token(app, Version::V00, Network::from(net), idx, case_id, ch, &secret))
+		});
+	let v01 = (any::<u8>(), any::<u8>(), any::<u8>(), short_case_id(), channel(), secret(Alphabet::Full, 8..=8))
+		.prop_map(|(app, net, idx, case_id, ch, secret)| {
+			Token::V01(TokenV01::new_with_secret(app, Version::V01, net, idx, case_id, ch, &secret))
+		});
+	let v03 = (any::<u8>(), any::<u8>(), any::<u8>(), short_case_id(), channel(), secret(Alphabet::Crockford, 8..=15))
+		.prop_map(|(app, net, idx, case_id, ch, secret)| {
+			Token::V03(TokenV03::new_with_secret(app, net, idx, case_id, ch, &secret))
+		});
+	let v04 = (any::<u8>(), any::<u8>(), any::<u8>(), any::<u64>(), channel(), secret(Alphabet::Full, 8..=8)).prop_map(
+		|(app, net, idx, case_id, ch, secret)| {
+			Token::V04(TokenV04::new_with_secret(app, net, idx, case_id, ch, &secret))
+		},
+	);
+	let unbound = prop_oneof![v00, v01, v03, v04];
+	let unbound = (unbound, account.clone());
+	let v02 =
+		(any::<u8>(), any::<u8>(), any::<u8>(), short_case_id(), channel(), secret(Alphabet::Full, 8..=8), account)
+			.prop_map(|(app, net, idx, case_id, ch, secret, account)| {
+				(Token::V02(TokenV02::new_with_secret(app, net, idx, case_id, ch, &secret, &account)), account)
+			});
+	prop_oneof![4 => unbound, 1 => v02]
+}
+
+fn fields(token: &Token) -> (u8, Version, u8, u8, u64, Channel, String, String) {
+	(
+		*token.app(),
+		*token.version(),
+		u8::from(token.network()),
+		*token.index(),
+		*token.case_id(),
+		*token.channel(),
+		token.secret().to_string(),
+		token.checksum(),
+	)
+}
+
+proptest! {
+	#[test]
+	fn it_parses_what_it_formats((token, account) in token()) {
+		let parsed = Builder::build_for_account(&token.to_string(), &account).unwrap();
+		prop_assert_eq!(token.to_string(), parsed.to_string());
+		prop_assert_eq!(fields(&token), fields(&parsed));
+	}
+
+	#[test]
+	fn it_ignores_separators((token, account) in token(), sep in prop::sample::select(vec!["-", "_", " ", ":", "/", " - "])) {
+		let formatted = token.format_string(sep);
+		prop_assert_eq!(clean_token_string(&formatted), token.to_string());
+		let parsed = Builder::build_for_account(&formatted, &account).unwrap();
+		prop_assert_eq!(token.to_string(), parsed.to_string());
+	}
+
+	#[test]
+	fn it_round_trips_envelopes_and_bytes((token, account) in token()) {
+		let unwrapped = Builder::build_from_envelope(&token.to_envelope());
+		match token.version() {
+			Version::V02 => {
+				let decoded = Token::from_bytes_for_account(&token.to_bytes().unwrap(), &account).unwrap();
+				prop_assert_eq!(token.to_string(), decoded.to_string());
+			}
+			_ => {
+				prop_assert_eq!(token.to_string(), unwrapped.unwrap().to_string());
+				prop_assert_eq!(token.to_string(), Token::from_bytes(&token.to_bytes().unwrap()).unwrap().to_string());
+			}
+		}
+	}
+
+	/// Any single char of a V01 token may be mistyped:
+	/// - a confusable typed in the secret, such as 0 for O, is mapped back to the original token
+	/// - other mistakes in the secret are caught by the checksum but for the few that collide, see
+	///   `it_detects_most_v01_substitutions`. Those are then read as typed, not as another token.
+	/// - a mistake in the header or in the checksum is always caught
+	#[test]
+	fn it_catches_single_char_mutations_of_v01_tokens(
+		secret in secret(Alphabet::Full, 8..=8),
+		case_id in short_case_id(),
+		pos in 0..25usize,
+		c in prop::sample::select("0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZ".chars().collect::<Vec<_>>()),
+	) {
+		let token = TokenV01::new_with_secret(0, Version::V01, 2, 1, case_id, Channel::Twitter, &secret).to_string();
+		prop_assume!(!token[pos..].starts_with(c));
+		let mutated = format!("{}{}{}", &token[..pos], c, &token[pos + 1..]);
+		let in_secret = (15..23).contains(&pos);
+		let confusable = in_secret && Alphabet::Full.normalize(&c.to_string()) == token[pos..=pos];
+
+		match Builder::build_with_variant(&mutated) {
+			Ok(parsed) if confusable => prop_assert_eq!(token, parsed.to_string()),
+			Ok(parsed) => {
+				prop_assert!(in_secret, "{} was accepted as {}", mutated, parsed);
+				prop_assert_eq!(mutated, parsed.to_string());
+			}
+			Err(e) => {
+				prop_assert!(!confusable, "{} was not mapped back: {:?}", mutated, e);
+				prop_assert!(pos < 23 || matches!(e, Error::ChecksumError(_)), "{:?}", e);
+			}
+		}
+	}
+
+	#[test]
+	fn it_never_panics_on_garbage(s in "\\PC{0,40}") {
+		let _ = clean_token_string(&s);
+		let _ = Detector::analyze(&s);
+		let _ = Builder::build_with_variant(&s);
+		let _ = Builder::build_any(&s);
+		let _ = Explainer::explain(&s);
+	}
+
+	#[test]
+	fn it_never_panics_on_near_tokens(
+		(token, _) in token(),
+		pos in 0..40usize,
+		c in prop_oneof![3 => prop::char::range(' ', '~'), 1 => any::<char>()],
+	) {
+		let mut chars: Vec<char> = token.to_string().chars().collect();
+		let pos = pos.min(chars.len() - 1);
+		chars[pos] = c;
+		let s: String = chars.into_iter().collect();
+		let _ = Detector::analyze(&s);
+		let _ = Builder::build_with_variant(&s);
+		let _ = Builder::build_any(&s);
+		let _ = Explainer::explain(&s);
+	}
+}
+
+/// The checksum must catch at least 99% of the substitutions of a single letter of the secret,
+/// over a sample that is the same on every run
+#[test]
+fn it_detects_most_v01_substitutions() {
+	let alphabet: Vec<char> = Alphabet::Full.chars().chars().collect();
+	let mut seed = 0x2545_F491_4F6C_DD1Du64;
+	let mut next = |n: usize| {
+		// xorshift64
+		seed ^= seed << 13;
+		seed ^= seed >> 7;
+		seed ^= seed << 17;
+		(seed % n as u64) as usize
+	};
+
+	let (mut tries, mut detected) = (0, 0);
+	for _ in 0..500 {
+		let secret: String = (0..8).map(|_| alphabet[next(alphabet.len())]).collect();
+		let case_id = next(0x100000) as u64;
+		let token = TokenV01::new_with_secret(0, Version::V01, 2, 1, case_id, Channel::Twitter, &secret).to_string();
+		for pos in 15..23 {
+			for c in alphabet.iter().filter(|c| !token[pos..].starts_with(**c)) {
+				let mutated = format!("{}{}{}", &token[..pos], c, &token[pos + 1..]);
+				tries += 1;
+				if Builder::build_with_variant(&mutated).is_err() {
+					detected += 1;
+				}
+			}
+		}
+	}
+	let rate = f64::from(detected) / f64::from(tries);
+	assert!(rate >= 0.99, "only {} of {} substitutions were detected ({:.4})", detected, tries, rate);
+}
@@ -0,0 +1,143 @@
+use crate::email::message::Message;
+use crate::error::Error;
+use rand::Rng;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// A mailbox in the Maildir format: messages are files in the `tmp`, `new` and `cur` folders
+#[derive(Debug, Clone)]
+pub struct Maildir {
+	path: PathBuf,
+}
+
+impl Maildir {
+	/// Opens the maildir, creating its folders if needed
+	pub fn open(path: &Path) -> Result<Self, Error> {
+		for dir in &["tmp", "new", "cur"] {
+			fs::create_dir_all(path.join(dir))?;
+		}
+		Ok(Self { path: path.to_path_buf() })
+	}
+
+	/// Delivers a message to `new`, through `tmp` so readers never see a partial message
+	pub fn deliver(&self, message: &Message) -> Result<PathBuf, Error> {
+		let now = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_micros()).unwrap_or_default();
+		let name = format!("{}.{}_{:08x}.rrt", now, std::process::id(), rand::thread_rng().gen::<u32>());
+
+		let tmp = self.path.join("tmp").join(&name);
+		let new = self.path.join("new").join(&name);
+		fs::write(&tmp, message.to_string())?;
+		fs::rename(&tmp, &new)?;
+		Ok(new)
+	}
+
+	/// Returns the new messages and moves them to `cur` so they are only returned once.
+	///
+	/// A message that cannot be parsed is moved to `cur` flagged instead of seen, for someone to look at it,
+	/// and does not prevent the others from being returned. A message that cannot be read or moved is
+	/// left in `new` and returned by a later call.
+	pub fn fetch_new(&self) -> Result<Vec<Message>, Error> {
+		let mut paths: Vec<PathBuf> =
+			fs::read_dir(self.path.join("new"))?.filter_map(|e| e.ok().map(|e| e.path())).collect();
+		paths.sort();
+
+		let mut messages = Vec::new();
+		for path in paths {
+			let (raw, name) = match (fs::read(&path), path.file_name()) {
+				(Ok(raw), Some(name)) => (raw, name),
+				_ => continue,
+			};
+			let message = Message::parse(&String::from_utf8_lossy(&raw));
+			let mut cur = self.path.join("cur").join(name);
+			cur.as_mut_os_string().push(if message.is_ok() { ":2,S" } else { ":2,F" });
+			if let (Ok(()), Ok(message)) = (fs::rename(&path, cur), message) {
+				messages.push(message);
+			}
+		}
+		Ok(messages)
+	}
+}
+
+/// Splits an mbox file into its messages. `>From ` escaping (mboxrd) is reverted.
+pub fn parse_mbox(content: &str) -> Result<Vec<Message>, Error> {
+	let mut raws: Vec<Vec<&str>> = Vec::new();
+	for line in content.lines() {
+		if line.starts_with("From ") {
+			raws.push(Vec::new());
+		} else if let Some(raw) = raws.last_mut() {
+			raw.push(line);
+		} else if !line.trim().is_empty() {
+			return Err(Error::InvalidMessage(String::from("An mbox must start with a \"From \" line")));
+		}
+	}
+
+	raws.iter()
+		.map(|lines| {
+			let unescaped: Vec<&str> = lines
+				.iter()
+				.map(|l| match l.strip_prefix('>') {
+					Some(rest) if rest.trim_start_matches('>').starts_with("From ") => rest,
+					_ => l,
+				})
+				.collect();
+			Message::parse(&unescaped.join("\n"))
+		})
+		.collect()
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn it_delivers_and_fetches_messages() {
+		let path = std::env::temp_dir().join(format!("rrt-maildir-{}", std::process::id()));
+		let maildir = Maildir::open(&path).unwrap();
+
+		let message =
+			Message::new("registrar@example.org", "alice@example.com", "Token", "0001020103039TWJXBACTSPAQ\n");
+		maildir.deliver(&message).unwrap();
+
+		let fetched = maildir.fetch_new().unwrap();
+		assert_eq!(vec![message], fetched);
+		assert!(maildir.fetch_new().unwrap().is_empty());
+		assert_eq!(1, fs::read_dir(path.join("cur")).unwrap().count());
+		fs::remove_dir_all(path).unwrap();
+	}
+
+	#[test]
+	fn it_sets_invalid_messages_aside() {
+		let path = std::env::temp_dir().join(format!("rrt-maildir-invalid-{}", std::process::id()));
+		let maildir = Maildir::open(&path).unwrap();
+
+		let message =
+			Message::new("registrar@example.org", "alice@example.com", "Token", "0001020103039TWJXBACTSPAQ\n");
+		// Named to be sorted between the two valid messages
+		let first = maildir.deliver(&message).unwrap();
+		let invalid = format!("{}.invalid", first.file_name().unwrap().to_str().unwrap());
+		fs::write(path.join("new").join(&invalid), "not a header\n\nbody").unwrap();
+		maildir.deliver(&message).unwrap();
+
+		assert_eq!(vec![message.clone(), message], maildir.fetch_new().unwrap());
+		assert!(maildir.fetch_new().unwrap().is_empty());
+		assert!(path.join("cur").join(format!("{}:2,F", invalid)).exists());
+		assert_eq!(3, fs::read_dir(path.join("cur")).unwrap().count());
+		fs::remove_dir_all(path).unwrap();
+	}
+
+	#[test]
+	fn it_parses_mbox_files() {
+		let mbox = "From alice@example.com Thu Jan  1 00:00:00 1970\n\
+			From: alice@example.com\nSubject: Re: Token\n\nHere it is: 0001020103039TWJXBACTSPAQ\n>From the registrar\n\n\
+			From bob@example.com Thu Jan  1 00:00:00 1970\nFrom: bob@example.com\n\nNo token\n";
+		let messages = parse_mbox(mbox).unwrap();
+		assert_eq!(2, messages.len());
+		assert_eq!(Some("alice@example.com"), messages[0].header("from"));
+		assert!(messages[0].body.contains("\nFrom the registrar"));
+		assert_eq!("No token", messages[1].body.trim());
+
+		assert!(parse_mbox("not an mbox").is_err());
+		assert!(parse_mbox("").unwrap().is_empty());
+	}
+}
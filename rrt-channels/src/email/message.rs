@@ -0,0 +1,298 @@
+use crate::error::Error;
+use crate::fill;
+use librrt::Token;
+use rand::Rng;
+use std::fmt::Display;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const DAYS: [&str; 7] = ["Thu", "Fri", "Sat", "Sun", "Mon", "Tue", "Wed"];
+const MONTHS: [&str; 12] = ["Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec"];
+
+/// The subject and body of the emails sent with a token. See [fill] for the placeholders.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Template {
+	pub subject: String,
+	pub body: String,
+}
+
+impl Default for Template {
+	fn default() -> Self {
+		Self {
+			subject: String::from("Your identity verification token"),
+			body: String::from(
+				"Hello,\n\n\
+				To verify this email address, please reply to this message with the following token:\n\n    \
+				{token}\n\n\
+				The same token must also be remarked on {network} from your identity account.\n",
+			),
+		}
+	}
+}
+
+impl Template {
+	/// Returns the subject and the body for `token`
+	pub fn render(&self, token: &Token) -> (String, String) {
+		(fill(&self.subject, token), fill(&self.body, token))
+	}
+}
+
+/// Formats a unix timestamp as an RFC 5322 date, in UTC
+fn rfc5322_date(secs: u64) -> String {
+	let days = secs / 86400;
+	let (h, m, s) = (secs % 86400 / 3600, secs % 3600 / 60, secs % 60);
+
+	// Civil from days, see http://howardhinnant.github.io/date_algorithms.html
+	let z = days as i64 + 719_468;
+	let era = z.div_euclid(146_097);
+	let doe = z - era * 146_097;
+	let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146_096) / 365;
+	let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+	let mp = (5 * doy + 2) / 153;
+	let day = doy - (153 * mp + 2) / 5 + 1;
+	let month = if mp < 10 { mp + 3 } else { mp - 9 };
+	let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+
+	format!(
+		"{}, {:02} {} {} {:02}:{:02}:{:02} +0000",
+		DAYS[(days % 7) as usize],
+		day,
+		MONTHS[(month - 1) as usize],
+		year,
+		h,
+		m,
+		s
+	)
+}
+
+/// Header names and values, in order
+pub type Headers = Vec<(String, String)>;
+
+/// An email: its headers, in order, and its text body
+#[derive(Debug, PartialEq, Clone)]
+pub struct Message {
+	pub headers: Headers,
+	pub body: String,
+}
+
+impl Message {
+	/// Creates a plain text message, dated now
+	pub fn new(from: &str, to: &str, subject: &str, body: &str) -> Self {
+		let now = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or_default();
+		let domain = from.rsplit('@').next().unwrap_or("localhost").trim_end_matches('>');
+		let id: u64 = rand::thread_rng().gen();
+
+		let headers = vec![
+			("From", from.to_string()),
+			("To", to.to_string()),
+			("Subject", subject.to_string()),
+			("Date", rfc5322_date(now)),
+			("Message-ID", format!("<{}.{:016x}@{}>", now, id, domain)),
+			("MIME-Version", String::from("1.0")),
+			("Content-Type", String::from("text/plain; charset=utf-8")),
+			("Content-Transfer-Encoding", String::from("8bit")),
+		];
+		let headers = headers.into_iter().map(|(k, v)| (k.to_string(), v)).collect();
+		Self { headers, body: body.to_string() }
+	}
+
+	/// Returns the first header with this name, ignoring the case
+	pub fn header(&self, name: &str) -> Option<&str> {
+		self.headers.iter().find(|(k, _)| k.eq_ignore_ascii_case(name)).map(|(_, v)| v.as_str())
+	}
+
+	/// Parses a raw message. The body is decoded: for multipart messages, it is made of the text parts.
+	pub fn parse(raw: &str) -> Result<Self, Error> {
+		let (headers, body) = split_headers(raw)?;
+		let body = decode_body(&headers, body)?.replace("\r\n", "\n");
+		Ok(Self { headers, body })
+	}
+}
+
+impl Display for Message {
+	/// Renders the message in the RFC 5322 format, with CRLF line endings
+	fn fmt(&self, fmt: &mut std::fmt::Formatter<'_>) -> std::result::Result<(), std::fmt::Error> {
+		for (k, v) in &self.headers {
+			write!(fmt, "{}: {}\r\n", k, v)?;
+		}
+		write!(fmt, "\r\n")?;
+		for line in self.body.lines() {
+			write!(fmt, "{}\r\n", line)?;
+		}
+		Ok(())
+	}
+}
+
+/// Splits the headers from the body and unfolds the headers
+fn split_headers(raw: &str) -> Result<(Headers, &str), Error> {
+	let (head, body) = match (raw.find("\r\n\r\n"), raw.find("\n\n")) {
+		(Some(crlf), Some(lf)) if lf < crlf => (&raw[..lf], &raw[lf + 2..]),
+		(Some(crlf), _) => (&raw[..crlf], &raw[crlf + 4..]),
+		(None, Some(lf)) => (&raw[..lf], &raw[lf + 2..]),
+		(None, None) => (raw, ""),
+	};
+
+	let mut headers: Headers = Vec::new();
+	for line in head.lines() {
+		if line.starts_with(' ') || line.starts_with('\t') {
+			match headers.last_mut() {
+				Some((_, v)) => v.push_str(&format!(" {}", line.trim())),
+				None => return Err(Error::InvalidMessage(format!("Continuation line without header: {:?}", line))),
+			}
+			continue;
+		}
+		match line.split_once(':') {
+			Some((k, v)) => headers.push((k.trim().to_string(), v.trim().to_string())),
+			None => return Err(Error::InvalidMessage(format!("Invalid header line: {:?}", line))),
+		}
+	}
+	Ok((headers, body))
+}
+
+fn header<'a>(headers: &'a [(String, String)], name: &str) -> Option<&'a str> {
+	headers.iter().find(|(k, _)| k.eq_ignore_ascii_case(name)).map(|(_, v)| v.as_str())
+}
+
+/// Returns a parameter of a header such as the boundary of a Content-Type
+fn param(value: &str, name: &str) -> Option<String> {
+	value.split(';').skip(1).find_map(|p| {
+		let (k, v) = p.split_once('=')?;
+		match k.trim().eq_ignore_ascii_case(name) {
+			true => Some(v.trim().trim_matches('"').to_string()),
+			false => None,
+		}
+	})
+}
+
+fn decode_body(headers: &[(String, String)], body: &str) -> Result<String, Error> {
+	let content_type = header(headers, "Content-Type").unwrap_or("text/plain");
+	let mime = content_type.split(';').next().unwrap_or_default().trim().to_ascii_lowercase();
+
+	if mime.starts_with("multipart/") {
+		let boundary = param(content_type, "boundary")
+			.ok_or_else(|| Error::InvalidMessage(String::from("Multipart message without boundary")))?;
+		let delimiter = format!("--{}", boundary);
+		let mut texts = Vec::new();
+		// The first chunk is the preamble, the one after the closing delimiter is the epilogue
+		for part in body.split(&delimiter).skip(1).filter(|p| !p.starts_with("--")) {
+			let part = part.trim_start_matches(['\r', '\n']);
+			let (part_headers, part_body) = split_headers(part)?;
+			let part_type = header(&part_headers, "Content-Type").unwrap_or("text/plain").to_ascii_lowercase();
+			if part_type.starts_with("text/") || part_type.starts_with("multipart/") {
+				texts.push(decode_body(&part_headers, part_body)?);
+			}
+		}
+		return Ok(texts.join("\n"));
+	}
+
+	match header(headers, "Content-Transfer-Encoding").map(|e| e.to_ascii_lowercase()).as_deref() {
+		Some("quoted-printable") => Ok(decode_quoted_printable(body)),
+		Some("base64") => decode_base64(body),
+		_ => Ok(body.to_string()),
+	}
+}
+
+/// The value of an ASCII hex digit, in either case
+fn hex_digit(c: u8) -> Option<u8> {
+	(c as char).to_digit(16).map(|d| d as u8)
+}
+
+fn decode_quoted_printable(s: &str) -> String {
+	let bytes = s.as_bytes();
+	let mut out = Vec::with_capacity(bytes.len());
+	let mut i = 0;
+	while i < bytes.len() {
+		match bytes[i] {
+			b'=' if bytes[i + 1..].starts_with(b"\r\n") => i += 3,
+			b'=' if bytes[i + 1..].starts_with(b"\n") => i += 2,
+			b'=' if i + 2 < bytes.len() => match (hex_digit(bytes[i + 1]), hex_digit(bytes[i + 2])) {
+				(Some(high), Some(low)) => {
+					out.push(high << 4 | low);
+					i += 3;
+				}
+				_ => {
+					out.push(b'=');
+					i += 1;
+				}
+			},
+			b => {
+				out.push(b);
+				i += 1;
+			}
+		}
+	}
+	String::from_utf8_lossy(&out).into_owned()
+}
+
+fn decode_base64(s: &str) -> Result<String, Error> {
+	let value = |c: u8| match c {
+		b'A'..=b'Z' => Some(c - b'A'),
+		b'a'..=b'z' => Some(c - b'a' + 26),
+		b'0'..=b'9' => Some(c - b'0' + 52),
+		b'+' => Some(62),
+		b'/' => Some(63),
+		_ => None,
+	};
+
+	let mut out = Vec::new();
+	let (mut acc, mut bits) = (0u32, 0);
+	for c in s.bytes().filter(|c| !c.is_ascii_whitespace() && *c != b'=') {
+		let v = value(c).ok_or_else(|| Error::InvalidMessage(format!("Invalid base64 char {:?}", c as char)))?;
+		acc = (acc << 6) | v as u32;
+		bits += 6;
+		if bits >= 8 {
+			bits -= 8;
+			out.push((acc >> bits) as u8);
+			acc &= (1 << bits) - 1;
+		}
+	}
+	Ok(String::from_utf8_lossy(&out).into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use librrt::Builder;
+
+	#[test]
+	fn it_formats_dates() {
+		assert_eq!("Thu, 01 Jan 1970 00:00:00 +0000", rfc5322_date(0));
+		assert_eq!("Tue, 29 Feb 2028 12:34:56 +0000", rfc5322_date(1_835_440_496));
+	}
+
+	#[test]
+	fn it_renders_a_message() {
+		let token = Builder::build_with_variant("0001020103039TWJXBACTSPAQ").unwrap();
+		let (subject, body) = Template::default().render(&token);
+		let message = Message::new("Registrar <registrar@example.org>", "alice@example.com", &subject, &body);
+		let raw = message.to_string();
+
+		assert!(raw.contains("To: alice@example.com\r\n"));
+		assert!(raw.contains("@example.org>\r\n"));
+		assert!(raw.contains("\r\n\r\nHello,\r\n"));
+		assert!(raw.contains("remarked on Kusama"));
+		assert_eq!(message, Message::parse(&raw).unwrap());
+	}
+
+	#[test]
+	fn it_decodes_bodies() {
+		let qp = "Content-Transfer-Encoding: quoted-printable\n\n0001020103039TWJXBA=\nCTSPAQ =C3=A9";
+		assert_eq!("0001020103039TWJXBACTSPAQ é", Message::parse(qp).unwrap().body);
+		// A `=` that does not start an escape is kept, even before a multibyte char
+		let qp = "Content-Transfer-Encoding: quoted-printable\n\nx=aé =3d=3D =G1";
+		assert_eq!("x=aé == =G1", Message::parse(qp).unwrap().body);
+
+		let b64 = "Content-Transfer-Encoding: base64\n\nMDAwMTAyMDEwMzAzOVRX\nSlhCQUNUU1BBUQ==";
+		assert_eq!("0001020103039TWJXBACTSPAQ", Message::parse(b64).unwrap().body);
+	}
+
+	#[test]
+	fn it_decodes_multipart_messages() {
+		let raw = "Content-Type: multipart/alternative;\n boundary=\"XX\"\n\npreamble\n--XX\n\
+			Content-Type: text/plain\nContent-Transfer-Encoding: quoted-printable\n\nmy token: 0001020103039TWJX=\nBACTSPAQ\n\
+			--XX\nContent-Type: image/png\n\nnot text\n--XX--\nepilogue";
+		let message = Message::parse(raw).unwrap();
+		assert_eq!("multipart/alternative; boundary=\"XX\"", message.header("content-type").unwrap());
+		assert!(message.body.contains("my token: 0001020103039TWJXBACTSPAQ"));
+		assert!(!message.body.contains("not text"));
+	}
+}
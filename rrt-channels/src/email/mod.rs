@@ -0,0 +1,139 @@
+pub use self::mailbox::*;
+pub use self::message::*;
+pub use self::smtp::*;
+
+mod mailbox;
+mod message;
+mod smtp;
+
+use crate::{Adapter, Error, Reply};
+use librrt::{Channel, Token};
+use std::fs;
+use std::path::PathBuf;
+
+/// Where the emails are sent
+#[derive(Debug, Clone)]
+pub enum Outbox {
+	/// Each message is written as an `.eml` file in this folder
+	Folder(PathBuf),
+	Maildir(Maildir),
+	Smtp(Smtp),
+}
+
+/// Where the replies are read from
+#[derive(Debug, Clone)]
+pub enum Inbox {
+	/// New messages are moved to `cur` once read
+	Maildir(Maildir),
+	/// The whole mbox is read each time
+	Mbox(PathBuf),
+}
+
+/// Sends tokens by email and reads the replies from a local mailbox
+pub struct EmailAdapter {
+	/// The sender of the emails, such as `Registrar <registrar@example.org>`
+	pub from: String,
+	pub template: Template,
+	pub outbox: Outbox,
+	pub inbox: Inbox,
+}
+
+impl EmailAdapter {
+	/// Renders the email carrying `token`
+	pub fn message(&self, recipient: &str, token: &Token) -> Message {
+		let (subject, body) = self.template.render(token);
+		Message::new(&self.from, recipient, &subject, &body)
+	}
+}
+
+impl Adapter for EmailAdapter {
+	fn channel(&self) -> Channel {
+		Channel::Email
+	}
+
+	fn send(&mut self, recipient: &str, token: &Token) -> Result<(), Error> {
+		let message = self.message(recipient, token);
+		match &self.outbox {
+			Outbox::Folder(path) => {
+				fs::create_dir_all(path)?;
+				let id = message.header("Message-ID").unwrap_or_default().trim_matches(['<', '>']).replace('/', "_");
+				fs::write(path.join(format!("{}.eml", id)), message.to_string())?;
+			}
+			Outbox::Maildir(maildir) => {
+				maildir.deliver(&message)?;
+			}
+			Outbox::Smtp(smtp) => smtp.send(&message)?,
+		}
+		Ok(())
+	}
+
+	fn receive(&mut self) -> Result<Vec<Reply>, Error> {
+		let messages = match &self.inbox {
+			Inbox::Maildir(maildir) => maildir.fetch_new()?,
+			Inbox::Mbox(path) => parse_mbox(&String::from_utf8_lossy(&fs::read(path)?))?,
+		};
+		Ok(messages
+			.into_iter()
+			.map(|m| Reply { from: self.key(m.header("From").unwrap_or_default()), body: m.body })
+			.collect())
+	}
+
+	/// The lowercased address, without the display name
+	fn key(&self, recipient: &str) -> String {
+		smtp::address(recipient).to_lowercase()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::Tracker;
+	use librrt::Builder;
+
+	#[test]
+	fn it_sends_and_receives_tokens() {
+		let path = std::env::temp_dir().join(format!("rrt-email-{}", std::process::id()));
+		let maildir = Maildir::open(&path).unwrap();
+		let mut adapter = EmailAdapter {
+			from: String::from("Registrar <registrar@example.org>"),
+			template: Template::default(),
+			outbox: Outbox::Maildir(maildir.clone()),
+			inbox: Inbox::Maildir(maildir),
+		};
+
+		// The user replies by quoting our message, which lands in the same maildir here
+		let token = Builder::build_with_variant("0001020103039TWJXBACTSPAQ").unwrap();
+		adapter.send("alice@example.com", &token).unwrap();
+
+		let replies = adapter.receive().unwrap();
+		assert_eq!(1, replies.len());
+		assert_eq!("registrar@example.org", replies[0].from);
+		assert_eq!("0001020103039TWJXBACTSPAQ", replies[0].tokens()[0].token_string());
+		fs::remove_dir_all(path).unwrap();
+	}
+
+	#[test]
+	fn it_verifies_replies_with_the_tracker() {
+		let path = std::env::temp_dir().join(format!("rrt-email-tracker-{}", std::process::id()));
+		let maildir = Maildir::open(&path.join("inbox")).unwrap();
+		let mut adapter = EmailAdapter {
+			from: String::from("Registrar <registrar@example.org>"),
+			template: Template::default(),
+			outbox: Outbox::Folder(path.join("outbox")),
+			inbox: Inbox::Maildir(maildir.clone()),
+		};
+		let mut tracker = Tracker::default();
+		let token = Builder::build_with_variant("0001020103039TWJXBACTSPAQ").unwrap();
+		tracker.send(&mut adapter, "Alice <Alice@example.com>", token).unwrap();
+
+		let body = "> Reply with 0001-02-01-03039-TW-JXBACTSPAQ\n\nDone";
+		maildir
+			.deliver(&Message::new("Alice <Alice@Example.COM>", "registrar@example.org", "Re: token", body))
+			.unwrap();
+		let confirmed = tracker.poll(&mut adapter).unwrap();
+		assert_eq!(1, confirmed.len());
+		assert_eq!("alice@example.com", confirmed[0].0);
+		assert_eq!(0, tracker.pending());
+		fs::remove_dir_all(path).unwrap();
+	}
+}
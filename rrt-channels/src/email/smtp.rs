@@ -0,0 +1,153 @@
+use crate::email::message::Message;
+use crate::error::Error;
+use std::io::{BufRead, BufReader, Write};
+use std::net::TcpStream;
+use std::time::Duration;
+
+/// A minimal SMTP client, without TLS nor authentication.
+/// It is meant for a local relay or a test server.
+#[derive(Debug, Clone)]
+pub struct Smtp {
+	pub addr: String,
+	/// The name given in the EHLO command
+	pub hostname: String,
+}
+
+/// Extracts the address from a header such as `Name <user@host>`
+pub(crate) fn address(header: &str) -> &str {
+	match (header.rfind('<'), header.rfind('>')) {
+		(Some(start), Some(end)) if start < end => &header[start + 1..end],
+		_ => header.trim(),
+	}
+}
+
+impl Smtp {
+	pub fn new(addr: &str) -> Self {
+		Self { addr: addr.to_string(), hostname: String::from("localhost") }
+	}
+
+	/// Sends the message to the recipient of its `To` header
+	pub fn send(&self, message: &Message) -> Result<(), Error> {
+		let from = message.header("From").map(address).unwrap_or_default().to_string();
+		let to =
+			message.header("To").map(address).ok_or_else(|| Error::InvalidMessage(String::from("No To header")))?;
+
+		let stream = TcpStream::connect(&self.addr)?;
+		stream.set_read_timeout(Some(Duration::from_secs(30)))?;
+		let mut reader = BufReader::new(stream.try_clone()?);
+		let mut writer = stream;
+
+		expect(&mut reader, 220)?;
+		command(&mut writer, &mut reader, &format!("EHLO {}", self.hostname), 250)?;
+		command(&mut writer, &mut reader, &format!("MAIL FROM:<{}>", from), 250)?;
+		command(&mut writer, &mut reader, &format!("RCPT TO:<{}>", to), 250)?;
+		command(&mut writer, &mut reader, "DATA", 354)?;
+
+		// Dot stuffing: lines starting with a dot get a second one
+		let mut data = String::new();
+		for line in message.to_string().lines() {
+			if line.starts_with('.') {
+				data.push('.');
+			}
+			data.push_str(line);
+			data.push_str("\r\n");
+		}
+		writer.write_all(data.as_bytes())?;
+		command(&mut writer, &mut reader, ".", 250)?;
+		command(&mut writer, &mut reader, "QUIT", 221)
+	}
+}
+
+fn command(writer: &mut impl Write, reader: &mut impl BufRead, line: &str, code: u16) -> Result<(), Error> {
+	writer.write_all(format!("{}\r\n", line).as_bytes())?;
+	expect(reader, code)
+}
+
+/// Reads a reply, which may span several lines such as `250-...` then `250 ...`
+fn expect(reader: &mut impl BufRead, code: u16) -> Result<(), Error> {
+	loop {
+		let mut line = String::new();
+		if reader.read_line(&mut line)? == 0 {
+			return Err(Error::Io(String::from("The SMTP server closed the connection")));
+		}
+		let found: u16 = line.get(..3).and_then(|c| c.parse().ok()).unwrap_or_default();
+		if found != code {
+			return Err(Error::Smtp(found, line.trim_end().to_string()));
+		}
+		if line.as_bytes().get(3) != Some(&b'-') {
+			return Ok(());
+		}
+	}
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+	use super::*;
+	use std::net::TcpListener;
+	use std::thread;
+
+	/// A stand-in SMTP server accepting one message and returning the DATA it received
+	pub fn serve_one() -> (String, thread::JoinHandle<String>) {
+		let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+		let addr = listener.local_addr().unwrap().to_string();
+
+		let handle = thread::spawn(move || {
+			let (stream, _) = listener.accept().unwrap();
+			let mut reader = BufReader::new(stream.try_clone().unwrap());
+			let mut writer = stream;
+			let mut data = String::new();
+			let mut in_data = false;
+
+			writer.write_all(b"220 localhost ready\r\n").unwrap();
+			loop {
+				let mut line = String::new();
+				if reader.read_line(&mut line).unwrap() == 0 {
+					break;
+				}
+				let reply: &[u8] = match (in_data, line.trim_end()) {
+					(true, ".") => {
+						in_data = false;
+						b"250 queued\r\n"
+					}
+					(true, _) => {
+						data.push_str(&line);
+						continue;
+					}
+					(false, "DATA") => {
+						in_data = true;
+						b"354 go ahead\r\n"
+					}
+					(false, "QUIT") => {
+						writer.write_all(b"221 bye\r\n").unwrap();
+						break;
+					}
+					(false, l) if l.starts_with("EHLO") => b"250-localhost\r\n250 8BITMIME\r\n",
+					(false, l) if l.starts_with("RCPT TO:<nobody@") => b"550 no such user\r\n",
+					_ => b"250 ok\r\n",
+				};
+				writer.write_all(reply).unwrap();
+			}
+			data
+		});
+		(addr, handle)
+	}
+
+	#[test]
+	fn it_sends_a_message() {
+		let (addr, server) = serve_one();
+		let message =
+			Message::new("Registrar <registrar@example.org>", "alice@example.com", "Token", ".hidden\nvisible\n");
+		Smtp::new(&addr).send(&message).unwrap();
+
+		let data = server.join().unwrap();
+		assert!(data.contains("To: alice@example.com\r\n"));
+		assert!(data.contains("\r\n..hidden\r\n"));
+	}
+
+	#[test]
+	fn it_reports_refused_recipients() {
+		let (addr, _server) = serve_one();
+		let message = Message::new("registrar@example.org", "nobody@example.com", "Token", "body");
+		assert_eq!(Err(Error::Smtp(550, String::from("550 no such user"))), Smtp::new(&addr).send(&message));
+	}
+}
@@ -0,0 +1,40 @@
+use std::fmt::Display;
+
+/// The errors of the channel adapters
+#[derive(Debug, PartialEq)]
+pub enum Error {
+	/// Reading or writing a file or a socket failed
+	Io(String),
+
+	/// A message could not be parsed
+	InvalidMessage(String),
+
+	/// The SMTP server refused the message. The tuple is (code, line).
+	Smtp(u16, String),
+
+	/// The recipient is not a valid address or user id for the channel
+	InvalidRecipient(String),
+
+	/// The homeserver returned an error. The tuple is (HTTP status, errcode).
+	Matrix(u16, String),
+}
+
+impl Display for Error {
+	fn fmt(&self, fmt: &mut std::fmt::Formatter<'_>) -> std::result::Result<(), std::fmt::Error> {
+		match self {
+			Error::Io(e) => write!(fmt, "I/O error: {}", e),
+			Error::InvalidMessage(e) => write!(fmt, "invalid message: {}", e),
+			Error::Smtp(code, line) => write!(fmt, "SMTP error {}: {}", code, line),
+			Error::InvalidRecipient(r) => write!(fmt, "invalid recipient: {}", r),
+			Error::Matrix(status, errcode) => write!(fmt, "Matrix error {}: {}", status, errcode),
+		}
+	}
+}
+
+impl std::error::Error for Error {}
+
+impl From<std::io::Error> for Error {
+	fn from(e: std::io::Error) -> Self {
+		Error::Io(e.to_string())
+	}
+}
@@ -0,0 +1,110 @@
+//! Adapters sending tokens to users and collecting their replies over the
+//! channels of [librrt::Channel].
+
+pub use email::*;
+pub use error::*;
+pub use matrix::*;
+
+mod email;
+mod error;
+mod matrix;
+
+use librrt::{Channel, Found, Scanner, Token, Tokenize};
+use std::collections::HashMap;
+
+/// Replaces `{token}`, `{token_sep}`, `{case_id}` and `{network}` by the values of the token
+pub fn fill(s: &str, token: &Token) -> String {
+	s.replace("{token}", &token.format_string(""))
+		.replace("{token_sep}", &token.format_string("-"))
+		.replace("{case_id}", &token.case_id().to_string())
+		.replace("{network}", &String::from(token.network()))
+}
+
+/// A message received from a user on a channel
+#[derive(Debug, PartialEq, Clone)]
+pub struct Reply {
+	/// The sender, such as an email address or a Matrix user id
+	pub from: String,
+	pub body: String,
+}
+
+impl Reply {
+	/// Returns the valid tokens found in the reply
+	pub fn tokens(&self) -> Vec<Found> {
+		Scanner::scan(&self.body)
+	}
+}
+
+/// Sends tokens and receives replies on a given channel
+pub trait Adapter {
+	fn channel(&self) -> Channel;
+
+	/// Sends `token` to `recipient`
+	fn send(&mut self, recipient: &str, token: &Token) -> Result<(), Error>;
+
+	/// Returns the replies received since the previous call
+	fn receive(&mut self) -> Result<Vec<Reply>, Error>;
+
+	/// Reduces a recipient to the form the `from` of its replies takes, so they can be matched
+	fn key(&self, recipient: &str) -> String {
+		recipient.to_string()
+	}
+}
+
+/// Remembers the tokens sent and verifies the replies against them:
+/// a reply verifies a token if it contains it and comes from the recipient of the token.
+#[derive(Debug, Default)]
+pub struct Tracker {
+	sent: HashMap<String, Token>,
+}
+
+impl Tracker {
+	/// Sends the token with the adapter and remembers it under the [Adapter::key] of the recipient
+	pub fn send(&mut self, adapter: &mut impl Adapter, recipient: &str, token: Token) -> Result<(), Error> {
+		adapter.send(recipient, &token)?;
+		self.sent.insert(adapter.key(recipient), token);
+		Ok(())
+	}
+
+	/// Receives the replies and returns the recipients and the tokens they confirmed.
+	/// A confirmed token is forgotten.
+	pub fn poll(&mut self, adapter: &mut impl Adapter) -> Result<Vec<(String, Token)>, Error> {
+		Ok(adapter.receive()?.iter().filter_map(|reply| self.verify(reply)).collect())
+	}
+
+	/// Returns the sender of the reply and its token if the reply contains the token sent to the sender
+	pub fn verify(&mut self, reply: &Reply) -> Option<(String, Token)> {
+		let expected = self.sent.get(&reply.from)?.format_string("");
+		if reply.tokens().iter().any(|found| found.token_string() == expected) {
+			self.sent.remove_entry(&reply.from)
+		} else {
+			None
+		}
+	}
+
+	/// The number of tokens waiting for a reply
+	pub fn pending(&self) -> usize {
+		self.sent.len()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use librrt::Builder;
+
+	#[test]
+	fn it_verifies_replies_from_the_recipient() {
+		let token = Builder::build_with_variant("0001020103039TWJXBACTSPAQ").unwrap();
+		let mut tracker = Tracker::default();
+		tracker.sent.insert(String::from("@alice:example.org"), token);
+
+		let reply = |from: &str, body: &str| Reply { from: from.to_string(), body: body.to_string() };
+		assert!(tracker.verify(&reply("@eve:example.org", "0001020103039TWJXBACTSPAQ")).is_none());
+		assert!(tracker.verify(&reply("@alice:example.org", "0000000012345TWRAJQFIZWW")).is_none());
+
+		let (from, _) = tracker.verify(&reply("@alice:example.org", "here: 0001-02-01-03039-TW-JXBACTSPAQ")).unwrap();
+		assert_eq!("@alice:example.org", from);
+		assert_eq!(0, tracker.pending());
+	}
+}
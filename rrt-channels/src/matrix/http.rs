@@ -0,0 +1,125 @@
+use crate::error::Error;
+use crate::matrix::{Event, MatrixClient};
+use serde_json::{json, Value};
+use std::time::Duration;
+
+const API: &str = "/_matrix/client/v3";
+
+/// Percent encodes a path segment such as a room id
+fn encode(s: &str) -> String {
+	s.bytes()
+		.map(|b| match b {
+			b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => (b as char).to_string(),
+			_ => format!("%{:02X}", b),
+		})
+		.collect()
+}
+
+/// A client of the Matrix client-server API, logged in with an access token
+pub struct HttpClient {
+	/// The base url of the homeserver, such as `https://matrix.example.org`
+	pub homeserver: String,
+	access_token: String,
+	user_id: String,
+	/// How long a sync waits for new events, in milliseconds
+	pub timeout: u64,
+	since: Option<String>,
+	txn: u64,
+	agent: ureq::Agent,
+}
+
+impl HttpClient {
+	pub fn new(homeserver: &str, user_id: &str, access_token: &str) -> Self {
+		Self {
+			homeserver: homeserver.trim_end_matches('/').to_string(),
+			access_token: access_token.to_string(),
+			user_id: user_id.to_string(),
+			timeout: 0,
+			since: None,
+			txn: 0,
+			agent: ureq::AgentBuilder::new().timeout_read(Duration::from_secs(60)).build(),
+		}
+	}
+
+	fn call(&self, method: &str, path: &str, body: Option<Value>) -> Result<Value, Error> {
+		let req = self
+			.agent
+			.request(method, &format!("{}{}{}", self.homeserver, API, path))
+			.set("Authorization", &format!("Bearer {}", self.access_token));
+		let res = match body {
+			Some(body) => req.send_json(body),
+			None => req.call(),
+		};
+
+		match res {
+			Ok(res) => res.into_json().map_err(Error::from),
+			Err(ureq::Error::Status(status, res)) => {
+				let body: Value = res.into_json().unwrap_or_default();
+				let errcode = body["errcode"].as_str().unwrap_or("M_UNKNOWN").to_string();
+				Err(Error::Matrix(status, errcode))
+			}
+			Err(e) => Err(Error::Io(e.to_string())),
+		}
+	}
+}
+
+impl MatrixClient for HttpClient {
+	fn user_id(&self) -> &str {
+		&self.user_id
+	}
+
+	fn create_direct_room(&mut self, user_id: &str) -> Result<String, Error> {
+		let body = json!({ "invite": [user_id], "is_direct": true, "preset": "trusted_private_chat" });
+		let res = self.call("POST", "/createRoom", Some(body))?;
+		res["room_id"]
+			.as_str()
+			.map(String::from)
+			.ok_or_else(|| Error::InvalidMessage(String::from("No room_id in the response")))
+	}
+
+	fn send_text(&mut self, room_id: &str, body: &str) -> Result<(), Error> {
+		self.txn += 1;
+		let txn = format!("rrt{}{}", std::process::id(), self.txn);
+		let path = format!("/rooms/{}/send/m.room.message/{}", encode(room_id), txn);
+		self.call("PUT", &path, Some(json!({ "msgtype": "m.text", "body": body })))?;
+		Ok(())
+	}
+
+	fn sync(&mut self) -> Result<Vec<Event>, Error> {
+		let mut path = format!("/sync?timeout={}", self.timeout);
+		if let Some(since) = &self.since {
+			path.push_str(&format!("&since={}", encode(since)));
+		}
+		let res = self.call("GET", &path, None)?;
+		self.since = res["next_batch"].as_str().map(String::from);
+
+		let mut events = Vec::new();
+		if let Some(rooms) = res["rooms"]["join"].as_object() {
+			for (room_id, room) in rooms {
+				for event in room["timeline"]["events"].as_array().into_iter().flatten() {
+					if let (Some("m.room.message"), Some(sender), Some(body)) =
+						(event["type"].as_str(), event["sender"].as_str(), event["content"]["body"].as_str())
+					{
+						events.push(Event {
+							room_id: room_id.clone(),
+							sender: sender.to_string(),
+							body: body.to_string(),
+						});
+					}
+				}
+			}
+		}
+		Ok(events)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn it_encodes_path_segments() {
+		assert_eq!("%21abc%3Aexample.org", encode("!abc:example.org"));
+		assert_eq!("s72594_4483_1934", encode("s72594_4483_1934"));
+	}
+}
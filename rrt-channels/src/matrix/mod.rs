@@ -0,0 +1,161 @@
+pub use self::http::*;
+
+mod http;
+
+use crate::{fill, Adapter, Error, Reply};
+use librrt::{Channel, Token};
+use std::collections::HashMap;
+
+/// A text message of a room
+#[derive(Debug, PartialEq, Clone)]
+pub struct Event {
+	pub room_id: String,
+	pub sender: String,
+	pub body: String,
+}
+
+/// The calls of the Matrix client-server API used by the adapter
+pub trait MatrixClient {
+	/// The user id of the bot, such as `@registrar:example.org`
+	fn user_id(&self) -> &str;
+
+	/// Creates a direct room with `user_id` and returns the id of the room
+	fn create_direct_room(&mut self, user_id: &str) -> Result<String, Error>;
+
+	fn send_text(&mut self, room_id: &str, body: &str) -> Result<(), Error>;
+
+	/// Returns the text messages received since the previous call
+	fn sync(&mut self) -> Result<Vec<Event>, Error>;
+}
+
+/// Checks that `user_id` looks like `@localpart:server`
+pub fn check_user_id(user_id: &str) -> Result<(), Error> {
+	match user_id.strip_prefix('@').and_then(|s| s.split_once(':')) {
+		Some((local, server)) if !local.is_empty() && !server.is_empty() => Ok(()),
+		_ => Err(Error::InvalidRecipient(user_id.to_string())),
+	}
+}
+
+/// Sends tokens in direct rooms and reads the replies of the users
+pub struct MatrixAdapter<C: MatrixClient> {
+	pub client: C,
+	/// The message carrying the token. See [fill] for the placeholders.
+	pub template: String,
+	/// The direct room of each user we sent a token to
+	rooms: HashMap<String, String>,
+}
+
+impl<C: MatrixClient> MatrixAdapter<C> {
+	pub fn new(client: C) -> Self {
+		Self {
+			client,
+			template: String::from(
+				"To verify this Matrix account, please reply with the following token: {token}\n\
+				The same token must also be remarked on {network} from your identity account.",
+			),
+			rooms: HashMap::new(),
+		}
+	}
+}
+
+impl<C: MatrixClient> Adapter for MatrixAdapter<C> {
+	fn channel(&self) -> Channel {
+		Channel::Matrix
+	}
+
+	fn send(&mut self, recipient: &str, token: &Token) -> Result<(), Error> {
+		check_user_id(recipient)?;
+		let room_id = match self.rooms.get(recipient) {
+			Some(room_id) => room_id.clone(),
+			None => {
+				let room_id = self.client.create_direct_room(recipient)?;
+				self.rooms.insert(recipient.to_string(), room_id.clone());
+				room_id
+			}
+		};
+		self.client.send_text(&room_id, &fill(&self.template, token))
+	}
+
+	/// Returns the messages of the other users, including those in rooms we did not create
+	fn receive(&mut self) -> Result<Vec<Reply>, Error> {
+		let user_id = self.client.user_id().to_string();
+		Ok(self
+			.client
+			.sync()?
+			.into_iter()
+			.filter(|e| e.sender != user_id)
+			.map(|e| Reply { from: e.sender, body: e.body })
+			.collect())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::Tracker;
+	use librrt::Builder;
+
+	/// Keeps the rooms in memory. Messages sent to a room are echoed back by the next sync.
+	#[derive(Default)]
+	struct MockClient {
+		rooms: Vec<String>,
+		events: Vec<Event>,
+	}
+
+	impl MatrixClient for MockClient {
+		fn user_id(&self) -> &str {
+			"@registrar:example.org"
+		}
+
+		fn create_direct_room(&mut self, user_id: &str) -> Result<String, Error> {
+			self.rooms.push(user_id.to_string());
+			Ok(format!("!{}:example.org", self.rooms.len()))
+		}
+
+		fn send_text(&mut self, room_id: &str, body: &str) -> Result<(), Error> {
+			let sender = self.user_id().to_string();
+			self.events.push(Event { room_id: room_id.to_string(), sender, body: body.to_string() });
+			Ok(())
+		}
+
+		fn sync(&mut self) -> Result<Vec<Event>, Error> {
+			Ok(std::mem::take(&mut self.events))
+		}
+	}
+
+	#[test]
+	fn it_checks_user_ids() {
+		assert!(check_user_id("@alice:example.org").is_ok());
+		assert!(check_user_id("alice:example.org").is_err());
+		assert!(check_user_id("@alice").is_err());
+		assert!(check_user_id("@:example.org").is_err());
+	}
+
+	#[test]
+	fn it_sends_tokens_and_verifies_replies() {
+		let token = || Builder::build_with_variant("0001020103039TWJXBACTSPAQ").unwrap();
+		let mut adapter = MatrixAdapter::new(MockClient::default());
+		let mut tracker = Tracker::default();
+
+		tracker.send(&mut adapter, "@alice:example.org", token()).unwrap();
+		assert_eq!(vec!["@alice:example.org"], adapter.client.rooms);
+		assert_eq!(1, adapter.client.events.len());
+		assert_eq!("!1:example.org", adapter.client.events[0].room_id);
+		assert!(adapter.client.events[0].body.contains("0001020103039TWJXBACTSPAQ"));
+		assert_eq!(Err(Error::InvalidRecipient(String::from("alice"))), tracker.send(&mut adapter, "alice", token()));
+		assert_eq!(1, adapter.client.events.len());
+
+		// Our own messages are not replies
+		assert!(tracker.poll(&mut adapter).unwrap().is_empty());
+
+		let body = String::from("0001020103039TWJXBACTSPAQ");
+		adapter.client.events.push(Event {
+			room_id: String::from("!1:example.org"),
+			sender: String::from("@alice:example.org"),
+			body,
+		});
+		let confirmed: Vec<_> =
+			tracker.poll(&mut adapter).unwrap().into_iter().map(|(from, t)| (from, t.to_string())).collect();
+		assert_eq!(vec![(String::from("@alice:example.org"), token().to_string())], confirmed);
+	}
+}
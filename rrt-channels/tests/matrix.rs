@@ -0,0 +1,115 @@
+//! Runs the Matrix adapter against a mock homeserver on localhost
+
+use librrt::Builder;
+use rrt_channels::{Adapter, Error, HttpClient, MatrixAdapter, Tracker};
+use serde_json::{json, Value};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use tiny_http::{Header, Response, Server};
+
+const TOKEN: &str = "secret";
+
+/// The events of the homeserver, as (room_id, sender, body). The sync batch is an index in this list.
+type Events = Arc<Mutex<Vec<(String, String, String)>>>;
+
+fn respond(status: u16, body: Value) -> Response<std::io::Cursor<Vec<u8>>> {
+	let header = Header::from_bytes("Content-Type", "application/json").unwrap();
+	Response::from_string(body.to_string()).with_status_code(status).with_header(header)
+}
+
+/// Starts a homeserver knowing a single bot user and returns its url and its events
+fn start() -> (String, Events) {
+	let server = Server::http("127.0.0.1:0").unwrap();
+	let url = format!("http://{}", server.server_addr().to_ip().unwrap());
+	let events: Events = Arc::default();
+	let shared = events.clone();
+
+	thread::spawn(move || {
+		for mut req in server.incoming_requests() {
+			let authorized = req
+				.headers()
+				.iter()
+				.any(|h| h.field.equiv("Authorization") && h.value == format!("Bearer {}", TOKEN).as_str());
+			let mut body = String::new();
+			req.as_reader().read_to_string(&mut body).unwrap();
+			let body: Value = serde_json::from_str(&body).unwrap_or_default();
+			let url = req.url().to_string();
+			let mut events = shared.lock().unwrap();
+
+			let res = if !authorized {
+				respond(401, json!({ "errcode": "M_MISSING_TOKEN" }))
+			} else if url == "/_matrix/client/v3/createRoom" {
+				respond(200, json!({ "room_id": format!("!{}:localhost", body["invite"][0].as_str().unwrap()) }))
+			} else if let Some(path) = url.strip_prefix("/_matrix/client/v3/rooms/") {
+				let room = path.split('/').next().unwrap().replace("%21", "!").replace("%40", "@").replace("%3A", ":");
+				events.push((room, String::from("@registrar:localhost"), body["body"].as_str().unwrap().to_string()));
+				respond(200, json!({ "event_id": format!("${}", events.len()) }))
+			} else if url.starts_with("/_matrix/client/v3/sync") {
+				let since: usize = url.split("since=").nth(1).and_then(|s| s.parse().ok()).unwrap_or(0);
+				let mut join = json!({});
+				for (room, sender, body) in &events[since..] {
+					let event = json!({ "type": "m.room.message", "sender": sender, "content": { "msgtype": "m.text", "body": body } });
+					join[room]["timeline"]["events"].as_array_mut().map(|a| a.push(event.clone())).unwrap_or_else(
+						|| {
+							join[room] = json!({ "timeline": { "events": [event] } });
+						},
+					);
+				}
+				respond(200, json!({ "next_batch": events.len().to_string(), "rooms": { "join": join } }))
+			} else {
+				respond(404, json!({ "errcode": "M_UNRECOGNIZED" }))
+			};
+			drop(events);
+			req.respond(res).unwrap();
+		}
+	});
+	(url, events)
+}
+
+#[test]
+fn it_sends_a_token_and_reads_the_reply() {
+	let (url, events) = start();
+	let client = HttpClient::new(&url, "@registrar:localhost", TOKEN);
+	let mut adapter = MatrixAdapter::new(client);
+	let mut tracker = Tracker::default();
+
+	let token = Builder::build_with_variant("0001020103039TWJXBACTSPAQ").unwrap();
+	tracker
+		.send(&mut adapter, "@alice:localhost", Builder::build_with_variant("0001020103039TWJXBACTSPAQ").unwrap())
+		.unwrap();
+	{
+		let events = events.lock().unwrap();
+		assert_eq!("!@alice:localhost:localhost", events[0].0);
+		assert!(events[0].2.contains("0001020103039TWJXBACTSPAQ"));
+	}
+	assert!(tracker.poll(&mut adapter).unwrap().is_empty());
+
+	let room = String::from("!@alice:localhost:localhost");
+	events.lock().unwrap().push((
+		room.clone(),
+		String::from("@eve:localhost"),
+		String::from("0001020103039TWJXBACTSPAQ"),
+	));
+	events.lock().unwrap().push((
+		room,
+		String::from("@alice:localhost"),
+		String::from("Sure: 0001-02-01-03039-TW-JXBACTSPAQ"),
+	));
+
+	let replies = adapter.receive().unwrap();
+	assert_eq!(2, replies.len());
+	let confirmed: Vec<_> =
+		replies.iter().filter_map(|r| tracker.verify(r)).map(|(from, t)| (from, t.to_string())).collect();
+	assert_eq!(vec![(String::from("@alice:localhost"), token.to_string())], confirmed);
+
+	// The next sync only returns the new events
+	assert!(adapter.receive().unwrap().is_empty());
+}
+
+#[test]
+fn it_reports_homeserver_errors() {
+	let (url, _) = start();
+	let mut adapter = MatrixAdapter::new(HttpClient::new(&url, "@registrar:localhost", "wrong"));
+	let token = Builder::build_with_variant("0001020103039TWJXBACTSPAQ").unwrap();
+	assert_eq!(Err(Error::Matrix(401, String::from("M_MISSING_TOKEN"))), adapter.send("@alice:localhost", &token));
+}
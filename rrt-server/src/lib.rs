@@ -0,0 +1,425 @@
+//! A small HTTP server issuing and verifying tokens for bots that do not link `librrt`.
+//!
+//! - `POST /tokens` issues a token for a case: `{"case_id": 12345, "channel": "TW", "network": 2}`
+//! - `POST /verify` checks a token and marks it consumed: `{"token": "...", "signer": "HNZata7..."}`
+//! - `GET /cases/{id}` returns the status of a case
+//!
+//! Case ids are only unique to a registrar: `GET /cases/{id}?network=2&index=1` tells which one,
+//! the defaults of the server being used otherwise.
+//! With a [Registry], the server only issues tokens for our registrars and keeps their cases in separate stores.
+
+pub use store::*;
+
+mod store;
+
+use librrt::*;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+
+/// Values used when a request does not specify them
+#[derive(Debug, Clone, Copy)]
+pub struct Defaults {
+	pub network: Option<u8>,
+	pub index: u8,
+}
+
+impl Default for Defaults {
+	fn default() -> Self {
+		Self { network: None, index: 1 }
+	}
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct IssueRequest {
+	case_id: u64,
+	channel: String,
+	network: Option<u8>,
+	index: Option<u8>,
+	/// The account being judged. It is required to verify the signer and for V02 tokens.
+	account: Option<String>,
+	#[serde(default = "default_version")]
+	version: u8,
+}
+
+fn default_version() -> u8 {
+	1
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct VerifyRequest {
+	token: String,
+	/// The account that remarked the token
+	signer: Option<String>,
+}
+
+/// An HTTP status and a JSON body
+pub type Response = (u16, Value);
+
+fn error(status: u16, msg: &str) -> Response {
+	(status, json!({ "error": msg }))
+}
+
+/// A librrt error, with its code so clients do not depend on the messages
+fn rrt_error(status: u16, msg: &str, e: &Error) -> Response {
+	(status, json!({ "error": msg, "code": e.code() }))
+}
+
+fn parse_account(s: &str) -> Result<AccountId, Response> {
+	s.parse().map_err(|_| error(400, &format!("Invalid account {:?}", s)))
+}
+
+/// Returns the value of `key` in the query string of `url`
+fn query<'a>(url: &'a str, key: &str) -> Option<&'a str> {
+	let query = url.split_once('?')?.1;
+	query.split('&').filter_map(|kv| kv.split_once('=')).find(|(k, _)| *k == key).map(|(_, v)| v)
+}
+
+/// Where the cases are kept
+enum Stores {
+	/// All the cases share a store, whatever their registrar
+	Single(Store),
+
+	/// Each registrar of the registry has its own store
+	PerRegistrar(Registry, HashMap<(u8, u8), Store>),
+}
+
+impl Stores {
+	/// Returns the store of a registrar. Only our registrars have one.
+	fn get(&mut self, network: Option<u8>, index: u8) -> Result<&mut Store, Response> {
+		match self {
+			Stores::Single(store) => Ok(store),
+			Stores::PerRegistrar(registry, stores) => {
+				let network = network.ok_or_else(|| error(400, "No network given"))?;
+				match (registry.get(network, index), stores.get_mut(&(network, index))) {
+					(Some(_), Some(store)) => Ok(store),
+					_ => Err(error(
+						400,
+						&format!("Registrar {} of {} is not ours", index, String::from(Network::from(network))),
+					)),
+				}
+			}
+		}
+	}
+}
+
+pub struct Server {
+	http: tiny_http::Server,
+	stores: Stores,
+	defaults: Defaults,
+	/// The revocation list, read again for each verification so `rt revoked --import` applies at once
+	revocations: Option<PathBuf>,
+	/// The versions of the tokens the server issues and verifies
+	builder: Builder,
+}
+
+impl Server {
+	/// Binds the server to `addr`. Use port 0 to pick a free port.
+	pub fn bind(addr: &str, store: Store, defaults: Defaults) -> Result<Self, String> {
+		let http = tiny_http::Server::http(addr).map_err(|e| format!("Cannot listen on {}: {}", addr, e))?;
+		Ok(Self { http, stores: Stores::Single(store), defaults, revocations: None, builder: Builder::new() })
+	}
+
+	/// Binds a server issuing tokens for the registrars of `registry` only.
+	/// `open` returns the store of each registrar.
+	pub fn bind_registry<F>(addr: &str, registry: Registry, open: F, defaults: Defaults) -> Result<Self, String>
+	where
+		F: Fn(&Registrar) -> Result<Store, String>,
+	{
+		let stores = registry
+			.registrars()
+			.iter()
+			.map(|r| Ok(((r.network, r.index), open(r)?)))
+			.collect::<Result<_, String>>()?;
+		let http = tiny_http::Server::http(addr).map_err(|e| format!("Cannot listen on {}: {}", addr, e))?;
+		Ok(Self {
+			http,
+			stores: Stores::PerRegistrar(registry, stores),
+			defaults,
+			revocations: None,
+			builder: Builder::new(),
+		})
+	}
+
+	/// Rejects the tokens revoked in the list saved at `path`
+	pub fn with_revocations(mut self, path: PathBuf) -> Self {
+		self.revocations = Some(path);
+		self
+	}
+
+	/// Only issues and verifies the tokens of the given versions
+	pub fn with_versions(mut self, versions: &[Version]) -> Self {
+		self.builder = self.builder.accept(versions);
+		self
+	}
+
+	pub fn addr(&self) -> Option<SocketAddr> {
+		self.http.server_addr().to_ip()
+	}
+
+	/// Serves the requests, one at a time, until the process stops
+	pub fn run(mut self) {
+		while let Ok(mut request) = self.http.recv() {
+			let mut body = String::new();
+			let (status, value) = match request.as_reader().read_to_string(&mut body) {
+				Ok(_) => self.handle(request.method().as_str(), request.url(), &body),
+				Err(_) => error(400, "The body is not valid UTF-8"),
+			};
+
+			let header = tiny_http::Header::from_bytes("Content-Type", "application/json").expect("static header");
+			let response =
+				tiny_http::Response::from_string(value.to_string()).with_status_code(status).with_header(header);
+			let _ = request.respond(response);
+		}
+	}
+
+	/// Routes a request and returns the response
+	pub fn handle(&mut self, method: &str, url: &str, body: &str) -> Response {
+		let path = url.split('?').next().unwrap_or_default();
+		let res = match (method, path.trim_end_matches('/')) {
+			("POST", "/tokens") => self.issue(body),
+			("POST", "/verify") => self.verify(body),
+			("GET", p) if p.starts_with("/cases/") => {
+				let network = query(url, "network").map(str::parse).transpose();
+				let index = query(url, "index").map(str::parse).transpose();
+				match (network, index) {
+					(Ok(network), Ok(index)) => self.case(
+						&p["/cases/".len()..],
+						network.or(self.defaults.network),
+						index.unwrap_or(self.defaults.index),
+					),
+					_ => Err(error(400, "Invalid network or index")),
+				}
+			}
+			_ => Err(error(404, "Not found")),
+		};
+		res.unwrap_or_else(|e| e)
+	}
+
+	fn issue(&mut self, body: &str) -> Result<Response, Response> {
+		let req: IssueRequest = serde_json::from_str(body).map_err(|e| error(400, &e.to_string()))?;
+		let network = req.network.or(self.defaults.network).ok_or_else(|| error(400, "No network given"))?;
+		let index = req.index.unwrap_or(self.defaults.index);
+		let account = req.account.as_deref().map(parse_account).transpose()?;
+		let channel = Channel::from(req.channel.as_str());
+		if channel == Channel::Unknown {
+			return Err(error(400, &format!("Unknown channel {:?}", req.channel)));
+		}
+		// A pending token may be replaced by a new one, a verified case is never issued again
+		let store = self.stores.get(Some(network), index)?;
+		if let Some(Case { status: Status::Consumed, .. }) = store.get(network, index, req.case_id) {
+			return Err(error(409, &format!("Case {} was already verified", req.case_id)));
+		}
+
+		let version = Version::try_from(req.version).map_err(|e| error(400, &e.to_string()))?;
+		if !self.builder.accepts(version) {
+			return Err(error(400, &format!("V{} tokens are not accepted", version)));
+		}
+		let token =
+			Token::new(version, 0, network, index, req.case_id, channel, account.as_ref()).map_err(|e| match e {
+				Error::CaseIdOverflow { max, .. } => {
+					rrt_error(400, &format!("The case id {} is larger than {}, use V04 tokens", req.case_id, max), &e)
+				}
+				Error::AccountRequired => rrt_error(400, "V02 tokens need an account", &e),
+				e => rrt_error(400, &e.report(), &e),
+			})?;
+
+		let case = Case {
+			case_id: req.case_id,
+			token: token.format_string(""),
+			channel: channel.to_string(),
+			network,
+			index,
+			account: account.map(|a| a.to_string()),
+			status: Status::Pending,
+			signer: None,
+		};
+		self.stores.get(Some(network), index)?.insert(case.clone()).map_err(|e| error(500, &e))?;
+		Ok((201, json!(case)))
+	}
+
+	fn verify(&mut self, body: &str) -> Result<Response, Response> {
+		let req: VerifyRequest = serde_json::from_str(body).map_err(|e| error(400, &e.to_string()))?;
+		let signer = req.signer.as_deref().map(parse_account).transpose()?;
+
+		let token = match &signer {
+			Some(signer) => self.builder.parse_for_account(&req.token, signer),
+			None => self.builder.parse(&req.token),
+		}
+		.map_err(|e| match e {
+			Error::AccountRequired => rrt_error(400, "This token is bound to an account, the signer is required", &e),
+			e => rrt_error(400, &e.report(), &e),
+		})?;
+
+		if let Some(path) = &self.revocations {
+			let revocations = RevocationList::load(path).map_err(|e| error(500, &e.to_string()))?;
+			if let Err(e @ Error::Revoked(_)) = revocations.check(&token) {
+				return Err(rrt_error(410, &e.to_string(), &e));
+			}
+		}
+
+		let case_id = *token.case_id();
+		let (network, index) = (u8::from(token.network()), *token.index());
+		let store = self.stores.get(Some(network), index)?;
+		let case =
+			store.get(network, index, case_id).ok_or_else(|| error(404, &format!("Unknown case {}", case_id)))?;
+		if case.token != token.format_string("") {
+			return Err(error(400, "This is not the token issued for this case"));
+		}
+		if case.status == Status::Consumed {
+			return Err(error(409, "This token was already used"));
+		}
+
+		if case.account.is_some() {
+			let signer = signer.ok_or_else(|| error(400, "The signer is required for this case"))?;
+			Verifier::new(&*store).verify(&req.token, &signer).map_err(|e| match e {
+				e @ Error::WrongSigner { .. } => {
+					rrt_error(403, "The token was not remarked by the account of the case", &e)
+				}
+				e => rrt_error(400, &e.report(), &e),
+			})?;
+		}
+
+		store.consume(network, index, case_id, signer.map(|s| s.to_string())).map_err(|e| error(500, &e))?;
+		self.case(&case_id.to_string(), Some(network), index)
+	}
+
+	fn case(&mut self, id: &str, network: Option<u8>, index: u8) -> Result<Response, Response> {
+		let id: u64 = id.parse().map_err(|_| error(400, &format!("Invalid case id {:?}", id)))?;
+		let store = self.stores.get(network, index)?;
+		let network = network.ok_or_else(|| error(400, "No network given"))?;
+		match store.get(network, index, id) {
+			Some(case) => Ok((200, json!(case))),
+			None => Err(error(404, &format!("Unknown case {}", id))),
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn server() -> Server {
+		let defaults = Defaults { network: Some(2), index: 1 };
+		Server::bind("127.0.0.1:0", Store::memory(), defaults).unwrap()
+	}
+
+	#[test]
+	fn it_issues_and_consumes_tokens() {
+		let mut server = server();
+		let (status, case) = server.handle("POST", "/tokens", r#"{"case_id": 7, "channel": "TW"}"#);
+		assert_eq!(201, status);
+		assert_eq!("pending", case["status"]);
+
+		let body = json!({ "token": case["token"] }).to_string();
+		let (status, case) = server.handle("POST", "/verify", &body);
+		assert_eq!(200, status);
+		assert_eq!("consumed", case["status"]);
+		assert_eq!(409, server.handle("POST", "/verify", &body).0);
+
+		// The case cannot be issued a new token once verified
+		let (status, body) = server.handle("POST", "/tokens", r#"{"case_id": 7, "channel": "TW"}"#);
+		assert_eq!((409, "Case 7 was already verified"), (status, body["error"].as_str().unwrap()));
+		assert_eq!("consumed", server.handle("GET", "/cases/7", "").1["status"]);
+	}
+
+	#[test]
+	fn it_replaces_pending_tokens() {
+		let mut server = server();
+		let (_, first) = server.handle("POST", "/tokens", r#"{"case_id": 7, "channel": "TW"}"#);
+		let (status, second) = server.handle("POST", "/tokens", r#"{"case_id": 7, "channel": "EM"}"#);
+		assert_eq!(201, status);
+		assert_eq!(400, server.handle("POST", "/verify", &json!({ "token": first["token"] }).to_string()).0);
+		assert_eq!(200, server.handle("POST", "/verify", &json!({ "token": second["token"] }).to_string()).0);
+	}
+
+	#[test]
+	fn it_rejects_bad_requests() {
+		let mut server = server();
+		assert_eq!(400, server.handle("POST", "/tokens", r#"{"case_id": 7}"#).0);
+		assert_eq!(400, server.handle("POST", "/tokens", r#"{"case_id": 7, "channel": "ZZ"}"#).0);
+		assert_eq!(400, server.handle("POST", "/tokens", r#"{"case_id": 7, "channel": "TW", "version": 2}"#).0);
+		assert_eq!(400, server.handle("GET", "/cases/abc", "").0);
+		assert_eq!(404, server.handle("GET", "/cases/7", "").0);
+		assert_eq!(404, server.handle("DELETE", "/tokens", "").0);
+	}
+
+	#[test]
+	fn it_only_accepts_some_versions() {
+		let mut server = server();
+		let (_, v00) = server.handle("POST", "/tokens", r#"{"case_id": 7, "channel": "TW", "version": 0}"#);
+		let mut server = server.with_versions(&[Version::V01, Version::V04]);
+		assert_eq!(400, server.handle("POST", "/tokens", r#"{"case_id": 8, "channel": "TW", "version": 0}"#).0);
+		assert_eq!(201, server.handle("POST", "/tokens", r#"{"case_id": 8, "channel": "TW", "version": 4}"#).0);
+
+		let (status, body) = server.handle("POST", "/verify", &json!({ "token": v00["token"] }).to_string());
+		assert_eq!(
+			(400, "Invalid token version: V00 tokens are not accepted"),
+			(status, body["error"].as_str().unwrap())
+		);
+		assert_eq!("version_not_accepted", body["code"]);
+	}
+
+	#[test]
+	fn it_rejects_revoked_tokens() {
+		let path = std::env::temp_dir().join(format!("rrt-revocations-{}.json", std::process::id()));
+		let mut server = server().with_revocations(path.clone());
+		let (_, case) = server.handle("POST", "/tokens", r#"{"case_id": 7, "channel": "TW"}"#);
+
+		let mut revocations = RevocationList::default();
+		revocations.revoke(Revocation::Case { network: 2, index: 1, case_id: 7 }, None, 0);
+		revocations.save(&path).unwrap();
+		let (status, body) = server.handle("POST", "/verify", &json!({ "token": case["token"] }).to_string());
+		std::fs::remove_file(path).unwrap();
+		assert_eq!(
+			(410, "This token was revoked: case 7 of registrar 1 of Kusama"),
+			(status, body["error"].as_str().unwrap())
+		);
+		assert_eq!("revoked", body["code"]);
+	}
+
+	#[test]
+	fn it_keeps_a_store_per_registrar() {
+		let registry = Registry::parse(
+			r#"[
+				{ "network": 2, "index": 1, "name": "Chevdor", "account": "HNZata7iMYWmk5RvZRTiAsSDhV8366zq2YGb3tLH5Upf74F" },
+				{ "network": 0, "index": 3, "name": "Chevdor", "account": "15oF4uVJwmo4TdGW7VfQxNLavjCXviqxT9S1MgbjMNHr6Sp5" }
+			]"#,
+		)
+		.unwrap();
+		let defaults = Defaults { network: Some(2), index: 1 };
+		let mut server = Server::bind_registry("127.0.0.1:0", registry, |_| Ok(Store::memory()), defaults).unwrap();
+
+		assert_eq!(201, server.handle("POST", "/tokens", r#"{"case_id": 7, "channel": "TW"}"#).0);
+		let (status, polkadot) =
+			server.handle("POST", "/tokens", r#"{"case_id": 7, "channel": "TW", "network": 0, "index": 3}"#);
+		assert_eq!((201, 3), (status, polkadot["index"].as_u64().unwrap()));
+		assert_eq!(400, server.handle("POST", "/tokens", r#"{"case_id": 7, "channel": "TW", "index": 3}"#).0);
+
+		// Both registrars have their own case 7
+		assert_eq!(200, server.handle("GET", "/cases/7", "").0);
+		let (status, case) = server.handle("GET", "/cases/7?network=0&index=3", "");
+		assert_eq!((200, &polkadot["token"]), (status, &case["token"]));
+		assert_eq!(400, server.handle("GET", "/cases/7?network=0&index=1", "").0);
+
+		let body = json!({ "token": polkadot["token"] }).to_string();
+		assert_eq!(200, server.handle("POST", "/verify", &body).0);
+		assert_eq!("pending", server.handle("GET", "/cases/7", "").1["status"]);
+
+		let foreign = TokenV01::new(0, Version::V01, 2, 9, 7, Channel::Twitter).to_string();
+		assert_eq!(400, server.handle("POST", "/verify", &json!({ "token": foreign }).to_string()).0);
+	}
+
+	#[test]
+	fn it_rejects_tokens_not_issued_by_the_server() {
+		let mut server = server();
+		server.handle("POST", "/tokens", r#"{"case_id": 12345, "channel": "TW"}"#);
+		let (status, _) = server.handle("POST", "/verify", r#"{"token": "0001020103039TWJXBACTSPAQ"}"#);
+		assert_eq!(400, status);
+	}
+}
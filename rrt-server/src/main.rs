@@ -0,0 +1,85 @@
+use clap::Parser;
+use librrt::{Registry, Version};
+use rrt_server::{Defaults, Server, Store};
+use std::convert::TryFrom;
+use std::path::PathBuf;
+use std::process;
+
+/// Issue and verify Registrar Remark Tokens over HTTP
+#[derive(Parser)]
+#[clap(version, author)]
+struct Opts {
+	/// The address to listen on
+	#[clap(long, default_value = "127.0.0.1:8080")]
+	addr: String,
+
+	/// The JSON file the cases are saved to. Cases are kept in memory only if not set.
+	#[clap(long, conflicts_with = "registry")]
+	store: Option<PathBuf>,
+
+	/// A JSON file listing our registrars. Only their tokens are issued and verified.
+	#[clap(long)]
+	registry: Option<PathBuf>,
+
+	/// The directory the cases of each registrar of the registry are saved to, as `{network}-{index}.json`
+	#[clap(long, requires = "registry")]
+	store_dir: Option<PathBuf>,
+
+	/// The network used when a request does not specify it
+	#[clap(long)]
+	network: Option<u8>,
+
+	/// The JSON file of the revoked tokens, as written by `rt revoke`
+	#[clap(long)]
+	revocations: Option<PathBuf>,
+
+	/// The versions of the tokens issued and verified, such as `1,4`. All of them if not set.
+	#[clap(long, value_delimiter = ',')]
+	versions: Vec<u8>,
+
+	/// The registrar index used when a request does not specify it
+	#[clap(long, default_value = "1")]
+	index: u8,
+}
+
+fn main() {
+	let opts = Opts::parse();
+	let defaults = Defaults { network: opts.network, index: opts.index };
+	let addr = opts.addr;
+	let store_dir = opts.store_dir;
+
+	let server = match (opts.registry, opts.store) {
+		(Some(path), _) => Registry::load(&path).map_err(|e| e.to_string()).and_then(|registry| {
+			let open = |r: &librrt::Registrar| match &store_dir {
+				Some(dir) => Store::open(dir.join(format!("{}-{}.json", r.network, r.index))),
+				None => Ok(Store::memory()),
+			};
+			Server::bind_registry(&addr, registry, open, defaults)
+		}),
+		(None, Some(path)) => Store::open(path).and_then(|store| Server::bind(&addr, store, defaults)),
+		(None, None) => Server::bind(&addr, Store::memory(), defaults),
+	};
+	let versions =
+		opts.versions.into_iter().map(Version::try_from).collect::<Result<Vec<_>, _>>().unwrap_or_else(|e| {
+			eprintln!("{}", e);
+			process::exit(1)
+		});
+	let server = server.map(|s| match versions.is_empty() {
+		true => s,
+		false => s.with_versions(&versions),
+	});
+	let revocations = opts.revocations;
+	let server = server.map(|s| match revocations {
+		Some(path) => s.with_revocations(path),
+		None => s,
+	});
+	let server = server.unwrap_or_else(|e| {
+		eprintln!("{}", e);
+		process::exit(1)
+	});
+
+	if let Some(addr) = server.addr() {
+		println!("Listening on http://{}", addr);
+	}
+	server.run()
+}
@@ -0,0 +1,147 @@
+//! The store keeps track of the tokens issued for each case and whether they were consumed.
+//! It is kept in memory and optionally saved as JSON after each change.
+//! Case ids are only unique to a registrar, so cases are keyed by network, registrar index and case id.
+
+use librrt::{AccountId, CaseStore};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+
+#[derive(Debug, PartialEq, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Status {
+	/// The token was issued and is waiting to be remarked
+	Pending,
+	/// The token was verified and cannot be used again
+	Consumed,
+}
+
+/// What we know about a case
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct Case {
+	pub case_id: u64,
+	pub token: String,
+	pub channel: String,
+	pub network: u8,
+	/// The registrar index. The stores written before it was recorded used the default index, 1.
+	#[serde(default = "default_index")]
+	pub index: u8,
+	/// The account being judged, as a hex public key
+	pub account: Option<String>,
+	pub status: Status,
+	/// The account that remarked the token, once consumed
+	pub signer: Option<String>,
+}
+
+fn default_index() -> u8 {
+	1
+}
+
+impl Case {
+	pub fn account(&self) -> Option<AccountId> {
+		self.account.as_ref().and_then(|a| a.parse().ok())
+	}
+}
+
+/// The network, the registrar index and the id of a case
+type Key = (u8, u8, u64);
+
+#[derive(Debug, Default)]
+pub struct Store {
+	cases: HashMap<Key, Case>,
+	path: Option<PathBuf>,
+}
+
+impl Store {
+	/// A store that is lost when the server stops
+	pub fn memory() -> Self {
+		Self::default()
+	}
+
+	/// Loads the store from `path` if it exists. Changes are saved to `path`.
+	pub fn open(path: PathBuf) -> Result<Self, String> {
+		let cases = match path.exists() {
+			true => {
+				let content =
+					fs::read_to_string(&path).map_err(|e| format!("Cannot read {}: {}", path.display(), e))?;
+				let cases: Vec<Case> =
+					serde_json::from_str(&content).map_err(|e| format!("Invalid store {}: {}", path.display(), e))?;
+				cases.into_iter().map(|c| ((c.network, c.index, c.case_id), c)).collect()
+			}
+			false => HashMap::new(),
+		};
+		Ok(Self { cases, path: Some(path) })
+	}
+
+	pub fn get(&self, network: u8, index: u8, case_id: u64) -> Option<&Case> {
+		self.cases.get(&(network, index, case_id))
+	}
+
+	/// Adds or replaces a case. Issuing a new token for a case invalidates the previous one,
+	/// callers must not replace a consumed case.
+	pub fn insert(&mut self, case: Case) -> Result<(), String> {
+		self.cases.insert((case.network, case.index, case.case_id), case);
+		self.save()
+	}
+
+	/// Marks the token of a case as consumed by `signer`
+	pub fn consume(&mut self, network: u8, index: u8, case_id: u64, signer: Option<String>) -> Result<(), String> {
+		if let Some(case) = self.cases.get_mut(&(network, index, case_id)) {
+			case.status = Status::Consumed;
+			case.signer = signer;
+		}
+		self.save()
+	}
+
+	fn save(&self) -> Result<(), String> {
+		let path = match &self.path {
+			Some(path) => path,
+			None => return Ok(()),
+		};
+		let mut cases: Vec<&Case> = self.cases.values().collect();
+		cases.sort_by_key(|c| (c.network, c.index, c.case_id));
+		let content = serde_json::to_string_pretty(&cases).map_err(|e| e.to_string())?;
+		fs::write(path, content).map_err(|e| format!("Cannot write {}: {}", path.display(), e))
+	}
+}
+
+impl CaseStore for Store {
+	fn account(&self, network: u8, index: u8, case_id: u64) -> Option<AccountId> {
+		self.get(network, index, case_id).and_then(Case::account)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn case() -> Case {
+		Case {
+			case_id: 12345,
+			token: String::from("0001020103039TWJXBACTSPAQ"),
+			channel: String::from("TW"),
+			network: 2,
+			index: 1,
+			account: Some(AccountId([1; 32]).to_string()),
+			status: Status::Pending,
+			signer: None,
+		}
+	}
+
+	#[test]
+	fn it_persists_cases() {
+		let path = std::env::temp_dir().join(format!("rrt-store-{}.json", std::process::id()));
+		let _ = fs::remove_file(&path);
+
+		let mut store = Store::open(path.clone()).unwrap();
+		store.insert(case()).unwrap();
+		store.consume(2, 1, 12345, None).unwrap();
+
+		let store = Store::open(path.clone()).unwrap();
+		assert_eq!(Status::Consumed, store.get(2, 1, 12345).unwrap().status);
+		assert_eq!(Some(AccountId([1; 32])), store.account(2, 1, 12345));
+		assert_eq!(None, store.account(0, 1, 12345));
+		fs::remove_file(path).unwrap();
+	}
+}
@@ -0,0 +1,128 @@
+//! Spins up the server on localhost and talks to it over HTTP
+
+use rrt_server::{Defaults, Server, Store};
+use serde_json::{json, Value};
+use std::thread;
+
+const ALICE: &str = "HNZata7iMYWmk5RvZRTiAsSDhV8366zq2YGb3tLH5Upf74F";
+const BOB: &str = "0x0202020202020202020202020202020202020202020202020202020202020202";
+
+/// Starts a server on a free port and returns its base url
+fn start() -> String {
+	let defaults = Defaults { network: Some(2), index: 1 };
+	let server = Server::bind("127.0.0.1:0", Store::memory(), defaults).unwrap();
+	let url = format!("http://{}", server.addr().unwrap());
+	thread::spawn(move || server.run());
+	url
+}
+
+/// Sends a request and returns the status and the JSON body, whatever the status
+fn call(method: &str, url: &str, body: Option<Value>) -> (u16, Value) {
+	let req = ureq::request(method, url);
+	let res = match body {
+		Some(body) => req.send_json(body),
+		None => req.call(),
+	};
+	let res = match res {
+		Ok(res) => res,
+		Err(ureq::Error::Status(_, res)) => res,
+		Err(e) => panic!("{}", e),
+	};
+	(res.status(), res.into_json().unwrap())
+}
+
+#[test]
+fn it_issues_and_verifies_tokens() {
+	let url = start();
+
+	let (status, case) = call("POST", &format!("{}/tokens", url), Some(json!({ "case_id": 42, "channel": "EM" })));
+	assert_eq!(201, status);
+	let token = case["token"].as_str().unwrap().to_string();
+	assert_eq!(25, token.len());
+
+	let (status, case) = call("GET", &format!("{}/cases/42", url), None);
+	assert_eq!((200, "pending"), (status, case["status"].as_str().unwrap()));
+
+	let (status, _) = call("POST", &format!("{}/verify", url), Some(json!({ "token": token })));
+	assert_eq!(200, status);
+
+	let (status, case) = call("GET", &format!("{}/cases/42", url), None);
+	assert_eq!((200, "consumed"), (status, case["status"].as_str().unwrap()));
+
+	let (status, body) = call("POST", &format!("{}/verify", url), Some(json!({ "token": token })));
+	assert_eq!(409, status);
+	assert!(body["error"].is_string());
+}
+
+#[test]
+fn it_checks_the_signer() {
+	let url = start();
+
+	let issue = json!({ "case_id": 7, "channel": "TW", "account": ALICE, "version": 2 });
+	let (status, case) = call("POST", &format!("{}/tokens", url), Some(issue));
+	assert_eq!(201, status);
+	let token = case["token"].as_str().unwrap().to_string();
+
+	let verify = |signer: Option<&str>| {
+		call("POST", &format!("{}/verify", url), Some(json!({ "token": token, "signer": signer })))
+	};
+	assert_eq!(400, verify(None).0);
+	assert_eq!(400, verify(Some(BOB)).0);
+	assert_eq!(200, verify(Some(ALICE)).0);
+}
+
+#[test]
+fn it_keeps_the_cases_of_each_network_apart() {
+	let url = start();
+
+	let issue = |network: u8| {
+		let (status, case) = call(
+			"POST",
+			&format!("{}/tokens", url),
+			Some(json!({ "case_id": 7, "channel": "TW", "network": network })),
+		);
+		assert_eq!(201, status);
+		case["token"].as_str().unwrap().to_string()
+	};
+	let kusama = issue(2);
+	let polkadot = issue(0);
+
+	let (status, _) = call("POST", &format!("{}/verify", url), Some(json!({ "token": kusama })));
+	assert_eq!(200, status);
+
+	let case = |network: u8| call("GET", &format!("{}/cases/7?network={}", url, network), None).1;
+	assert_eq!(
+		("consumed", kusama.as_str()),
+		(case(2)["status"].as_str().unwrap(), case(2)["token"].as_str().unwrap())
+	);
+	assert_eq!(
+		("pending", polkadot.as_str()),
+		(case(0)["status"].as_str().unwrap(), case(0)["token"].as_str().unwrap())
+	);
+	assert_eq!(404, call("GET", &format!("{}/cases/7?network=42", url), None).0);
+
+	let (status, _) = call("POST", &format!("{}/verify", url), Some(json!({ "token": polkadot })));
+	assert_eq!(200, status);
+}
+
+#[test]
+fn it_returns_404_for_unknown_cases() {
+	let url = start();
+	assert_eq!(404, call("GET", &format!("{}/cases/1", url), None).0);
+	assert_eq!(404, call("GET", &format!("{}/nope", url), None).0);
+}
+
+#[test]
+fn it_rejects_non_ascii_accounts() {
+	let url = start();
+	let account = format!("0x{}", "a€".repeat(16));
+
+	let issue = json!({ "case_id": 8, "channel": "TW", "account": account, "version": 2 });
+	assert_eq!(400, call("POST", &format!("{}/tokens", url), Some(issue)).0);
+
+	let verify = json!({ "token": "0001020103039TWJOBACTIPVR", "signer": account });
+	assert_eq!(400, call("POST", &format!("{}/verify", url), Some(verify)).0);
+
+	// The server is still up
+	assert_eq!(404, call("GET", &format!("{}/cases/8", url), None).0);
+}
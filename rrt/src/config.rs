@@ -0,0 +1,326 @@
+//! The configuration of `rt`.
+//!
+//! Values are resolved in this order, the last one wins:
+//! - built-in defaults
+//! - the config file: `$RRT_CONFIG`, or `$XDG_CONFIG_HOME/rrt/config.toml`, or `~/.config/rrt/config.toml`
+//! - `RRT_*` environment variables
+//! - command line flags
+
+use librrt::Alphabet;
+use serde::Deserialize;
+use std::env;
+use std::fmt::Display;
+use std::fs;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+#[derive(Debug, PartialEq, Clone, Copy, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputFormat {
+	Text,
+	Json,
+}
+
+impl FromStr for OutputFormat {
+	type Err = String;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		match s {
+			"text" => Ok(OutputFormat::Text),
+			"json" => Ok(OutputFormat::Json),
+			_ => Err(format!("Unknown format {:?}, expected text or json", s)),
+		}
+	}
+}
+
+impl Display for OutputFormat {
+	fn fmt(&self, fmt: &mut std::fmt::Formatter<'_>) -> std::result::Result<(), std::fmt::Error> {
+		match self {
+			OutputFormat::Text => write!(fmt, "text"),
+			OutputFormat::Json => write!(fmt, "json"),
+		}
+	}
+}
+
+/// Where a value of the configuration comes from
+#[derive(Debug, PartialEq, Clone)]
+pub enum Source {
+	Default,
+	File(PathBuf),
+	Env(&'static str),
+}
+
+impl Display for Source {
+	fn fmt(&self, fmt: &mut std::fmt::Formatter<'_>) -> std::result::Result<(), std::fmt::Error> {
+		match self {
+			Source::Default => write!(fmt, "default"),
+			Source::File(p) => write!(fmt, "{}", p.display()),
+			Source::Env(var) => write!(fmt, "env {}", var),
+		}
+	}
+}
+
+/// A value of the configuration and where it comes from
+#[derive(Debug, PartialEq, Clone)]
+pub struct Setting<T> {
+	pub value: T,
+	pub source: Source,
+}
+
+impl<T> Setting<T> {
+	fn new(value: T) -> Self {
+		Self { value, source: Source::Default }
+	}
+
+	fn set(&mut self, value: T, source: Source) {
+		self.value = value;
+		self.source = source;
+	}
+}
+
+/// The content of the config file. All fields are optional.
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct FileConfig {
+	pub network: Option<u8>,
+	pub index: Option<u8>,
+	pub app: Option<u8>,
+	pub version: Option<u8>,
+	pub separator: Option<String>,
+	pub format: Option<OutputFormat>,
+	pub alphabet: Option<String>,
+	pub registry: Option<PathBuf>,
+	pub revocations: Option<PathBuf>,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Config {
+	/// The path of the config file, whether it exists or not
+	pub path: Option<PathBuf>,
+	pub network: Setting<Option<u8>>,
+	pub index: Setting<u8>,
+	pub app: Setting<u8>,
+	pub version: Setting<u8>,
+	pub separator: Setting<String>,
+	pub format: Setting<OutputFormat>,
+	/// The alphabet of the secrets, the one of the version when not set
+	pub alphabet: Setting<Option<Alphabet>>,
+	/// The JSON file listing our registrars
+	pub registry: Setting<Option<PathBuf>>,
+	/// The JSON file of the revoked tokens, next to the config file by default
+	pub revocations: Setting<Option<PathBuf>>,
+}
+
+impl Default for Config {
+	fn default() -> Self {
+		Self {
+			path: None,
+			network: Setting::new(None),
+			index: Setting::new(1),
+			app: Setting::new(0),
+			version: Setting::new(1),
+			separator: Setting::new(String::new()),
+			format: Setting::new(OutputFormat::Text),
+			alphabet: Setting::new(None),
+			registry: Setting::new(None),
+			revocations: Setting::new(None),
+		}
+	}
+}
+
+/// Parses an env variable if it is set
+fn from_env<T: FromStr>(
+	env: &dyn Fn(&str) -> Option<String>,
+	var: &'static str,
+) -> Result<Option<(T, Source)>, String> {
+	match env(var) {
+		None => Ok(None),
+		Some(v) => {
+			v.parse().map(|v| Some((v, Source::Env(var)))).map_err(|_| format!("Invalid value for {}: {:?}", var, v))
+		}
+	}
+}
+
+impl Config {
+	/// Returns the path of the config file
+	pub fn path() -> Option<PathBuf> {
+		if let Some(p) = env::var_os("RRT_CONFIG") {
+			return Some(PathBuf::from(p));
+		}
+		let base = match env::var_os("XDG_CONFIG_HOME") {
+			Some(p) => PathBuf::from(p),
+			None => PathBuf::from(env::var_os("HOME")?).join(".config"),
+		};
+		Some(base.join("rrt").join("config.toml"))
+	}
+
+	/// Loads the configuration from the config file and the environment
+	pub fn load() -> Result<Self, String> {
+		let path = Self::path();
+		let file = match &path {
+			Some(p) if p.exists() => {
+				let content = fs::read_to_string(p).map_err(|e| format!("Cannot read {}: {}", p.display(), e))?;
+				let file: FileConfig =
+					toml::from_str(&content).map_err(|e| format!("Invalid {}: {}", p.display(), e))?;
+				Some((p.clone(), file))
+			}
+			_ => None,
+		};
+
+		let mut config = Self::resolve(file, &|var| env::var(var).ok())?;
+		if config.revocations.value.is_none() {
+			config.revocations.value = path.as_ref().and_then(|p| p.parent()).map(|dir| dir.join("revocations.json"));
+		}
+		config.path = path;
+		Ok(config)
+	}
+
+	/// Merges the defaults, the content of the config file and the environment
+	pub fn resolve(file: Option<(PathBuf, FileConfig)>, env: &dyn Fn(&str) -> Option<String>) -> Result<Self, String> {
+		let mut config = Config::default();
+
+		if let Some((path, file)) = file {
+			let src = || Source::File(path.clone());
+			if let Some(v) = file.network {
+				config.network.set(Some(v), src());
+			}
+			if let Some(v) = file.index {
+				config.index.set(v, src());
+			}
+			if let Some(v) = file.app {
+				config.app.set(v, src());
+			}
+			if let Some(v) = file.version {
+				config.version.set(v, src());
+			}
+			if let Some(v) = file.separator {
+				config.separator.set(v, src());
+			}
+			if let Some(v) = file.format {
+				config.format.set(v, src());
+			}
+			if let Some(v) = file.alphabet {
+				config.alphabet.set(Some(v.parse().map_err(|e| format!("Invalid {}: {}", path.display(), e))?), src());
+			}
+			if let Some(v) = file.registry {
+				config.registry.set(Some(v), src());
+			}
+			if let Some(v) = file.revocations {
+				config.revocations.set(Some(v), src());
+			}
+		}
+
+		if let Some((v, src)) = from_env(env, "RRT_NETWORK")? {
+			config.network.set(Some(v), src);
+		}
+		if let Some((v, src)) = from_env(env, "RRT_INDEX")? {
+			config.index.set(v, src);
+		}
+		if let Some((v, src)) = from_env(env, "RRT_APP")? {
+			config.app.set(v, src);
+		}
+		if let Some((v, src)) = from_env(env, "RRT_VERSION")? {
+			config.version.set(v, src);
+		}
+		if let Some((v, src)) = from_env(env, "RRT_SEPARATOR")? {
+			config.separator.set(v, src);
+		}
+		if let Some((v, src)) = from_env(env, "RRT_FORMAT")? {
+			config.format.set(v, src);
+		}
+		if let Some((v, src)) = from_env(env, "RRT_ALPHABET")? {
+			config.alphabet.set(Some(v), src);
+		}
+		if let Some((v, src)) = from_env(env, "RRT_REGISTRY")? {
+			config.registry.set(Some(v), src);
+		}
+		if let Some((v, src)) = from_env(env, "RRT_REVOCATIONS")? {
+			config.revocations.set(Some(v), src);
+		}
+
+		Ok(config)
+	}
+}
+
+impl Display for Config {
+	fn fmt(&self, fmt: &mut std::fmt::Formatter<'_>) -> std::result::Result<(), std::fmt::Error> {
+		match &self.path {
+			Some(p) if p.exists() => writeln!(fmt, "# config file: {}", p.display())?,
+			Some(p) => writeln!(fmt, "# config file: {} (not found)", p.display())?,
+			None => writeln!(fmt, "# config file: none")?,
+		}
+		let network = self.network.value.map(|n| n.to_string()).unwrap_or_else(|| String::from("none"));
+		writeln!(fmt, "network   = {:<8} # {}", network, self.network.source)?;
+		writeln!(fmt, "index     = {:<8} # {}", self.index.value, self.index.source)?;
+		writeln!(fmt, "app       = {:<8} # {}", self.app.value, self.app.source)?;
+		writeln!(fmt, "version   = {:<8} # {}", self.version.value, self.version.source)?;
+		writeln!(fmt, "separator = {:<8} # {}", format!("{:?}", self.separator.value), self.separator.source)?;
+		writeln!(fmt, "format    = {:<8} # {}", format!("{:?}", self.format.value.to_string()), self.format.source)?;
+		let alphabet = self.alphabet.value.map(|a| format!("{:?}", a.to_string()));
+		writeln!(
+			fmt,
+			"alphabet  = {:<8} # {}",
+			alphabet.unwrap_or_else(|| String::from("none")),
+			self.alphabet.source
+		)?;
+		let registry = self.registry.value.as_ref().map(|p| format!("{:?}", p.display().to_string()));
+		writeln!(
+			fmt,
+			"registry  = {:<8} # {}",
+			registry.unwrap_or_else(|| String::from("none")),
+			self.registry.source
+		)?;
+		let revocations = self.revocations.value.as_ref().map(|p| format!("{:?}", p.display().to_string()));
+		writeln!(
+			fmt,
+			"revocations = {:<6} # {}",
+			revocations.unwrap_or_else(|| String::from("none")),
+			self.revocations.source
+		)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn no_env(_: &str) -> Option<String> {
+		None
+	}
+
+	#[test]
+	fn it_uses_defaults() {
+		let config = Config::resolve(None, &no_env).unwrap();
+		assert_eq!(Config::default(), config);
+		assert_eq!(1, config.index.value);
+		assert_eq!(None, config.network.value);
+		assert_eq!(None, config.alphabet.value);
+	}
+
+	#[test]
+	fn it_overrides_the_file_with_env() {
+		let file: FileConfig =
+			toml::from_str("network = 2\nindex = 3\nformat = \"json\"\nalphabet = \"crockford\"").unwrap();
+		let path = PathBuf::from("/tmp/rrt.toml");
+		let env = |var: &str| match var {
+			"RRT_INDEX" => Some(String::from("7")),
+			"RRT_REGISTRY" => Some(String::from("/etc/rrt/registry.json")),
+			_ => None,
+		};
+
+		let config = Config::resolve(Some((path.clone(), file)), &env).unwrap();
+		assert_eq!(Setting { value: Some(2), source: Source::File(path.clone()) }, config.network);
+		assert_eq!(Setting { value: 7, source: Source::Env("RRT_INDEX") }, config.index);
+		assert_eq!(Setting { value: OutputFormat::Json, source: Source::File(path.clone()) }, config.format);
+		assert_eq!(Setting { value: Some(Alphabet::Crockford), source: Source::File(path) }, config.alphabet);
+		assert_eq!(Source::Default, config.app.source);
+		assert_eq!(Some(PathBuf::from("/etc/rrt/registry.json")), config.registry.value);
+	}
+
+	#[test]
+	fn it_rejects_invalid_values() {
+		let env = |var: &str| if var == "RRT_NETWORK" { Some(String::from("300")) } else { None };
+		assert!(Config::resolve(None, &env).is_err());
+		assert!(toml::from_str::<FileConfig>("netwrok = 2").is_err());
+	}
+}
@@ -1,165 +1,730 @@
-use clap::{crate_authors, crate_version, Clap};
+mod config;
+mod opts;
+mod qr;
+mod scan;
+
+use clap::{CommandFactory, Parser};
+use config::{Config, OutputFormat};
+use librrt::signing::Keypair;
 use librrt::*;
+use opts::*;
+use qr::{QrContent, QrFormat};
+use rand::rngs::{OsRng, StdRng};
+use rand::{Rng, SeedableRng};
+use serde::Deserialize;
+use serde_json::json;
+use std::collections::HashMap;
 use std::convert::TryFrom;
+use std::fmt::Display;
+use std::fs::{self, File};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::time::{SystemTime, UNIX_EPOCH};
 use termion::{color, style};
 
-// TODO: Fix doc below
-/// This doc string acts as a help message when the user runs '--help'
-/// as do all doc strings on fields
-#[derive(Clap)]
-#[clap(version = crate_version!(), author = crate_authors!())]
-struct Opts {
-	/// Some input. Because this isn't an Option<T> it's required to be used
-	// token: Option<String>,
+/// A colored separator, reset to the default style once printed
+struct Separator<'a>(&'a str);
+
+impl Display for Separator<'_> {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		write!(f, "{}{}{}", color::Fg(color::Yellow), self.0, style::Reset)
+	}
+}
 
-	#[clap(subcommand)]
-	subcmd: SubCommand,
+/// Returns the fields of the token in the order they are written, as given by its `format_string`
+fn token_fields(token: &impl Tokenize) -> Vec<(Field, String)> {
+	let mut fields = vec![Field::App, Field::Version, Field::Network, Field::Index];
+	if *token.version() == Version::V04 {
+		fields.push(Field::CaseIdLength);
+	}
+	fields.extend([Field::CaseId, Field::Channel]);
+	if *token.version() == Version::V03 {
+		fields.push(Field::SecretLength);
+	}
+	fields.extend([Field::Secret, Field::Checksum]);
+	fields.into_iter().zip(token.format_string("\n").split('\n').map(String::from)).collect()
 }
 
-#[derive(Clap)]
-enum SubCommand {
-	/// Generate a new token
-	#[clap(author = "Chevdor <chevdor@gmail.com>")]
-	New(New),
+fn print_token(token: impl Tokenize) {
+	let c1 = color::Fg(color::Red);
+	let c2 = color::Fg(color::Green);
+	let c3 = color::Fg(color::Blue);
+	let c4 = color::Fg(color::Yellow);
+	let fields = token_fields(&token);
+	let colors: Vec<&dyn Display> = fields
+		.iter()
+		.enumerate()
+		.map(|(i, (field, _))| match (field, i % 3) {
+			(Field::Checksum, _) => &c4 as &dyn Display,
+			(_, 0) => &c1,
+			(_, 1) => &c3,
+			_ => &c2,
+		})
+		.collect();
+
+	let line: Vec<String> = fields.iter().zip(&colors).map(|((_, raw), c)| format!("{}{}", c, raw)).collect();
+	println!("{}{}", line.join(&Separator("-").to_string()), style::Reset);
 
-	/// Check an existing token
-	#[clap(author = "Chevdor <chevdor@gmail.com>")]
-	Check(Check),
+	// Each field is described on its own line, the last one first, the descriptions being aligned
+	let starts: Vec<usize> =
+		fields.iter().scan(0, |start, (_, raw)| Some(std::mem::replace(start, *start + raw.len() + 1))).collect();
+	let column = starts.last().copied().unwrap_or_default() + 5;
+	let width = fields.iter().map(|(f, _)| f.to_string().len()).max().unwrap_or_default().max(10);
+	for k in (0..fields.len()).rev() {
+		let (field, raw) = &fields[k];
+		let value = match field {
+			Field::App => format!("0x{:02x}", token.app()),
+			Field::Version => format!("0x{:02x}", token.version()),
+			Field::Network => match token.network() {
+				Network::Known(n) => format!("{:?}", n),
+				Network::Unknown(u) => format!("0x{:02x}", u),
+			},
+			Field::Index => format!("0x{:02x}", token.index()),
+			Field::CaseIdLength => format!("{} digits", u8::from_str_radix(raw, 16).unwrap_or_default() + 1),
+			Field::CaseId => format!("{} (hex: {})", token.case_id(), raw),
+			Field::Channel => token.channel().format_str(),
+			Field::SecretLength => format!("{} chars", token.secret().len()),
+			Field::Secret => token.secret().clone(),
+			Field::Checksum => token.checksum(),
+		};
+		let diagram: String =
+			fields[..k].iter().zip(&colors).map(|((_, raw), c)| format!("{}│{}", c, " ".repeat(raw.len()))).collect();
+		let dashes = "╴".repeat(column - starts[k] - 1);
+		println!("{}{}└{}{:<w$}: {}{}", diagram, colors[k], dashes, field.to_string(), value, style::Reset, w = width);
+	}
+	println!();
 }
 
-/// A subcommand for generating new tokens
-#[derive(Clap, Debug)]
-struct New {
-	/// The application
-	#[clap(long, default_value = "0")]
-	app: u8,
+fn print_explanation(explanation: &Explanation) {
+	let ok = color::Fg(color::Green);
+	let ko = color::Fg(color::Red);
+	let warn = color::Fg(color::Yellow);
 
-	/// The version of the App
-	#[clap(long, default_value = "1")]
-	version: u8,
+	println!("Explaining: {}", explanation.cleaned);
+	for f in &explanation.fields {
+		let (c, mark): (&dyn Display, &str) = match explanation.issues_for(f.field).map(|i| i.is_fatal()).max() {
+			None => (&ok, "✔"),
+			Some(false) => (&warn, "!"),
+			Some(true) => (&ko, "✘"),
+		};
+		println!("  {}{} {:<14} @{:<3} {}{}", c, mark, f.field.to_string(), f.offset, f.raw, style::Reset);
+	}
 
-	/// The Network. 01: Polkadot, 02: Kusama, 42: Westend
-	#[clap(long)]
-	network: u8,
+	if explanation.is_valid() {
+		println!("{}This token is valid{}", ok, style::Reset);
+	}
+	for issue in &explanation.issues {
+		let c: &dyn Display = if issue.is_fatal() { &ko } else { &warn };
+		println!("{}- {}{}", c, issue, style::Reset);
+	}
+}
+
+fn token_to_json(token: &impl Tokenize) -> serde_json::Value {
+	json!({
+		"token": token.format_string(""),
+		"app": token.app(),
+		"version": *token.version() as u8,
+		"network": Into::<u8>::into(token.network()),
+		"network_name": Into::<String>::into(token.network()),
+		"index": token.index(),
+		"case_id": token.case_id(),
+		"channel": token.channel().to_string(),
+		"secret": token.secret(),
+		"checksum": token.checksum(),
+	})
+}
+
+fn explanation_to_json(explanation: &Explanation) -> serde_json::Value {
+	json!({
+		"token": explanation.cleaned,
+		"valid": explanation.is_valid(),
+		"issues": explanation.issues.iter().map(|i| i.to_string()).collect::<Vec<_>>(),
+	})
+}
+
+/// Renders the QR code of `data` to `output` or to the terminal
+fn write_qr(format: QrFormat, data: &str, output: Option<PathBuf>) -> Result<(), String> {
+	let content = match (format, &output) {
+		(QrFormat::Png, Some(path)) => {
+			let file = File::create(path).map_err(|e| format!("Cannot create {}: {}", path.display(), e))?;
+			return qr::to_png(data, io::BufWriter::new(file));
+		}
+		(QrFormat::Png, None) => return Err(String::from("A PNG QR code needs a file, use --qr-output")),
+		(QrFormat::Term, _) => qr::to_term(data)?,
+		(QrFormat::Svg, _) => qr::to_svg(data)?,
+	};
+
+	match output {
+		Some(path) => fs::write(&path, content).map_err(|e| format!("Cannot write {}: {}", path.display(), e)),
+		None => {
+			println!("{}", content);
+			Ok(())
+		}
+	}
+}
+
+/// A store associating the same account with every case
+struct AnyCase(AccountId);
+
+impl CaseStore for AnyCase {
+	fn account(&self, _network: u8, _index: u8, _case_id: u64) -> Option<AccountId> {
+		Some(self.0)
+	}
+}
 
-	/// Registrar index 0..255
-	#[clap(long, default_value = "1")]
+/// A case of a file given to `rt verify --cases`. The other fields, such as those of a store of rrt-server, are ignored.
+#[derive(Deserialize)]
+struct CaseEntry {
+	network: u8,
 	index: u8,
+	case_id: u64,
+	account: Option<String>,
+}
 
-	/// The case ID of our process
-	#[clap(long)]
-	id: u64,
+/// Loads a JSON list of cases, keyed by the network, the registrar index and the id of each case.
+/// The cases without an account are skipped.
+fn load_cases(path: &Path) -> Result<HashMap<(u8, u8, u64), AccountId>, String> {
+	let content = fs::read_to_string(path).map_err(|e| format!("Cannot read {}: {}", path.display(), e))?;
+	let cases: Vec<CaseEntry> =
+		serde_json::from_str(&content).map_err(|e| format!("Invalid {}: {}", path.display(), e))?;
+	cases
+		.into_iter()
+		.filter_map(|CaseEntry { network, index, case_id, account }| {
+			let account = account?;
+			let parsed = account.parse().map_err(|_| format!("Invalid account {:?} in {}", account, path.display()));
+			Some(parsed.map(|account| ((network, index, case_id), account)))
+		})
+		.collect()
+}
 
-	/// The channel: TW | EM | MX
-	#[clap(long)]
-	channel: String,
+/// Loads the revoked tokens. The list is empty until a token is revoked.
+fn load_revocations(config: &Config) -> RevocationList {
+	match &config.revocations.value {
+		Some(path) => {
+			RevocationList::load(path).unwrap_or_else(|e| fail_with(&e, &format!("Invalid {}: {}", path.display(), e)))
+		}
+		None => RevocationList::default(),
+	}
+}
 
-	#[clap(long)]
-	separator: Option<String>,
+/// Returns the current unix timestamp, in seconds
+fn now() -> u64 {
+	SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or_default()
 }
 
-#[derive(Clap, Debug)]
-struct Check {
-	/// Print debug info
-	#[clap(index = 1)]
-	// #[clap(short)]
-	token: String,
+/// Writes a secret to a file only readable by its owner
+fn write_secret(path: &Path, content: &str) -> io::Result<()> {
+	let mut options = fs::OpenOptions::new();
+	options.write(true).create_new(true);
+	#[cfg(unix)]
+	std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+	io::Write::write_all(&mut options.open(path)?, content.as_bytes())
 }
 
-fn print_token(token: impl Tokenize + std::fmt::Debug) {
-	let c1 = color::Fg(color::Red);
-	let c2 = color::Fg(color::Green);
-	let c3 = color::Fg(color::Blue);
-	let c4 = color::Fg(color::Yellow);
+/// Reads a key written by `rt keygen`
+fn load_key(path: &Path) -> Keypair {
+	let key = fs::read_to_string(path).unwrap_or_else(|e| fail(&format!("Cannot read {}: {}", path.display(), e)));
+	Keypair::parse(&key).unwrap_or_else(|e| fail_with(&e, &format!("Invalid key {}: {}", path.display(), e)))
+}
+
+/// Prints an error and exits
+fn fail(msg: &str) -> ! {
+	eprintln!("{}", msg);
+	process::exit(1)
+}
+
+/// The exit code for each class of errors, so scripts can tell a typo from a revoked token.
+/// 1 is used for the other failures and 2 for invalid arguments.
+fn exit_code(e: &Error) -> i32 {
+	match e.class() {
+		ErrorClass::Format => 3,
+		ErrorClass::Checksum => 4,
+		ErrorClass::Account => 5,
+		ErrorClass::Registrar => 6,
+		ErrorClass::Revoked => 7,
+		ErrorClass::Signature => 8,
+		ErrorClass::Config => 9,
+	}
+}
 
-	println!(
-		"{app}{S}{version}{S}{network}{S}{index}{S}{case_id}{S}{channel}{S}{secret}{S}{checksum}",
-		S = format!("{}{}{}", color::Fg(color::Yellow), "-", style::Reset),
-		app = format!("{}{:02x}{}", c1, token.app(), style::Reset),
-		version = format!("{}{:02x}{}", c3, token.version(), style::Reset),
-		network = format!("{}{:02x}{}", c2, Into::<u8>::into(token.network()), style::Reset),
-		index = format!("{}{:02x}{}", c1, token.index(), style::Reset),
-		case_id = format!("{}{:05x}{}", c3, token.case_id(), style::Reset),
-		channel = format!("{}{}{}", c2, token.channel().to_string(), style::Reset),
-		secret = format!("{}{}{}", c1, token.secret(), style::Reset),
-		checksum = format!("{}{}{}", c4, token.checksum(), style::Reset),
-	);
-
-	println!(
-		r#"{C1}│  {C3}│  {C2}│  {C1}│  {C3}│     {C2}│  {C1}│        {C4}└╴╴╴╴checksum  : {checksum}{NORM}
-{C1}│  {C3}│  {C2}│  {C1}│  {C3}│     {C2}│  {C1}└╴╴╴╴╴╴╴╴╴╴╴╴╴secret    : {secret}{NORM}
-{C1}│  {C3}│  {C2}│  {C1}│  {C3}│     {C2}└╴╴╴╴╴╴╴╴╴╴╴╴╴╴╴╴channel   : {channel}{NORM}
-{C1}│  {C3}│  {C2}│  {C1}│  {C3}└╴╴╴╴╴╴╴╴╴╴╴╴╴╴╴╴╴╴╴╴╴╴case Id   : {case_id} (hex: {case_id:05X}){NORM}
-{C1}│  {C3}│  {C2}│  {C1}└╴╴╴╴╴╴╴╴╴╴╴╴╴╴╴╴╴╴╴╴╴╴╴╴╴reg_index : {C1}0x{index:02x}{NORM}
-{C1}│  {C3}│  {C2}└╴╴╴╴╴╴╴╴╴╴╴╴╴╴╴╴╴╴╴╴╴╴╴╴╴╴╴╴network   : {network}{NORM}
-{C1}│  {C3}└╴╴╴╴╴╴╴╴╴╴╴╴╴╴╴╴╴╴╴╴╴╴╴╴╴╴╴╴╴╴╴version   : 0x{version:02x}{NORM}
-{C1}└╴╴╴╴╴╴╴╴╴╴╴╴╴╴╴╴╴╴╴╴╴╴╴╴╴╴╴╴╴╴╴╴╴╴app       : 0x{app:02x}{NORM}
-        "#,
-		app = token.app(),
-		version = token.version(),
-		network = match token.network() {
-			Network::Known(n) => format!("{:?}", n),
-			Network::Unknown(u) => format!("0x{:02x}", u),
-		},
-		index = token.index(),
-		case_id = token.case_id(),
-		channel = token.channel().format_str(),
-		secret = token.secret(),
-		checksum = token.checksum(),
-		C1 = c1,
-		C2 = c2,
-		C3 = c3,
-		C4 = c4,
-		NORM = style::Reset,
-	);
+/// Prints a message about `e` and exits with the code of its class
+fn fail_with(e: &Error, msg: &str) -> ! {
+	eprintln!("{}", msg);
+	process::exit(exit_code(e))
+}
+
+/// Returns our registrar with the given name on `network`, or on its only network
+fn find_registrar<'a>(registry: &'a Registry, name: &'a str, network: Option<u8>) -> &'a Registrar {
+	let found: Vec<&Registrar> = registry.by_name(name).filter(|r| network.is_none_or(|n| r.network == n)).collect();
+	match found[..] {
+		[registrar] => registrar,
+		[] => fail(&format!("No registrar named {:?} in the registry", name)),
+		_ => fail(&format!("Registrar {:?} is on several networks, use --network", name)),
+	}
+}
+
+/// Returns the channel named `name`, failing for the unknown ones
+fn parse_channel(name: &str) -> Channel {
+	match Channel::from(name) {
+		Channel::Unknown => fail(&format!("Unknown channel {:?}, expected one of TW, EM, MX", name)),
+		channel => channel,
+	}
+}
+
+/// Explains why a token does not target one of our registrars
+fn unknown_registrar(network: u8, index: u8) -> String {
+	format!(
+		"The token is for registrar {} of {}, which is not in the registry",
+		index,
+		String::from(Network::from(network))
+	)
 }
 
 fn main() {
 	let opts: Opts = Opts::parse();
+	let config = Config::load().unwrap_or_else(|e| fail(&e));
+	let format = opts.format.unwrap_or(config.format.value);
+	let registry = opts.registry.or_else(|| config.registry.value.clone()).map(|path| match Registry::load(&path) {
+		Ok(registry) => registry,
+		Err(e @ Error::InvalidRegistry(_)) => fail_with(&e, &format!("Invalid {}: {}", path.display(), e)),
+		Err(e) => fail_with(&e, &e.to_string()),
+	});
 
 	match opts.subcmd {
 		SubCommand::New(n) => {
-			let token = match (n.app, Version::try_from(n.version)) {
-				(0, Ok(Version::V00)) => Token::V00(TokenV00::new(
-					n.app,
-					Version::try_from(n.version).expect("version not supported"),
-					Network::from(n.network),
-					n.index,
-					n.id,
-					Channel::from(n.channel.as_str()),
-				)),
-				(0, Ok(Version::V01)) => Token::V01(TokenV01::new(
-					n.app,
-					Version::try_from(n.version).expect("version not supported"),
-					n.network,
-					n.index,
-					n.id,
-					Channel::from(n.channel.as_str()),
-				)),
-				_ => panic!("App/Version not supported"),
+			let app = n.app.unwrap_or(config.app.value);
+			let version = n.version.unwrap_or(config.version.value);
+			let (network, index) = match (&n.registrar, &registry) {
+				(Some(name), Some(registry)) => {
+					let registrar = find_registrar(registry, name, n.network.or(config.network.value));
+					(registrar.network, registrar.index)
+				}
+				(Some(_), None) => fail("--registrar needs a registry, use --registry or set it in the config"),
+				(None, _) => {
+					let network = n.network.or(config.network.value).unwrap_or_else(|| {
+						fail("No network given, use --network or set a default network in the config, see `rt config show`")
+					});
+					(network, n.index.unwrap_or(config.index.value))
+				}
 			};
+			if registry.as_ref().is_some_and(|r| r.get(network, index).is_none()) {
+				fail(&format!(
+					"Registrar {} of {} is not in the registry",
+					index,
+					String::from(Network::from(network))
+				));
+			}
+			let channel = parse_channel(&n.channel);
 
-			// The following could be done in debug
-			// println!("Token   : {}", token.format_string(""));
-			// println!("Token   : {}", token.format_string("_"));
-			// print_token(token);
+			let version = match (app, Version::try_from(version)) {
+				(0, Ok(version)) => version,
+				_ => fail(&format!("App {} with version {} is not supported", app, version)),
+			};
+			if n.secret_length.is_some() && version != Version::V03 {
+				fail("--secret-length is only supported by V03 tokens, use --version 3");
+			}
+			let length = n.secret_length.map_or(version.default_secret_length(), usize::from);
+			let alphabet = n.alphabet.or(config.alphabet.value).unwrap_or_else(|| version.alphabet());
+			if !alphabet.is_subset_of(version.alphabet()) {
+				fail(&format!(
+					"The {} alphabet cannot be used by V{} tokens, their secret is drawn from the {} alphabet",
+					alphabet,
+					version,
+					version.alphabet()
+				));
+			}
+			let secret = alphabet.random(length);
 
-			// Output the generated token
-			let sep = match n.separator {
-				None => String::from(""),
-				Some(s) => s,
+			let token = match Token::new_with_secret(
+				version,
+				app,
+				network,
+				index,
+				n.id,
+				channel,
+				&secret,
+				n.account.as_ref(),
+			) {
+				Ok(token) => token,
+				Err(e @ Error::CaseIdOverflow { max, .. }) => fail_with(
+					&e,
+					&format!("The case id {} is larger than {} for V{} tokens, use --version 4", n.id, max, version),
+				),
+				Err(e @ Error::AccountRequired) => fail_with(&e, "V02 tokens are bound to an account, use --account"),
+				Err(e) => fail_with(&e, &e.report()),
 			};
-			println!("{}", token.format_string(&sep));
+
+			let (token, signed) = match &n.sign {
+				Some(path) => {
+					let signed = SignedToken::sign(token, &load_key(path));
+					let text = signed.to_string();
+					(signed.token, Some(text))
+				}
+				None => (token, None),
+			};
+
+			let call = if n.with_event { RemarkCall::RemarkWithEvent } else { RemarkCall::Remark };
+			let call_data = n.call_data.then(|| match n.call_index {
+				Some(index) => encode_call(index, token.format_string("").as_bytes()),
+				None => remark_call_data(&token, call),
+			});
+			let deep_link = match (&call_data, n.deep_link) {
+				(Some(call_data), true) => Some(polkadot_js_link(token.network(), call_data).unwrap_or_else(|| {
+					fail(&format!(
+						"No known RPC endpoint for {}, cannot build a deep link",
+						String::from(token.network())
+					))
+				})),
+				_ => None,
+			};
+			let instructions = n.instructions.then(|| {
+				let locale = match Locale::get(&n.lang) {
+					Ok(locale) => locale,
+					Err(e) => fail_with(&e, &e.report()),
+				};
+				let data = call_data.clone().unwrap_or_else(|| remark_call_data(&token, call));
+				librrt::instructions(&token, &locale, Some(&data))
+			});
+
+			// Output the generated token
+			let sep = n.separator.unwrap_or(config.separator.value);
+			match format {
+				OutputFormat::Text => {
+					match &signed {
+						Some(signed) => println!("{}", signed),
+						None => println!("{}", token.format_string(&sep)),
+					}
+					if n.uri {
+						println!("{}", token.to_uri());
+					}
+					if n.envelope {
+						println!("{}", token.to_envelope());
+					}
+					if let Some(call_data) = &call_data {
+						println!("{}", to_hex(call_data));
+					}
+					if let Some(link) = &deep_link {
+						println!("{}", link);
+					}
+					if let Some(instructions) = &instructions {
+						println!("\n{}", instructions);
+					}
+				}
+				OutputFormat::Json => {
+					let mut json = token_to_json(&token);
+					if let Some(signed) = &signed {
+						json["signed"] = json!(signed);
+					}
+					if n.uri {
+						json["uri"] = json!(token.to_uri());
+					}
+					if n.envelope {
+						json["envelope"] = json!(token.to_envelope());
+					}
+					if let Some(call_data) = &call_data {
+						json["call_data"] = json!(to_hex(call_data));
+					}
+					if let Some(link) = &deep_link {
+						json["deep_link"] = json!(link);
+					}
+					if let Some(instructions) = &instructions {
+						json["instructions"] = json!(instructions);
+					}
+					println!("{}", json)
+				}
+			}
+
+			if let Some(qr_format) = n.qr {
+				let data = match n.qr_content {
+					QrContent::Token => token.format_string(""),
+					QrContent::Uri => token.to_uri(),
+					QrContent::Envelope => token.to_envelope(),
+				};
+				write_qr(qr_format, &data, n.qr_output).unwrap_or_else(|e| fail(&e));
+			}
 		}
 		SubCommand::Check(tkn) => {
-			let candidate = Builder::build_with_variant(&tkn.token);
-			match candidate {
-				Ok(t) => {
+			let candidate = match &tkn.account {
+				Some(account) => Builder::build_for_account(&tkn.token, account),
+				None => Builder::build_any(&tkn.token),
+			};
+			let candidate = match (candidate, &registry) {
+				(Ok(t), Some(registry)) => registry.check(&t).map(|_| t),
+				(candidate, _) => candidate,
+			};
+			let candidate = candidate.and_then(|t| load_revocations(&config).check(&t).map(|_| t));
+			match (candidate, format) {
+				(Ok(t), OutputFormat::Text) => {
 					println!("{}", t.format_string(""));
 					print_token(t);
 				}
-				Err(e) => println!("No valid token found:\n{:?}", e),
+				(Ok(t), OutputFormat::Json) => println!("{}", token_to_json(&t)),
+				(Err(e), format) => {
+					let msg = match &e {
+						Error::UnknownRegistrar { network, index } => unknown_registrar(*network, *index),
+						e => e.report(),
+					};
+					match (format, e.class()) {
+						(OutputFormat::Text, ErrorClass::Format | ErrorClass::Checksum) => {
+							println!("No valid token found: {}", msg);
+							println!("Run `rt explain {}` for details", tkn.token);
+						}
+						(OutputFormat::Text, _) => println!("{}", msg),
+						(OutputFormat::Json, _) => {
+							println!("{}", json!({ "valid": false, "message": msg, "code": e.code() }))
+						}
+					}
+					process::exit(exit_code(&e))
+				}
+			}
+		}
+		SubCommand::Convert(c) => {
+			let converted = match &c.account {
+				Some(account) => {
+					Builder::build_for_account(&c.token, account).and_then(|t| t.convert_to_for_account(c.to, account))
+				}
+				None => Builder::build_any(&c.token).and_then(|t| t.convert_to(c.to)),
+			};
+			match (converted, format) {
+				(Ok(t), OutputFormat::Text) => println!("{}", t),
+				(Ok(t), OutputFormat::Json) => println!("{}", token_to_json(&t)),
+				(Err(e @ Error::AccountRequired), _) => {
+					fail_with(&e, "V02 tokens are bound to an account, use --account")
+				}
+				(Err(e), _) => fail_with(&e, &format!("Cannot convert the token: {}", e.report())),
+			}
+		}
+		SubCommand::Verify(v) => {
+			let revocations = load_revocations(&config);
+			let res = match (&v.account, &v.cases) {
+				(_, Some(path)) => {
+					let cases = load_cases(path).unwrap_or_else(|e| fail(&e));
+					Verifier::new(&cases).with_revocations(&revocations).verify(&v.token, &v.signer)
+				}
+				(Some(account), None) => {
+					Verifier::new(&AnyCase(*account)).with_revocations(&revocations).verify(&v.token, &v.signer)
+				}
+				(None, None) => unreachable!("clap requires --account or --cases"),
+			};
+			let res = match (res, &registry) {
+				(Ok(t), Some(registry)) => registry.check(&t).map(|_| t),
+				(res, _) => res,
+			};
+			let msg = match &res {
+				Ok(t) => format!("{} was remarked by the account of case {}", t, t.case_id()),
+				Err(Error::UnknownRegistrar { network, index }) => unknown_registrar(*network, *index),
+				Err(Error::ChecksumError(_)) if Detector::version(&v.token) == Some(Version::V02) => {
+					String::from("The token was not issued for this signer")
+				}
+				Err(e) if matches!(e.class(), ErrorClass::Format | ErrorClass::Checksum) => {
+					format!("No valid token found: {}", e.report())
+				}
+				Err(e) => e.report(),
+			};
+			let code = res.as_ref().err().map(Error::code);
+			match format {
+				OutputFormat::Text => println!("{}", msg),
+				OutputFormat::Json => println!("{}", json!({ "verified": res.is_ok(), "message": msg, "code": code })),
+			}
+			if let Err(e) = res {
+				process::exit(exit_code(&e))
+			}
+		}
+		SubCommand::Explain(e) => {
+			let explanation = Explainer::explain_for_account(&e.token, e.account.as_ref());
+			match format {
+				OutputFormat::Text => print_explanation(&explanation),
+				OutputFormat::Json => println!("{}", explanation_to_json(&explanation)),
+			}
+		}
+		SubCommand::Revoke(r) => {
+			let path = config.revocations.value.clone().unwrap_or_else(|| {
+				fail("No revocation list, set revocations in the config or RRT_REVOCATIONS, see `rt config show`")
+			});
+			let revocation = match (&r.token, r.case, &r.channel) {
+				(Some(token), _, _) => {
+					let token = match &r.account {
+						Some(account) => Builder::build_for_account(token, account),
+						None => Builder::build_any(token),
+					};
+					match token {
+						Ok(t) => Revocation::Token(t.format_string("")),
+						Err(e @ Error::AccountRequired) => {
+							fail_with(&e, "V02 tokens are bound to an account, use --account")
+						}
+						Err(e) => fail_with(&e, &format!("No valid token found: {}", e.report())),
+					}
+				}
+				(None, Some(case_id), channel) => {
+					// Case ids are only unique to a registrar
+					let network = r.network.or(config.network.value).unwrap_or_else(|| {
+						fail("No network given, use --network or set a default network in the config, see `rt config show`")
+					});
+					let index = r.index.unwrap_or(config.index.value);
+					match channel.as_deref() {
+						None => Revocation::Case { network, index, case_id },
+						Some(name) => Revocation::CaseChannel { network, index, case_id, channel: parse_channel(name) },
+					}
+				}
+				(None, None, _) => unreachable!("clap requires a token or --case"),
+			};
+
+			let mut list = load_revocations(&config);
+			if list.revoke(revocation.clone(), r.reason, now()) {
+				list.save(&path).unwrap_or_else(|e| fail_with(&e, &e.to_string()));
+				println!("Revoked {}", revocation);
+			} else {
+				println!("{} was already revoked", revocation);
+			}
+		}
+		SubCommand::Revoked(r) => {
+			let mut list = load_revocations(&config);
+			match (r.export, r.import) {
+				(Some(export), _) => {
+					let key = load_key(&r.key.expect("clap requires --key with --export"));
+					fs::write(&export, list.sign(&key))
+						.unwrap_or_else(|e| fail(&format!("Cannot write {}: {}", export.display(), e)));
+					println!("{} revocation(s) signed by {}", list.entries().len(), key.public());
+				}
+				(None, Some(import)) => {
+					let path = config.revocations.value.clone().unwrap_or_else(|| {
+						fail("No revocation list, set revocations in the config or RRT_REVOCATIONS, see `rt config show`")
+					});
+					let content = fs::read_to_string(&import)
+						.unwrap_or_else(|e| fail(&format!("Cannot read {}: {}", import.display(), e)));
+					let imported = match RevocationList::from_signed(&content, &r.signer) {
+						Ok(imported) => imported,
+						Err(e @ Error::InvalidSignature) => fail_with(&e, "The list is not signed by a trusted signer"),
+						Err(e) => fail_with(&e, &format!("Invalid {}: {}", import.display(), e)),
+					};
+					let added = list.merge(imported);
+					list.save(&path).unwrap_or_else(|e| fail_with(&e, &e.to_string()));
+					println!("{} new revocation(s)", added);
+				}
+				(None, None) => match format {
+					OutputFormat::Text => {
+						for entry in list.entries() {
+							println!(
+								"{}{}",
+								entry.revocation,
+								entry.reason.as_ref().map(|r| format!(": {}", r)).unwrap_or_default()
+							);
+						}
+					}
+					OutputFormat::Json => println!("{}", list.to_json()),
+				},
+			}
+		}
+		SubCommand::Keygen(k) => {
+			let key = Keypair::generate(k.scheme);
+			write_secret(&k.file, &key.to_secret_string())
+				.unwrap_or_else(|e| fail(&format!("Cannot write {}: {}", k.file.display(), e)));
+			println!("{}", key.public());
+		}
+		SubCommand::CheckSignature(c) => {
+			let signed = match &c.account {
+				Some(account) => SignedToken::parse_for_account(&c.token, account),
+				None => SignedToken::parse(&c.token),
 			}
+			.unwrap_or_else(|e| fail_with(&e, &format!("No valid signed token found: {}", e.report())));
+			let res = match (&c.signer, &registry) {
+				(Some(signer), _) => signed.verify(signer).map(|_| *signer),
+				(None, Some(registry)) => signed.verify_with_registry(registry).map(|r| r.account),
+				(None, None) => fail("No signer given, use --signer or a registry"),
+			};
+			let msg = match &res {
+				Ok(signer) => format!("{} was signed by {}", signed.token, signer),
+				Err(Error::UnknownRegistrar { network, index }) => unknown_registrar(*network, *index),
+				Err(Error::InvalidSignature) => String::from("The token was not signed by its registrar"),
+				Err(e) => format!("Invalid signature: {}", e.report()),
+			};
+			let code = res.as_ref().err().map(Error::code);
+			match format {
+				OutputFormat::Text => println!("{}", msg),
+				OutputFormat::Json => println!("{}", json!({ "verified": res.is_ok(), "message": msg, "code": code })),
+			}
+			if let Err(e) = res {
+				process::exit(exit_code(&e))
+			}
+		}
+		SubCommand::Scan(scan) => {
+			let mut matches = Vec::new();
+			if scan.paths.is_empty() {
+				matches.extend(scan::scan_blocks(io::stdin().lock()).unwrap_or_else(|e| fail(&e)));
+			}
+			for path in &scan.paths {
+				matches.extend(scan::scan_path(path).unwrap_or_else(|e| fail(&e)));
+			}
+
+			let token = scan.token.as_ref().map(|t| {
+				let token = match &scan.signer {
+					Some(signer) => Builder::build_for_account(t, signer),
+					None => Builder::build_any(t),
+				};
+				match token {
+					Ok(token) => token.format_string(""),
+					Err(e @ Error::AccountRequired) => {
+						fail_with(&e, "V02 tokens are bound to an account, use --signer")
+					}
+					Err(e) => fail_with(&e, &format!("No valid token found: {}", e.report())),
+				}
+			});
+			let matches = matches.iter().filter(|m| {
+				scan.signer
+					.map(|s| m.remark.signer.as_deref().and_then(|r| r.parse::<AccountId>().ok()) == Some(s))
+					.unwrap_or(true)
+					&& token.as_ref().map(|t| &m.token == t).unwrap_or(true)
+			});
+			for m in matches {
+				match format {
+					OutputFormat::Text => println!(
+						"{} block {} extrinsic {} {} by {}",
+						m.token,
+						m.remark.block,
+						m.remark.extrinsic,
+						m.remark.call,
+						m.remark.signer.as_deref().unwrap_or("unsigned")
+					),
+					OutputFormat::Json => println!("{}", m.to_json()),
+				}
+			}
+		}
+		SubCommand::BenchChecksum(b) => {
+			let seed = b.seed.unwrap_or_else(|| OsRng.gen());
+			let mut rng = StdRng::seed_from_u64(seed);
+			let mode = if b.exhaustive { analysis::Mode::Exhaustive } else { analysis::Mode::Random };
+			let samples = analysis::sample_tokens(&mut rng, b.samples);
+			let reports: Vec<_> =
+				analysis::candidates().iter().map(|c| analysis::analyze(c, &samples, mode, &mut rng)).collect();
+
+			match format {
+				OutputFormat::Text => {
+					println!("{}", analysis::report_table(&reports));
+					println!("seed: {}", seed);
+				}
+				OutputFormat::Json => {
+					let reports: Vec<_> = reports
+						.iter()
+						.map(|r| {
+							let rates: serde_json::Map<_, _> = r
+								.rates
+								.iter()
+								.map(|(m, rate)| {
+									(m.to_string(), json!({ "detected": rate.detected, "total": rate.total }))
+								})
+								.collect();
+							json!({ "checksum": r.name, "samples": r.samples, "chi_square": r.chi_square, "rates": rates })
+						})
+						.collect();
+					println!("{}", json!({ "seed": seed, "reports": reports }));
+				}
+			}
+		}
+		SubCommand::Completions(c) => clap_complete::generate(c.shell, &mut Opts::command(), "rt", &mut io::stdout()),
+		SubCommand::Man => {
+			clap_mangen::Man::new(Opts::command()).render(&mut io::stdout()).unwrap_or_else(|e| fail(&e.to_string()))
 		}
+		SubCommand::Config(ConfigCommand::Show) => print!("{}", config),
 	}
 }
@@ -0,0 +1,366 @@
+use crate::config::OutputFormat;
+use crate::qr::{QrContent, QrFormat};
+use clap::{Parser, Subcommand};
+use clap_complete::Shell;
+use librrt::signing::Scheme;
+use librrt::{AccountId, Alphabet, CallIndex, Version};
+use std::convert::TryFrom;
+use std::path::PathBuf;
+
+/// Generate and check Registrar Remark Tokens.
+///
+/// Defaults for most options can be set in ~/.config/rrt/config.toml or
+/// using RRT_* environment variables. See `rt config show`.
+#[derive(Parser)]
+#[clap(name = "rt", version, author)]
+pub struct Opts {
+	/// Output format [default: text]
+	#[clap(long, global = true, value_enum)]
+	pub format: Option<OutputFormat>,
+
+	/// A JSON file listing our registrars. Tokens for other registrars are rejected.
+	#[clap(long, global = true)]
+	pub registry: Option<PathBuf>,
+
+	#[clap(subcommand)]
+	pub subcmd: SubCommand,
+}
+
+fn parse_account(s: &str) -> Result<AccountId, String> {
+	s.parse().map_err(|_| format!("{:?} is neither an SS58 address nor a 0x prefixed public key", s))
+}
+
+fn parse_version(s: &str) -> Result<Version, String> {
+	s.parse::<u8>().map_err(|e| e.to_string()).and_then(|v| Version::try_from(v).map_err(|e| e.to_string()))
+}
+
+#[derive(Subcommand)]
+pub enum SubCommand {
+	/// Generate a new token
+	#[clap(author = "Chevdor <chevdor@gmail.com>")]
+	New(New),
+
+	/// Check an existing token
+	#[clap(author = "Chevdor <chevdor@gmail.com>")]
+	Check(Check),
+
+	/// Re-issue a token in another version, keeping its case and its secret
+	#[clap(author = "Chevdor <chevdor@gmail.com>")]
+	Convert(Convert),
+
+	/// Verify that a token was remarked by the account of its case
+	#[clap(author = "Chevdor <chevdor@gmail.com>")]
+	Verify(Verify),
+
+	/// Explain, field by field, what is wrong with a token
+	#[clap(author = "Chevdor <chevdor@gmail.com>")]
+	Explain(Explain),
+
+	/// Revoke a token whose secret leaked, or all the tokens of a case
+	#[clap(author = "Chevdor <chevdor@gmail.com>")]
+	Revoke(Revoke),
+
+	/// List, export or import the revoked tokens
+	#[clap(author = "Chevdor <chevdor@gmail.com>")]
+	Revoked(Revoked),
+
+	/// Generate a key to sign tokens or revocation lists
+	#[clap(author = "Chevdor <chevdor@gmail.com>")]
+	Keygen(Keygen),
+
+	/// Check that a signed token was signed by its registrar
+	#[clap(author = "Chevdor <chevdor@gmail.com>")]
+	CheckSignature(CheckSignature),
+
+	/// Find the tokens remarked in exported blocks
+	#[clap(author = "Chevdor <chevdor@gmail.com>")]
+	Scan(Scan),
+
+	/// Measure how well the checksums detect typing mistakes
+	#[clap(author = "Chevdor <chevdor@gmail.com>")]
+	BenchChecksum(BenchChecksum),
+
+	/// Generate the completion script for your shell
+	Completions(Completions),
+
+	/// Generate the man page
+	Man,
+
+	/// Show the configuration
+	#[clap(subcommand)]
+	Config(ConfigCommand),
+}
+
+/// A subcommand for generating new tokens
+#[derive(Parser, Debug)]
+pub struct New {
+	/// The application [default: 0]
+	#[clap(long)]
+	pub app: Option<u8>,
+
+	/// The version of the App [default: 1]
+	#[clap(long)]
+	pub version: Option<u8>,
+
+	/// The Network. 0: Polkadot, 2: Kusama, 42: Westend
+	#[clap(long)]
+	pub network: Option<u8>,
+
+	/// Registrar index 0..255 [default: 1]
+	#[clap(long)]
+	pub index: Option<u8>,
+
+	/// The name of one of our registrars, in place of --index. Requires a registry.
+	#[clap(long, conflicts_with = "index")]
+	pub registrar: Option<String>,
+
+	/// The case ID of our process
+	#[clap(long)]
+	pub id: u64,
+
+	/// The channel: TW | EM | MX
+	#[clap(long)]
+	pub channel: String,
+
+	/// The account the token is issued for, as SS58 or hex. Required for V02 tokens.
+	#[clap(long, value_parser = parse_account)]
+	pub account: Option<AccountId>,
+
+	/// Separator to insert between the fields of the token [default: none]
+	#[clap(long)]
+	pub separator: Option<String>,
+
+	/// The alphabet of the secret: full, no-confusables or crockford [default: the one of the version].
+	/// It must be part of the alphabet of the version: no-confusables or full for V00 to V04 except V03, crockford for V03.
+	#[clap(long)]
+	pub alphabet: Option<Alphabet>,
+
+	/// The length of the secret of V03 tokens, from 8 to 15 [default: 12]
+	#[clap(long, value_parser = clap::value_parser!(u8).range(8..=15))]
+	pub secret_length: Option<u8>,
+
+	/// Sign the token with the key of the registrar in this file, see `rt keygen`
+	#[clap(long)]
+	pub sign: Option<PathBuf>,
+
+	/// Also output the token as a QR code
+	#[clap(long, value_enum)]
+	pub qr: Option<QrFormat>,
+
+	/// What the QR code contains
+	#[clap(long, value_enum, default_value = "token", requires = "qr")]
+	pub qr_content: QrContent,
+
+	/// The file to write the QR code to. Required for png, svg goes to stdout otherwise.
+	#[clap(long, requires = "qr", required_if_eq("qr", "png"))]
+	pub qr_output: Option<PathBuf>,
+
+	/// Also output the `rrt:` URI of the token
+	#[clap(long)]
+	pub uri: bool,
+
+	/// Also output the token in its envelope such as `rrt1-ksm-...`
+	#[clap(long)]
+	pub envelope: bool,
+
+	/// Also output the hex encoded `system.remark` call carrying the token
+	#[clap(long)]
+	pub call_data: bool,
+
+	/// Use `system.remarkWithEvent` instead of `system.remark`
+	#[clap(long, requires = "call-data")]
+	pub with_event: bool,
+
+	/// The index of the call as pallet:call, if it differs from the default of the network
+	#[clap(long, requires = "call-data")]
+	pub call_index: Option<CallIndex>,
+
+	/// Also output a Polkadot.js Apps link to the decoded call
+	#[clap(long, requires = "call-data")]
+	pub deep_link: bool,
+
+	/// Also output the instructions for the user
+	#[clap(long)]
+	pub instructions: bool,
+
+	/// The language of the instructions: en, fr, de or zh
+	#[clap(long, default_value = "en", requires = "instructions")]
+	pub lang: String,
+}
+
+#[derive(Parser, Debug)]
+pub struct Check {
+	/// The token to check. It may also be given in its envelope such as `rrt1-ksm-...`.
+	#[clap(index = 1)]
+	pub token: String,
+
+	/// The account the token was issued for, as SS58 or hex. Required for V02 tokens.
+	#[clap(long, value_parser = parse_account)]
+	pub account: Option<AccountId>,
+}
+
+#[derive(Parser, Debug)]
+pub struct Convert {
+	/// The token to convert
+	#[clap(index = 1)]
+	pub token: String,
+
+	/// The version to convert the token to
+	#[clap(long, value_parser = parse_version)]
+	pub to: Version,
+
+	/// The account the token was or will be issued for, as SS58 or hex. Required for V02 tokens.
+	#[clap(long, value_parser = parse_account)]
+	pub account: Option<AccountId>,
+}
+
+#[derive(Parser, Debug)]
+pub struct Verify {
+	/// The token that was remarked. It may contain separators or be given in its envelope.
+	#[clap(index = 1)]
+	pub token: String,
+
+	/// The account that signed the remark, as SS58 or hex
+	#[clap(long, value_parser = parse_account)]
+	pub signer: AccountId,
+
+	/// The account associated with the case of the token
+	#[clap(long, value_parser = parse_account, required_unless_present = "cases", conflicts_with = "cases")]
+	pub account: Option<AccountId>,
+
+	/// A JSON list of cases, such as [{"network": 2, "index": 1, "case_id": 12345, "account": "HNZata7..."}].
+	/// The store of rrt-server may be given as is.
+	#[clap(long)]
+	pub cases: Option<PathBuf>,
+}
+
+#[derive(Parser, Debug)]
+pub struct Explain {
+	/// The token to explain. It may contain separators.
+	#[clap(index = 1)]
+	pub token: String,
+
+	/// The account the token was issued for, as SS58 or hex. Needed to check the checksum of V02 tokens.
+	#[clap(long, value_parser = parse_account)]
+	pub account: Option<AccountId>,
+}
+
+#[derive(Parser, Debug)]
+pub struct Revoke {
+	/// The token to revoke. It may contain separators or be enveloped.
+	#[clap(index = 1, required_unless_present = "case", conflicts_with = "case")]
+	pub token: Option<String>,
+
+	/// The account the token was issued for, as SS58 or hex. Required for V02 tokens.
+	#[clap(long, value_parser = parse_account, conflicts_with = "case")]
+	pub account: Option<AccountId>,
+
+	/// Revoke all the tokens of this case
+	#[clap(long)]
+	pub case: Option<u64>,
+
+	/// Only revoke the tokens of the case sent on this channel: TW | EM | MX
+	#[clap(long, requires = "case")]
+	pub channel: Option<String>,
+
+	/// The network of the case. 0: Polkadot, 2: Kusama, 42: Westend
+	#[clap(long, requires = "case")]
+	pub network: Option<u8>,
+
+	/// The registrar index of the case [default: 1]
+	#[clap(long, requires = "case")]
+	pub index: Option<u8>,
+
+	/// Why the token is revoked
+	#[clap(long)]
+	pub reason: Option<String>,
+}
+
+#[derive(Parser, Debug)]
+pub struct Revoked {
+	/// Write the list, signed with --key, to this file
+	#[clap(long, requires = "key", conflicts_with = "import")]
+	pub export: Option<PathBuf>,
+
+	/// The file holding the signing key, see `rt keygen`
+	#[clap(long)]
+	pub key: Option<PathBuf>,
+
+	/// Add the revocations of a list exported by another instance
+	#[clap(long, requires = "signer")]
+	pub import: Option<PathBuf>,
+
+	/// The public key trusted to sign the imported lists, as SS58 or hex. May be repeated.
+	#[clap(long, value_parser = parse_account)]
+	pub signer: Vec<AccountId>,
+}
+
+#[derive(Parser, Debug)]
+pub struct Keygen {
+	/// The file to write the key to. It must not exist.
+	#[clap(index = 1)]
+	pub file: PathBuf,
+
+	/// The signature scheme: ed25519 or sr25519
+	#[clap(long, default_value = "ed25519")]
+	pub scheme: Scheme,
+}
+
+#[derive(Parser, Debug)]
+pub struct CheckSignature {
+	/// The signed token, as output by `rt new --sign`
+	#[clap(index = 1)]
+	pub token: String,
+
+	/// The public key of the registrar, as SS58 or hex. Defaults to the account of the registrar in the registry.
+	#[clap(long, value_parser = parse_account)]
+	pub signer: Option<AccountId>,
+
+	/// The account the token was issued for, as SS58 or hex. Required for V02 tokens.
+	#[clap(long, value_parser = parse_account)]
+	pub account: Option<AccountId>,
+}
+
+/// Reads blocks in the JSON format of substrate-api-sidecar
+#[derive(Parser, Debug)]
+pub struct Scan {
+	/// Files or directories of `.json` files containing blocks. Reads stdin if none is given.
+	#[clap(index = 1)]
+	pub paths: Vec<PathBuf>,
+
+	/// Only show the tokens remarked by this account, as SS58 on any network or hex
+	#[clap(long, value_parser = parse_account)]
+	pub signer: Option<AccountId>,
+
+	/// Only show this token, in any form `rt check` accepts. V02 tokens need --signer.
+	#[clap(long)]
+	pub token: Option<String>,
+}
+
+#[derive(Parser, Debug)]
+pub struct BenchChecksum {
+	/// The number of random tokens to alter
+	#[clap(long, default_value = "10000")]
+	pub samples: usize,
+
+	/// Try every possible mistake of each token instead of a random one
+	#[clap(long)]
+	pub exhaustive: bool,
+
+	/// The seed of the random tokens, to reproduce a report
+	#[clap(long)]
+	pub seed: Option<u64>,
+}
+
+#[derive(Parser, Debug)]
+pub struct Completions {
+	/// The shell to generate the completions for
+	#[clap(index = 1, value_enum)]
+	pub shell: Shell,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum ConfigCommand {
+	/// Print the effective configuration and where each value comes from
+	Show,
+}
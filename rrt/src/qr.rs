@@ -0,0 +1,98 @@
+//! Rendering of tokens as QR codes so they can be scanned from a phone.
+
+use qrcode::render::{svg, unicode};
+use qrcode::{Color, QrCode};
+use std::io::Write;
+
+/// Size of a module, in pixels, in the PNG output
+const PNG_SCALE: usize = 8;
+
+/// Width of the blank border around the code, in modules
+const QUIET_ZONE: usize = 4;
+
+#[derive(Debug, PartialEq, Clone, Copy, clap::ValueEnum)]
+pub enum QrFormat {
+	/// Unicode blocks printed in the terminal
+	Term,
+	Svg,
+	Png,
+}
+
+/// What the QR code contains
+#[derive(Debug, PartialEq, Clone, Copy, clap::ValueEnum)]
+pub enum QrContent {
+	/// The token alone, ready to be pasted into a remark
+	Token,
+	/// The `rrt:` URI of the token
+	Uri,
+	/// The token in its envelope such as `rrt1-ksm-...`
+	Envelope,
+}
+
+fn encode(data: &str) -> Result<QrCode, String> {
+	QrCode::new(data.as_bytes()).map_err(|e| format!("Cannot encode the QR code: {}", e))
+}
+
+/// Renders the QR code as unicode blocks, 2 modules per character.
+/// Colors are swapped so the code reads correctly on the usual light on dark terminals.
+pub fn to_term(data: &str) -> Result<String, String> {
+	Ok(encode(data)?
+		.render::<unicode::Dense1x2>()
+		.dark_color(unicode::Dense1x2::Light)
+		.light_color(unicode::Dense1x2::Dark)
+		.build())
+}
+
+pub fn to_svg(data: &str) -> Result<String, String> {
+	Ok(encode(data)?.render::<svg::Color>().min_dimensions(200, 200).build())
+}
+
+/// Renders the QR code as a grayscale PNG image
+pub fn to_png(data: &str, out: impl Write) -> Result<(), String> {
+	let code = encode(data)?;
+	let modules = code.width();
+	let colors = code.to_colors();
+	let size = (modules + 2 * QUIET_ZONE) * PNG_SCALE;
+
+	let mut pixels = vec![0xffu8; size * size];
+	for (i, color) in colors.iter().enumerate() {
+		if *color == Color::Light {
+			continue;
+		}
+		let (x, y) = ((i % modules + QUIET_ZONE) * PNG_SCALE, (i / modules + QUIET_ZONE) * PNG_SCALE);
+		for row in y..y + PNG_SCALE {
+			pixels[row * size + x..row * size + x + PNG_SCALE].iter_mut().for_each(|p| *p = 0);
+		}
+	}
+
+	let mut encoder = png::Encoder::new(out, size as u32, size as u32);
+	encoder.set_color(png::ColorType::Grayscale);
+	encoder.set_depth(png::BitDepth::Eight);
+	let err = |e: png::EncodingError| format!("Cannot write the PNG: {}", e);
+	encoder.write_header().map_err(err)?.write_image_data(&pixels).map_err(err)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	const TOKEN: &str = "0001020103039TWJXBACTSPAQ";
+
+	#[test]
+	fn it_renders_svg() {
+		let svg = to_svg(TOKEN).unwrap();
+		assert!(svg.contains("<svg"));
+	}
+
+	#[test]
+	fn it_renders_png() {
+		let mut buf = Vec::new();
+		to_png(TOKEN, &mut buf).unwrap();
+		assert_eq!(b"\x89PNG", &buf[..4]);
+
+		let decoder = png::Decoder::new(buf.as_slice());
+		let reader = decoder.read_info().unwrap();
+		let modules = encode(TOKEN).unwrap().width();
+		assert_eq!(((modules + 2 * QUIET_ZONE) * PNG_SCALE) as u32, reader.info().width);
+	}
+}
@@ -0,0 +1,286 @@
+//! Extraction of the remarks from exported blocks.
+//!
+//! Blocks are expected in the JSON format of substrate-api-sidecar's `/blocks/{n}` endpoint.
+//! A file or stream may contain a single block, an array of blocks or one block per line.
+
+use librrt::{AccountId, Scanner, Tokenize};
+use serde_json::{json, Value};
+use std::fs;
+use std::io::Read;
+use std::path::Path;
+
+/// A `system.remark` found in a block
+#[derive(Debug, PartialEq, Clone)]
+pub struct Remark {
+	pub block: u64,
+	pub extrinsic: usize,
+	pub signer: Option<String>,
+	/// The name of the call such as `system.remark`
+	pub call: String,
+	pub content: String,
+}
+
+/// A token found in a remark
+#[derive(Debug, PartialEq)]
+pub struct Match {
+	pub token: String,
+	pub remark: Remark,
+}
+
+impl Match {
+	pub fn to_json(&self) -> Value {
+		json!({
+			"token": self.token,
+			"block": self.remark.block,
+			"extrinsic": self.remark.extrinsic,
+			"signer": self.remark.signer,
+			"call": self.remark.call,
+		})
+	}
+}
+
+/// Decodes the `0x` prefixed hex remarks, other remarks are returned as is
+fn decode_remark(remark: &str) -> String {
+	let hex = match remark.strip_prefix("0x") {
+		// Slicing by 2 bytes is only safe on ASCII
+		Some(hex) if hex.len() % 2 == 0 && hex.is_ascii() => hex,
+		_ => return String::from(remark),
+	};
+	let bytes: Result<Vec<u8>, _> = (0..hex.len()).step_by(2).map(|i| u8::from_str_radix(&hex[i..i + 2], 16)).collect();
+	match bytes {
+		Ok(bytes) => String::from_utf8_lossy(&bytes).into_owned(),
+		Err(_) => String::from(remark),
+	}
+}
+
+/// Returns the account of a signer or of a proxied call. Depending on its version, sidecar may or may not wrap it
+/// in an `id`.
+fn account(value: &Value) -> Option<String> {
+	value["id"].as_str().or_else(|| value.as_str()).map(String::from)
+}
+
+/// Collects the remarks of a call and of the calls it wraps, such as in `utility.batch` or `proxy.proxy`.
+/// The remarks of a proxied call are credited to the `real` account the proxy acts for.
+fn collect_remarks(call: &Value, signer: &Option<String>, remarks: &mut Vec<(String, String, Option<String>)>) {
+	let pallet = call["method"]["pallet"].as_str().unwrap_or_default();
+	let method = call["method"]["method"].as_str().unwrap_or_default();
+
+	if pallet == "system" && (method == "remark" || method == "remarkWithEvent") {
+		if let Some(remark) = call["args"]["remark"].as_str() {
+			remarks.push((format!("{}.{}", pallet, method), decode_remark(remark), signer.clone()));
+		}
+	}
+
+	if let Some(calls) = call["args"]["calls"].as_array() {
+		calls.iter().for_each(|c| collect_remarks(c, signer, remarks));
+	}
+	if call["args"]["call"].is_object() {
+		let signer = match account(&call["args"]["real"]) {
+			Some(real) => Some(real),
+			None => signer.clone(),
+		};
+		collect_remarks(&call["args"]["call"], &signer, remarks);
+	}
+}
+
+/// Returns all the remarks of a block. The extrinsics that failed, and so were reverted, are skipped.
+pub fn remarks(block: &Value) -> Vec<Remark> {
+	let number = match &block["number"] {
+		Value::String(s) => s.parse().unwrap_or_default(),
+		n => n.as_u64().unwrap_or_default(),
+	};
+
+	let mut res = Vec::new();
+	for (index, extrinsic) in block["extrinsics"].as_array().into_iter().flatten().enumerate() {
+		if extrinsic["success"] == Value::Bool(false) {
+			continue;
+		}
+		let mut remarks = Vec::new();
+		collect_remarks(extrinsic, &account(&extrinsic["signature"]["signer"]), &mut remarks);
+		res.extend(remarks.into_iter().map(|(call, content, signer)| Remark {
+			block: number,
+			extrinsic: index,
+			signer,
+			call,
+			content,
+		}));
+	}
+	res
+}
+
+/// Returns the tokens found in the given blocks
+pub fn scan_blocks(reader: impl Read) -> Result<Vec<Match>, String> {
+	let mut matches = Vec::new();
+	for value in serde_json::Deserializer::from_reader(reader).into_iter::<Value>() {
+		let value = value.map_err(|e| format!("Invalid block: {}", e))?;
+		let blocks = match value {
+			Value::Array(blocks) => blocks,
+			block => vec![block],
+		};
+		for remark in blocks.iter().flat_map(remarks) {
+			// The V02 tokens are checked against the signer of the remark
+			let found = match remark.signer.as_deref().and_then(|s| s.parse::<AccountId>().ok()) {
+				Some(signer) => Scanner::scan_for_account(&remark.content, &signer),
+				None => Scanner::scan(&remark.content),
+			};
+			for found in found {
+				matches.push(Match { token: found.token.format_string(""), remark: remark.clone() });
+			}
+		}
+	}
+	Ok(matches)
+}
+
+/// Scans a file, or all the `.json` files of a directory, sorted by name
+pub fn scan_path(path: &Path) -> Result<Vec<Match>, String> {
+	let open = |p: &Path| fs::File::open(p).map_err(|e| format!("Cannot open {}: {}", p.display(), e));
+
+	if !path.is_dir() {
+		return scan_blocks(std::io::BufReader::new(open(path)?));
+	}
+
+	let mut files: Vec<_> = fs::read_dir(path)
+		.map_err(|e| format!("Cannot read {}: {}", path.display(), e))?
+		.filter_map(|e| e.ok().map(|e| e.path()))
+		.filter(|p| p.extension().map(|e| e == "json").unwrap_or(false))
+		.collect();
+	files.sort();
+
+	let mut matches = Vec::new();
+	for file in files {
+		matches.extend(scan_blocks(std::io::BufReader::new(open(&file)?))?);
+	}
+	Ok(matches)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	// "Hi 0001020103039TWJXBACTSPAQ" in hex
+	const REMARK: &str = "0x4869203030303130323031303330333954574a58424143545350415120";
+
+	fn block() -> String {
+		format!(
+			r#"{{
+			"number": "1234",
+			"extrinsics": [
+				{{ "method": {{ "pallet": "timestamp", "method": "set" }}, "signature": null, "args": {{ "now": "1" }} }},
+				{{
+					"method": {{ "pallet": "system", "method": "remark" }},
+					"signature": {{ "signer": {{ "id": "HNZata7iMYWmk5RvZRTiAsSDhV8366zq2YGb3tLH5Upf74F" }} }},
+					"args": {{ "remark": "{}" }}
+				}},
+				{{
+					"method": {{ "pallet": "utility", "method": "batchAll" }},
+					"signature": {{ "signer": "FvMK4n6ZoAnLNBf9Vy1XHmTA3mGfsBTeWHr1LBkPRsEqvKm" }},
+					"args": {{ "calls": [
+						{{ "method": {{ "pallet": "system", "method": "remarkWithEvent" }}, "args": {{ "remark": "0000000012345TWRAJQFIZWW" }} }}
+					] }}
+				}}
+			]
+		}}"#,
+			REMARK
+		)
+	}
+
+	#[test]
+	fn it_decodes_remarks() {
+		assert_eq!("Hi 0001020103039TWJXBACTSPAQ ", decode_remark(REMARK));
+		assert_eq!("0xZZ", decode_remark("0xZZ"));
+		assert_eq!("0xaé1", decode_remark("0xaé1"));
+		assert_eq!("plain", decode_remark("plain"));
+	}
+
+	#[test]
+	fn it_finds_remarks_in_batches() {
+		let block: Value = serde_json::from_str(&block()).unwrap();
+		let remarks = remarks(&block);
+		assert_eq!(2, remarks.len());
+		assert_eq!(1234, remarks[0].block);
+		assert_eq!(1, remarks[0].extrinsic);
+		assert_eq!("system.remarkWithEvent", remarks[1].call);
+		assert_eq!(Some("FvMK4n6ZoAnLNBf9Vy1XHmTA3mGfsBTeWHr1LBkPRsEqvKm".into()), remarks[1].signer);
+	}
+
+	#[test]
+	fn it_skips_failed_extrinsics() {
+		let block = json!({
+			"number": 5,
+			"extrinsics": [
+				{
+					"method": { "pallet": "utility", "method": "batchAll" },
+					"signature": { "signer": { "id": "HNZata7iMYWmk5RvZRTiAsSDhV8366zq2YGb3tLH5Upf74F" } },
+					"args": { "calls": [
+						{ "method": { "pallet": "system", "method": "remark" }, "args": { "remark": "0000000012345TWRAJQFIZWW" } }
+					] },
+					"success": false
+				},
+				{
+					"method": { "pallet": "system", "method": "remark" },
+					"signature": { "signer": { "id": "HNZata7iMYWmk5RvZRTiAsSDhV8366zq2YGb3tLH5Upf74F" } },
+					"args": { "remark": "0001020103039TWJXBACTSPAQ" },
+					"success": true
+				}
+			]
+		});
+		let remarks = remarks(&block);
+		assert_eq!(1, remarks.len());
+		assert_eq!(1, remarks[0].extrinsic);
+	}
+
+	#[test]
+	fn it_credits_proxied_remarks_to_the_real_account() {
+		let block = json!({
+			"number": 6,
+			"extrinsics": [{
+				"method": { "pallet": "proxy", "method": "proxy" },
+				"signature": { "signer": { "id": "FvMK4n6ZoAnLNBf9Vy1XHmTA3mGfsBTeWHr1LBkPRsEqvKm" } },
+				"args": {
+					"real": { "id": "HNZata7iMYWmk5RvZRTiAsSDhV8366zq2YGb3tLH5Upf74F" },
+					"force_proxy_type": null,
+					"call": { "method": { "pallet": "system", "method": "remark" }, "args": { "remark": "0001020103039TWJXBACTSPAQ" } }
+				},
+				"success": true
+			}]
+		});
+		let remarks = remarks(&block);
+		assert_eq!(1, remarks.len());
+		assert_eq!(Some("HNZata7iMYWmk5RvZRTiAsSDhV8366zq2YGb3tLH5Upf74F".into()), remarks[0].signer);
+	}
+
+	#[test]
+	fn it_scans_streams_of_blocks() {
+		let stream = format!("{}\n[{}]", block(), block());
+		let matches = scan_blocks(stream.as_bytes()).unwrap();
+		assert_eq!(4, matches.len());
+		assert_eq!("0001020103039TWJXBACTSPAQ", matches[0].token);
+		assert_eq!(Some("HNZata7iMYWmk5RvZRTiAsSDhV8366zq2YGb3tLH5Upf74F".into()), matches[0].remark.signer);
+		assert_eq!("0000000012345TWRAJQFIZWW", matches[1].token);
+		assert_eq!(2, matches[1].remark.extrinsic);
+
+		assert!(scan_blocks("{ not json".as_bytes()).is_err());
+	}
+
+	#[test]
+	fn it_checks_v02_tokens_against_the_signer() {
+		let signer = "HNZata7iMYWmk5RvZRTiAsSDhV8366zq2YGb3tLH5Upf74F";
+		let token = librrt::TokenV02::new(0, 2, 1, 12345, librrt::Channel::Email, &signer.parse().unwrap()).to_string();
+		let block = |signer: &str| {
+			json!({
+				"number": 77,
+				"extrinsics": [{
+					"method": { "pallet": "system", "method": "remark" },
+					"signature": { "signer": { "id": signer } },
+					"args": { "remark": token }
+				}]
+			})
+			.to_string()
+		};
+
+		let matches = scan_blocks(block(signer).as_bytes()).unwrap();
+		assert_eq!(1, matches.len());
+		assert_eq!(token, matches[0].token);
+		assert!(scan_blocks(block("FvMK4n6ZoAnLNBf9Vy1XHmTA3mGfsBTeWHr1LBkPRsEqvKm").as_bytes()).unwrap().is_empty());
+	}
+}
@@ -0,0 +1,241 @@
+//! Runs the `rt` binary, without any config file
+
+use librrt::{AccountId, Builder, Channel, Token, TokenV02, TokenV03, TokenV04, Tokenize};
+use serde_json::Value;
+use std::process::Command;
+
+/// Runs `rt --format json <args>` and returns its exit code and its JSON output
+fn rt(args: &[&str]) -> (i32, Value) {
+	rt_env(&[], args)
+}
+
+/// Like `rt`, with some more environment variables
+fn rt_env(envs: &[(&str, &str)], args: &[&str]) -> (i32, Value) {
+	let output = Command::new(env!("CARGO_BIN_EXE_rt"))
+		.env("RRT_CONFIG", "/nonexistent/rrt.toml")
+		.envs(envs.iter().copied())
+		.args(["--format", "json"])
+		.args(args)
+		.output()
+		.unwrap();
+	let json = serde_json::from_slice(&output.stdout).unwrap();
+	(output.status.code().unwrap(), json)
+}
+
+#[test]
+fn it_checks_and_explains_separated_tokens_alike() {
+	let (code, checked) = rt(&["check", "00-01-02-01-03039-TW-JXBACTSP-AQ"]);
+	assert_eq!(0, code);
+	assert_eq!("0001020103039TWJXBACTSPAQ", checked["token"]);
+	let (_, explained) = rt(&["explain", "00-01-02-01-03039-TW-JXBACTSP-AQ"]);
+	assert_eq!(Some(true), explained["valid"].as_bool());
+
+	let (code, checked) = rt(&["check", "00 01 02 01 03039 TW JXBACTSP AA"]);
+	assert_eq!((4, "invalid_checksum"), (code, checked["code"].as_str().unwrap()));
+	let (_, explained) = rt(&["explain", "00 01 02 01 03039 TW JXBACTSP AA"]);
+	assert_eq!(Some(false), explained["valid"].as_bool());
+}
+
+#[test]
+fn it_checks_and_explains_confusables_and_envelopes_alike() {
+	let (code, checked) = rt(&["check", "0001020103039TWJ0BACT1PVR"]);
+	assert_eq!((0, "0001020103039TWJOBACTIPVR"), (code, checked["token"].as_str().unwrap()));
+	let (_, explained) = rt(&["explain", "0001020103039TWJ0BACT1PVR"]);
+	assert_eq!(Some(true), explained["valid"].as_bool());
+	assert!(explained["issues"][0].as_str().unwrap().contains("JOBACTIP"));
+
+	let envelope = Builder::build_with_variant("0001020103039TWJXBACTSPAQ").unwrap().to_envelope();
+	let (code, _) = rt(&["check", &envelope]);
+	assert_eq!(0, code);
+	let (_, explained) = rt(&["explain", &envelope]);
+	assert_eq!(Some(true), explained["valid"].as_bool());
+}
+
+#[test]
+fn it_filters_scanned_tokens_by_any_form_of_the_signer() {
+	let block = r#"{
+		"number": 9,
+		"extrinsics": [{
+			"method": { "pallet": "system", "method": "remark" },
+			"signature": { "signer": { "id": "HNZata7iMYWmk5RvZRTiAsSDhV8366zq2YGb3tLH5Upf74F" } },
+			"args": { "remark": "0001020103039TWJXBACTSPAQ" }
+		}]
+	}"#;
+	let path = std::env::temp_dir().join(format!("rrt-scan-{}.json", std::process::id()));
+	std::fs::write(&path, block).unwrap();
+	let path = path.to_str().unwrap();
+
+	let alice = "0xd43593c715fdd31c61141abd04a99fd6822c8558854ccde39a5684e7a56da27d";
+	let (code, found) = rt(&["scan", path, "--signer", alice]);
+	assert_eq!((0, "0001020103039TWJXBACTSPAQ"), (code, found["token"].as_str().unwrap()));
+	let (code, found) = rt(&["scan", path, "--signer", "5GrwvaEF5zXb26Fz9rcQpDWS57CtERHpNehXCPcNoHGKutQY"]);
+	assert_eq!((0, "0001020103039TWJXBACTSPAQ"), (code, found["token"].as_str().unwrap()));
+
+	let output = Command::new(env!("CARGO_BIN_EXE_rt"))
+		.env("RRT_CONFIG", "/nonexistent/rrt.toml")
+		.args(["scan", path, "--signer", "FvMK4n6ZoAnLNBf9Vy1XHmTA3mGfsBTeWHr1LBkPRsEqvKm"])
+		.output()
+		.unwrap();
+	std::fs::remove_file(path).unwrap();
+	assert!(output.stdout.is_empty());
+}
+
+/// Runs `rt <args>` with the text output and returns it without its colors
+fn rt_text(args: &[&str]) -> String {
+	let output =
+		Command::new(env!("CARGO_BIN_EXE_rt")).env("RRT_CONFIG", "/nonexistent/rrt.toml").args(args).output().unwrap();
+	let mut text = String::new();
+	let mut chars = String::from_utf8(output.stdout).unwrap().chars().collect::<Vec<_>>().into_iter();
+	while let Some(c) = chars.next() {
+		match c {
+			'\x1b' => {
+				chars.by_ref().find(|c| c.is_ascii_alphabetic());
+			}
+			c => text.push(c),
+		}
+	}
+	text
+}
+
+#[test]
+fn it_prints_the_fields_of_v03_and_v04_tokens() {
+	let v03 = TokenV03::new_with_secret(0, 2, 1, 12345, Channel::Twitter, "7M3K9T2XQ4HB");
+	let text = rt_text(&["check", &v03.to_string()]);
+	let lines: Vec<&str> = text.lines().collect();
+	assert_eq!(v03.to_string(), lines[0]);
+	assert_eq!(v03.format_string("-"), lines[1]);
+	assert!(text.contains("secret length: 12 chars"));
+	assert!(text.contains("secret       : 7M3K9T2XQ4HB"));
+
+	let v04 = TokenV04::new_with_secret(0, 2, 1, 1_000_000, Channel::Twitter, "JXBACTSP");
+	let text = rt_text(&["check", &v04.to_string()]);
+	let lines: Vec<&str> = text.lines().collect();
+	assert_eq!(v04.to_string(), lines[0]);
+	assert_eq!(v04.format_string("-"), lines[1]);
+	assert!(text.contains("case Id length: 5 digits"));
+	assert!(text.contains("case Id       : 1000000 (hex: F4240)"));
+}
+
+#[test]
+fn it_tells_a_v02_token_issued_for_another_signer_in_any_form() {
+	let alice = AccountId([1; 32]);
+	let eve = AccountId([6; 32]).to_string();
+	let v02 = TokenV02::new_with_secret(0, 2, 1, 12345, Channel::Twitter, "JXBACTSP", &alice);
+	let (alice, separated, envelope) = (alice.to_string(), v02.format_string("-"), Token::V02(v02).to_envelope());
+
+	for token in &[separated.as_str(), envelope.as_str()] {
+		let (code, verified) = rt(&["verify", token, "--signer", &alice, "--account", &alice]);
+		assert_eq!((0, Some(true)), (code, verified["verified"].as_bool()));
+
+		let (code, verified) = rt(&["verify", token, "--signer", &eve, "--account", &alice]);
+		assert_eq!(4, code);
+		assert_eq!("The token was not issued for this signer", verified["message"]);
+	}
+}
+
+/// Runs `rt revoke <args>` saving to the revocation list at `path` and returns its exit code
+fn revoke(path: &str, args: &[&str]) -> i32 {
+	let output = Command::new(env!("CARGO_BIN_EXE_rt"))
+		.env("RRT_CONFIG", "/nonexistent/rrt.toml")
+		.env("RRT_REVOCATIONS", path)
+		.arg("revoke")
+		.args(args)
+		.output()
+		.unwrap();
+	output.status.code().unwrap()
+}
+
+#[test]
+fn it_revokes_a_token_given_in_any_form() {
+	let v02 = TokenV02::new_with_secret(0, 2, 1, 12345, Channel::Twitter, "JXBACTSP", &AccountId([1; 32]));
+	let (account, v02) = (AccountId([1; 32]).to_string(), Token::V02(v02));
+	let v01 = Builder::build_with_variant("0001020103039TWJXBACTSPAQ").unwrap();
+	let forms = [
+		(v01.format_string(""), "0001-02-01-03039-twjxbactspaq".to_string(), None),
+		(v01.format_string(""), v01.to_envelope().to_lowercase(), None),
+		(v02.format_string(""), v02.to_envelope(), Some(account.as_str())),
+	];
+
+	for (i, (token, form, account)) in forms.iter().enumerate() {
+		let path = format!("{}/revocations-{}.json", env!("CARGO_TARGET_TMPDIR"), i);
+		let _ = std::fs::remove_file(&path);
+		let env = [("RRT_REVOCATIONS", path.as_str())];
+		let mut args = vec![form.as_str()];
+		args.extend(account.iter().flat_map(|a| ["--account", a]));
+		assert_eq!(0, revoke(&path, &args));
+
+		let mut args = vec!["check", token.as_str()];
+		args.extend(account.iter().flat_map(|a| ["--account", a]));
+		let (code, checked) = rt_env(&env, &args);
+		assert_eq!((7, "revoked"), (code, checked["code"].as_str().unwrap()));
+	}
+
+	let path = format!("{}/revocations-invalid.json", env!("CARGO_TARGET_TMPDIR"));
+	assert_eq!(4, revoke(&path, &["0001020103039TWJXBACTSPAA"]));
+	assert!(!std::path::Path::new(&path).exists());
+}
+
+#[test]
+fn it_verifies_against_the_case_of_the_registrar_of_the_token() {
+	let (alice, eve) = (AccountId([1; 32]).to_string(), AccountId([6; 32]).to_string());
+	let path = format!("{}/cases.json", env!("CARGO_TARGET_TMPDIR"));
+	let cases = serde_json::json!([
+		{ "network": 2, "index": 1, "case_id": 12345, "account": alice },
+		{ "network": 2, "index": 2, "case_id": 12345, "account": eve },
+		{ "network": 0, "index": 1, "case_id": 12345, "account": eve },
+	]);
+	std::fs::write(&path, cases.to_string()).unwrap();
+
+	let (code, verified) = rt(&["verify", "0001020103039TWJXBACTSPAQ", "--signer", &alice, "--cases", &path]);
+	assert_eq!((0, Some(true)), (code, verified["verified"].as_bool()));
+	let (code, verified) = rt(&["verify", "0001020103039TWJXBACTSPAQ", "--signer", &eve, "--cases", &path]);
+	assert_eq!((5, "wrong_signer"), (code, verified["code"].as_str().unwrap()));
+}
+
+#[test]
+fn it_refuses_to_generate_a_token_for_an_unknown_channel() {
+	let (code, generated) = rt(&["new", "--id", "7", "--channel", "EM", "--network", "2"]);
+	assert_eq!((0, "EM"), (code, generated["channel"].as_str().unwrap()));
+
+	let output = Command::new(env!("CARGO_BIN_EXE_rt"))
+		.env("RRT_CONFIG", "/nonexistent/rrt.toml")
+		.args(["new", "--id", "7", "--channel", "foo", "--network", "2"])
+		.output()
+		.unwrap();
+	assert_eq!(Some(1), output.status.code());
+	assert!(output.stdout.is_empty());
+	assert!(String::from_utf8_lossy(&output.stderr).contains("Unknown channel \"foo\", expected one of TW, EM, MX"));
+}
+
+#[test]
+fn it_filters_scanned_tokens_by_any_form_of_the_token() {
+	let block = r#"{
+		"number": 9,
+		"extrinsics": [{
+			"method": { "pallet": "system", "method": "remark" },
+			"signature": { "signer": { "id": "HNZata7iMYWmk5RvZRTiAsSDhV8366zq2YGb3tLH5Upf74F" } },
+			"args": { "remark": "0001020103039TWJXBACTSPAQ" }
+		}, {
+			"method": { "pallet": "system", "method": "remark" },
+			"signature": { "signer": { "id": "HNZata7iMYWmk5RvZRTiAsSDhV8366zq2YGb3tLH5Upf74F" } },
+			"args": { "remark": "0001020103039TWJOBACTIPVR" }
+		}]
+	}"#;
+	let path = format!("{}/scan-token.json", env!("CARGO_TARGET_TMPDIR"));
+	std::fs::write(&path, block).unwrap();
+
+	let envelope = Builder::build_with_variant("0001020103039TWJXBACTSPAQ").unwrap().to_envelope();
+	for token in &["0001-02-01-03039-twjxbactspaq", envelope.as_str()] {
+		let (code, found) = rt(&["scan", &path, "--token", token]);
+		assert_eq!((0, "0001020103039TWJXBACTSPAQ"), (code, found["token"].as_str().unwrap()));
+	}
+	let (code, found) = rt(&["scan", &path, "--token", "0001020103039TWJ0BACT1PVR"]);
+	assert_eq!((0, "0001020103039TWJOBACTIPVR"), (code, found["token"].as_str().unwrap()));
+
+	let output = Command::new(env!("CARGO_BIN_EXE_rt"))
+		.env("RRT_CONFIG", "/nonexistent/rrt.toml")
+		.args(["scan", &path, "--token", "0001020103039TWJXBACTSPAA"])
+		.output()
+		.unwrap();
+	assert_eq!((Some(4), true), (output.status.code(), output.stdout.is_empty()));
+}